@@ -0,0 +1,32 @@
+extern crate wayland_scanner;
+
+use std::env::var;
+use std::path::Path;
+use wayland_scanner::{generate_code, generate_interfaces, Side};
+
+// protocols not provided by wayland-protocols 0.12, or only in older versions
+static PROTOCOLS: &[&str] = &[
+    "blur",
+    "plasma-shell",
+    "single-pixel-buffer-v1",
+    "xdg-activation-v1",
+    "xdg-decoration-unstable-v1",
+    "xdg-output-unstable-v1",
+    "xdg-shell",
+];
+
+fn main() {
+    let out_dir_str = var("OUT_DIR").unwrap();
+    let out_dir = Path::new(&out_dir_str);
+
+    for name in PROTOCOLS {
+        let file = Path::new("./protocols").join(format!("{}.xml", name));
+        println!("cargo:rerun-if-changed={}", file.display());
+        generate_interfaces(&file, out_dir.join(format!("{}_interfaces.rs", name)));
+        generate_code(
+            &file,
+            out_dir.join(format!("{}_client_api.rs", name)),
+            Side::Client,
+        );
+    }
+}
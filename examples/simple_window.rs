@@ -2,7 +2,6 @@ extern crate byteorder;
 extern crate tempfile;
 #[macro_use]
 extern crate wayland_client;
-extern crate wayland_protocols;
 extern crate wayland_window;
 
 use byteorder::{NativeEndian, WriteBytesExt};
@@ -12,10 +11,8 @@ use std::io::{Seek, SeekFrom, Write};
 use std::os::unix::io::AsRawFd;
 use tempfile::tempfile;
 use wayland_client::{EnvHandler, Proxy, StateToken};
-use wayland_client::protocol::{wl_buffer, wl_compositor, wl_shell, wl_shm, wl_shm_pool, wl_subcompositor,
-                               wl_surface};
-use wayland_protocols::unstable::xdg_shell::v6::client::zxdg_shell_v6::{self, ZxdgShellV6};
-use wayland_window::create_frame;
+use wayland_client::protocol::{wl_buffer, wl_compositor, wl_shm, wl_shm_pool, wl_subcompositor, wl_surface};
+use wayland_window::{CloseDecision, FrameBuilder};
 
 wayland_env!(
     WaylandEnv,
@@ -48,10 +45,14 @@ fn window_implementation() -> wayland_window::FrameImplementation<StateToken<Win
         close: |evqh, token| {
             println!("close window");
             evqh.state().get_mut(token).closed = true;
+            CloseDecision::Close
         },
         refresh: |evqh, token| {
             evqh.state().get_mut(token).refresh = true;
         },
+        state_changed: |_, _, state, active| {
+            println!("state changed: {:?} {}", state, if active { "set" } else { "unset" });
+        },
     }
 }
 
@@ -118,41 +119,12 @@ fn main() {
     let env_token = EnvHandler::<WaylandEnv>::init(&mut event_queue, &registry);
     event_queue.sync_roundtrip().unwrap();
 
-    // Use `xdg-shell` if its available. Otherwise, fall back to `wl-shell`.
-    let (mut xdg_shell, mut wl_shell) = (None, None);
-    {
+    // Use the stable `xdg-shell` if its available, then the unstable one.
+    // Otherwise, fall back to `wl-shell`. The frames answer the pings of the shell.
+    let shell = {
         let state = event_queue.state();
         let env = state.get(&env_token);
-        for &(name, ref interface, version) in env.globals() {
-            if interface == ZxdgShellV6::interface_name() {
-                xdg_shell = Some(registry.bind::<ZxdgShellV6>(version, name));
-                break;
-            }
-        }
-
-        if xdg_shell.is_none() {
-            for &(name, ref interface, version) in env.globals() {
-                if interface == wl_shell::WlShell::interface_name() {
-                    wl_shell = Some(registry.bind::<wl_shell::WlShell>(version, name));
-                    break;
-                }
-            }
-        }
-    }
-
-    let shell = match (xdg_shell, wl_shell) {
-        (Some(shell), _) => {
-            // If using xdg-shell, we'll need to answer the pings.
-            let shell_implementation = zxdg_shell_v6::Implementation {
-                ping: |_, _, shell, serial| {
-                    shell.pong(serial);
-                },
-            };
-            event_queue.register(&shell, shell_implementation, ());
-            wayland_window::Shell::Xdg(shell)
-        }
-        (_, Some(shell)) => wayland_window::Shell::Wl(shell),
-        _ => panic!("No available shell"),
+        wayland_window::Shell::detect(&registry, env.globals()).expect("No available shell")
     };
 
     // get the env
@@ -174,24 +146,18 @@ fn main() {
         None
     });
 
-    let mut frame = create_frame(
-        &mut event_queue,
-        window_implementation(),
-        window_token.clone(),
-        &wl_surface,
-        16,
-        16,
-        &env.compositor,
-        &env.subcompositor,
-        &env.shm,
-        &shell,
-        seat,
-    ).unwrap();
-
-    frame.set_title("My example window".into());
-    frame.set_decorate(true);
-    frame.set_min_size(Some((10, 10)));
-    frame.refresh();
+    let mut builder = FrameBuilder::new(&wl_surface, window_implementation(), window_token.clone())
+        .globals(&env.compositor, Some(&env.subcompositor), &env.shm, &shell)
+        .size(16, 16)
+        .title("My example window")
+        .decorate(true)
+        .min_size(10, 10);
+    if let Some(seat) = seat {
+        builder = builder.seat(seat);
+    }
+    let mut frame = builder.build(&mut event_queue).unwrap();
+
+    frame.refresh(&mut event_queue);
 
     loop {
         display.flush().unwrap();
@@ -202,16 +168,18 @@ fn main() {
             if let Some((w, h)) = window.newsize.take() {
                 frame.resize(w, h);
                 window.resize(w, h);
-                frame.refresh();
-            } else if window.refresh {
-                frame.refresh();
+                window.refresh = true;
             }
-            window.refresh = false;
             !window.closed
         });
 
         if !keep_going {
             break;
         }
+
+        let refresh = ::std::mem::replace(&mut event_queue.state().get_mut(&window_token).refresh, false);
+        if refresh {
+            frame.refresh(&mut event_queue);
+        }
     }
 }
@@ -0,0 +1,29 @@
+use protocols::xdg_activation::v1::client::xdg_activation_token_v1;
+use std::env;
+use wayland_client::EventQueueHandle;
+
+/// Take the activation token given by the application that launched this one
+///
+/// Launchers pass it in the `XDG_ACTIVATION_TOKEN` environment variable, which is
+/// removed so that it does not leak to the child processes of this application. Give
+/// the token to `Frame::request_activation` once your window is created.
+pub fn activation_token_from_env() -> Option<String> {
+    let token = env::var("XDG_ACTIVATION_TOKEN").ok();
+    env::remove_var("XDG_ACTIVATION_TOKEN");
+    token.filter(|token| !token.is_empty())
+}
+
+pub(crate) struct TokenIData<ID> {
+    pub(crate) done: fn(&mut EventQueueHandle, &mut ID, String),
+    pub(crate) idata: ID,
+}
+
+pub(crate) fn token_implementation<ID>() -> xdg_activation_token_v1::Implementation<TokenIData<ID>> {
+    xdg_activation_token_v1::Implementation {
+        done: |evqh, idata, token_object, token| {
+            // the token stays valid once its object is destroyed
+            token_object.destroy();
+            (idata.done)(evqh, &mut idata.idata, token);
+        },
+    }
+}
@@ -0,0 +1,65 @@
+use FrameIData;
+use std::time::Instant;
+use wayland_client::{EventQueueHandle, Proxy};
+use wayland_client::protocol::{wl_callback, wl_surface};
+
+/// Duration of the hover fade of the buttons, in milliseconds
+pub(crate) const HOVER_FADE_DURATION: u32 = 150;
+
+/// Assumed duration of an output frame, used when no previous frame time is known
+const DEFAULT_FRAME_DURATION: u32 = 16;
+
+pub(crate) struct AnimationIData<ID> {
+    frame: FrameIData<ID>,
+    surface: wl_surface::WlSurface,
+}
+
+/// Schedule the next step of the running animations of the decorations
+///
+/// A frame callback is requested on the decoration surface, the animations
+/// are then advanced and the user asked for a refresh once the compositor
+/// signals it is a good time to draw a new frame.
+///
+/// Does nothing if no animation is running or if a frame callback is already
+/// pending.
+pub(crate) fn schedule<ID: 'static>(evqh: &mut EventQueueHandle, surface: &wl_surface::WlSurface,
+                                    frame: FrameIData<ID>) {
+    {
+        let mut meta = frame.meta.lock().unwrap();
+        if meta.frame_callback_pending || !meta.is_animating() {
+            return;
+        }
+        meta.frame_callback_pending = true;
+    }
+    let callback = surface.frame().expect("The frame surface cannot be destroyed.");
+    let surface = surface.clone().expect("The frame surface cannot be destroyed.");
+    evqh.register(
+        &callback,
+        frame_callback_implementation(),
+        AnimationIData { frame, surface },
+    );
+}
+
+fn frame_callback_implementation<ID: 'static>() -> wl_callback::Implementation<AnimationIData<ID>> {
+    wl_callback::Implementation {
+        done: |evqh, idata, _, time| {
+            {
+                let mut meta = idata.frame.meta.lock().unwrap();
+                meta.frame_callback_pending = false;
+                let elapsed = match meta.last_frame_time {
+                    Some(last) => time.wrapping_sub(last),
+                    None => DEFAULT_FRAME_DURATION,
+                };
+                meta.step_animations(elapsed);
+                if !meta.timers.external {
+                    meta.fire_timers(Instant::now());
+                }
+                meta.last_frame_time = if meta.is_animating() { Some(time) } else { None };
+                meta.need_redraw = true;
+            }
+            schedule(evqh, &idata.surface, idata.frame.clone());
+            let mut user_idata = idata.frame.idata.borrow_mut();
+            (idata.frame.implementation.refresh)(evqh, &mut *user_idata);
+        },
+    }
+}
@@ -0,0 +1,77 @@
+use Location;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wayland_client::EventQueueHandle;
+use wayland_client::protocol::wl_pointer;
+
+/// A scroll event of a pointer over the decorations, see `AxisImplementation`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AxisEvent {
+    /// Scroll along an axis, by a distance in the coordinates of the surface
+    Axis {
+        /// Timestamp of the event, in milliseconds
+        time: u32,
+        /// The axis scrolled along
+        axis: wl_pointer::Axis,
+        /// The distance scrolled, positive when scrolling down or right
+        value: f64,
+    },
+    /// Scroll along an axis by steps, like the notches of a wheel
+    ///
+    /// This is sent before the `Axis` event of the same axis in the same pointer frame.
+    Discrete {
+        /// The axis scrolled along
+        axis: wl_pointer::Axis,
+        /// The number of steps, positive when scrolling down or right
+        steps: i32,
+    },
+    /// The end of a group of events sent at once, like a diagonal scroll
+    ///
+    /// Only sent by compositors supporting version 5 of `wl_seat`.
+    Frame,
+}
+
+/// For handling the scrolls over the decorations, see `Frame::set_axis_handler`
+pub struct AxisImplementation<ID> {
+    /// Called for each scroll event of a pointer over the decorations
+    ///
+    /// `location` is the part of the decorations under the pointer. Return `true` to
+    /// keep the frame from handling the event itself: a vertical scroll over the
+    /// titlebar shades or unshades the window otherwise.
+    pub axis: fn(evqh: &mut EventQueueHandle, idata: &mut ID, location: Location, event: AxisEvent) -> bool,
+}
+
+impl<ID> Copy for AxisImplementation<ID> {}
+impl<ID> Clone for AxisImplementation<ID> {
+    fn clone(&self) -> AxisImplementation<ID> {
+        *self
+    }
+}
+
+/// An implementation with its implementation data, without its type
+pub(crate) trait AxisHandler {
+    fn axis(&mut self, evqh: &mut EventQueueHandle, location: Location, event: AxisEvent) -> bool;
+}
+
+impl<ID> AxisHandler for (AxisImplementation<ID>, ID) {
+    fn axis(&mut self, evqh: &mut EventQueueHandle, location: Location, event: AxisEvent) -> bool {
+        (self.0.axis)(evqh, &mut self.1, location, event)
+    }
+}
+
+// the handler in its own cell, so that it can replace itself
+type SharedHandler = Rc<RefCell<dyn AxisHandler>>;
+
+/// The axis handler of a frame, shared with the handlers of its pointers
+pub(crate) type AxisHandlerSlot = Rc<RefCell<Option<SharedHandler>>>;
+
+/// Give an event to the handler of a frame, returns whether it consumed it
+pub(crate) fn forward(slot: &AxisHandlerSlot, evqh: &mut EventQueueHandle, location: Location,
+                      event: AxisEvent)
+                      -> bool {
+    let handler = slot.borrow().clone();
+    match handler {
+        Some(handler) => handler.borrow_mut().axis(evqh, location, event),
+        None => false,
+    }
+}
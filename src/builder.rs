@@ -0,0 +1,251 @@
+use {DecorationManager, Frame, FrameEvent, FrameEventSender, FrameImplementation, Shell, ShellObjects};
+use std::sync::mpsc::Receiver;
+use wayland_client::EventQueueHandle;
+use wayland_client::protocol::*;
+
+/// A builder of decorated frames
+///
+/// The frame is created from the globals given to `globals`, or from a shared
+/// `DecorationManager` given to `manager`. All the other settings are optional, and
+/// applied before the initial commit of the window.
+///
+/// ```ignore
+/// use wayland_window::FrameBuilder;
+/// let frame = FrameBuilder::new(&my_surface, my_implementation, my_implementation_data)
+///     .globals(&compositor, Some(&subcompositor), &shm, &shell)
+///     .seat(seat)
+///     .size(width, height)
+///     .title("My window")
+///     .decorate(true)
+///     .build(&mut event_queue)
+///     .unwrap(); // creation can fail
+/// ```
+pub struct FrameBuilder<'a, ID> {
+    surface: &'a wl_surface::WlSurface,
+    implementation: FrameImplementation<ID>,
+    idata: ID,
+    globals: Option<Globals<'a>>,
+    shell_only: Option<(&'a wl_compositor::WlCompositor, &'a Shell)>,
+    manager: Option<&'a DecorationManager>,
+    seat: Option<wl_seat::WlSeat>,
+    cursor_theme: Option<(&'a str, u32)>,
+    size: (i32, i32),
+    decorate: bool,
+    title: Option<String>,
+    app_id: Option<String>,
+    min_size: Option<(i32, i32)>,
+    max_size: Option<(i32, i32)>,
+    contents_sync: bool,
+    adopted: Option<ShellObjects>,
+}
+
+type Globals<'a> = (
+    &'a wl_compositor::WlCompositor,
+    Option<&'a wl_subcompositor::WlSubcompositor>,
+    &'a wl_shm::WlShm,
+    &'a Shell,
+);
+
+impl<'a> FrameBuilder<'a, FrameEventSender> {
+    /// Start building a frame sending its events to a channel
+    ///
+    /// See `DecorationManager::create_frame_with_channel`.
+    pub fn with_channel(surface: &'a wl_surface::WlSurface)
+                        -> (FrameBuilder<'a, FrameEventSender>, Receiver<FrameEvent>) {
+        let (sender, receiver) = ::channel::channel();
+        let builder = FrameBuilder::new(surface, ::channel::channel_implementation(), sender);
+        (builder, receiver)
+    }
+}
+
+impl<'a, ID: 'static> FrameBuilder<'a, ID> {
+    /// Start building a frame for a wl_surface
+    ///
+    /// The implementation and its data are used as with `DecorationManager::create_frame`.
+    pub fn new(surface: &'a wl_surface::WlSurface, implementation: FrameImplementation<ID>, idata: ID)
+               -> FrameBuilder<'a, ID> {
+        FrameBuilder {
+            surface,
+            implementation,
+            idata,
+            globals: None,
+            shell_only: None,
+            manager: None,
+            seat: None,
+            cursor_theme: None,
+            size: (1, 1),
+            decorate: false,
+            title: None,
+            app_id: None,
+            min_size: None,
+            max_size: None,
+            contents_sync: false,
+            adopted: None,
+        }
+    }
+
+    /// Create the frame from these globals
+    ///
+    /// The frame then gets its own shm pool and cursor theme, see `manager` to share
+    /// them between several windows. Without a subcompositor the frame is never
+    /// decorated, see `DecorationManager::new`.
+    pub fn globals(mut self, compositor: &'a wl_compositor::WlCompositor,
+                   subcompositor: Option<&'a wl_subcompositor::WlSubcompositor>, shm: &'a wl_shm::WlShm,
+                   shell: &'a Shell)
+                   -> FrameBuilder<'a, ID> {
+        self.globals = Some((compositor, subcompositor, shm, shell));
+        self
+    }
+
+    /// Create a frame only managing its shell surface, rather than from globals
+    ///
+    /// The frame never draws decorations nor reacts to a seat, see
+    /// `DecorationManager::shell_only`. `seat` and `cursor_theme` are ignored.
+    pub fn shell_only(mut self, compositor: &'a wl_compositor::WlCompositor, shell: &'a Shell)
+                      -> FrameBuilder<'a, ID> {
+        self.shell_only = Some((compositor, shell));
+        self
+    }
+
+    /// Create the frame from a decoration manager, rather than from globals
+    ///
+    /// The seat and cursor theme of the manager are used, `seat` and `cursor_theme`
+    /// are ignored.
+    pub fn manager(mut self, manager: &'a DecorationManager) -> FrameBuilder<'a, ID> {
+        self.manager = Some(manager);
+        self
+    }
+
+    /// React to the pointer of this seat
+    ///
+    /// Without a seat, the window cannot be moved or resized by the user.
+    pub fn seat(mut self, seat: wl_seat::WlSeat) -> FrameBuilder<'a, ID> {
+        self.seat = Some(seat);
+        self
+    }
+
+    /// Name and size of the cursor theme used over the decorations
+    ///
+    /// By default, they are read from the `XCURSOR_THEME` and `XCURSOR_SIZE`
+    /// environment variables. Ignored without the `cursor_theme` feature.
+    pub fn cursor_theme(mut self, name: &'a str, size: u32) -> FrameBuilder<'a, ID> {
+        self.cursor_theme = Some((name, size));
+        self
+    }
+
+    /// Initial size of the contents, 1x1 by default
+    pub fn size(mut self, width: i32, height: i32) -> FrameBuilder<'a, ID> {
+        self.size = (width, height);
+        self
+    }
+
+    /// Whether the window is decorated, see `Frame::set_decorate`
+    ///
+    /// Windows are not decorated by default.
+    pub fn decorate(mut self, decorate: bool) -> FrameBuilder<'a, ID> {
+        self.decorate = decorate;
+        self
+    }
+
+    /// Title of the window, see `Frame::set_title`
+    pub fn title<S: Into<String>>(mut self, title: S) -> FrameBuilder<'a, ID> {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// App id of the window, see `Frame::set_app_id`
+    pub fn app_id<S: Into<String>>(mut self, app_id: S) -> FrameBuilder<'a, ID> {
+        self.app_id = Some(app_id.into());
+        self
+    }
+
+    /// Minimum size of the contents, see `Frame::set_min_size`
+    pub fn min_size(mut self, width: i32, height: i32) -> FrameBuilder<'a, ID> {
+        self.min_size = Some((width, height));
+        self
+    }
+
+    /// Maximum size of the contents, see `Frame::set_max_size`
+    pub fn max_size(mut self, width: i32, height: i32) -> FrameBuilder<'a, ID> {
+        self.max_size = Some((width, height));
+        self
+    }
+
+    /// Whether the contents are synchronized with the decorations, see
+    /// `Frame::set_contents_sync`
+    ///
+    /// They are desynchronized by default.
+    pub fn contents_sync(mut self, sync: bool) -> FrameBuilder<'a, ID> {
+        self.contents_sync = sync;
+        self
+    }
+
+    /// Adopt the shell objects the application already created for the surface
+    ///
+    /// The objects must come from the same shell as the frame, and the surface may
+    /// already have been committed and configured. The frame takes them over: it replaces
+    /// their implementation with its own, and destroys them when it is dropped. The
+    /// decorations are drawn in a subsurface placed below the surface, and the window
+    /// geometry is extended to them.
+    ///
+    /// Unlike with a new toplevel, the surface stays the window: commit it with
+    /// `Frame::commit`, so that the configures and the placement of the decorations are
+    /// applied with it. If it was not committed yet, the frame leaves the initial commit
+    /// to the application. Such windows cannot be shaded.
+    pub fn adopt(mut self, shell: ShellObjects) -> FrameBuilder<'a, ID> {
+        self.adopted = Some(shell);
+        self
+    }
+
+    /// Create the frame and declare it as a shell surface to the compositor
+    ///
+    /// This fails if none of `globals`, `shell_only` and `manager` were given, or if the adopted shell
+    /// objects do not come from their shell.
+    #[allow(clippy::result_unit_err)]
+    pub fn build(self, evqh: &mut EventQueueHandle) -> Result<Frame, ()> {
+        let FrameBuilder {
+            surface,
+            implementation,
+            idata,
+            globals,
+            shell_only,
+            manager,
+            seat,
+            cursor_theme,
+            size,
+            decorate,
+            title,
+            app_id,
+            min_size,
+            max_size,
+            contents_sync,
+            adopted,
+        } = self;
+        let owned_manager;
+        let manager = match (manager, globals, shell_only) {
+            (Some(manager), _, _) => manager,
+            (None, Some((compositor, subcompositor, shm, shell)), _) => {
+                owned_manager =
+                    DecorationManager::new(compositor, subcompositor, shm, shell, seat, cursor_theme)?;
+                &owned_manager
+            }
+            (None, None, Some((compositor, shell))) => {
+                owned_manager = DecorationManager::shell_only(compositor, shell)?;
+                &owned_manager
+            }
+            (None, None, None) => return Err(()),
+        };
+        manager.create_frame_with(evqh, implementation, idata, (surface, adopted), size, |frame| {
+            if let Some(title) = title {
+                frame.set_title(title);
+            }
+            if let Some(app_id) = app_id {
+                frame.set_app_id(app_id);
+            }
+            frame.set_min_size(min_size);
+            frame.set_max_size(max_size);
+            frame.set_decorate(decorate);
+            frame.set_contents_sync(contents_sync);
+        })
+    }
+}
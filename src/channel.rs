@@ -0,0 +1,50 @@
+use {CloseDecision, Configure, FrameImplementation, WindowState};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// An event of a frame, sent to a channel
+///
+/// These are the calls of `FrameImplementation` of frames created with a channel, see
+/// `DecorationManager::create_frame_with_channel`.
+#[derive(Debug, Clone)]
+pub enum FrameEvent {
+    /// The window was configured, see `FrameImplementation::configure`
+    Configure(Configure, Option<(i32, i32)>),
+    /// The user asked to close the window
+    ///
+    /// The window is kept open: drop its frame, or call `Frame::close`, to close it.
+    Close,
+    /// The frame wants to be refreshed
+    Refresh,
+    /// A state of the window was set or unset, see `FrameImplementation::state_changed`
+    StateChanged(WindowState, bool),
+}
+
+/// Implementation data of the frames sending their events to a channel
+pub struct FrameEventSender {
+    sender: Sender<FrameEvent>,
+}
+
+/// A channel receiving the events of a frame
+pub(crate) fn channel() -> (FrameEventSender, Receiver<FrameEvent>) {
+    let (sender, receiver) = mpsc::channel();
+    (FrameEventSender { sender }, receiver)
+}
+
+pub(crate) fn channel_implementation() -> FrameImplementation<FrameEventSender> {
+    FrameImplementation {
+        configure: |_, sender, cfg, newsize| {
+            // the receiver may be dropped, the events are then discarded
+            let _ = sender.sender.send(FrameEvent::Configure(cfg, newsize));
+        },
+        close: |_, sender| {
+            let _ = sender.sender.send(FrameEvent::Close);
+            CloseDecision::Ignore
+        },
+        refresh: |_, sender| {
+            let _ = sender.sender.send(FrameEvent::Refresh);
+        },
+        state_changed: |_, sender, state, active| {
+            let _ = sender.sender.send(FrameEvent::StateChanged(state, active));
+        },
+    }
+}
@@ -0,0 +1,120 @@
+#![allow(deprecated)]
+
+use {CloseDecision, DecorationManager, Frame, FrameImplementation, Shell};
+use shell::Configure;
+use std::ops::{Deref, DerefMut};
+use wayland_client::EventQueueHandle;
+use wayland_client::protocol::*;
+
+/// For handling the events of a `DecoratedSurface`
+///
+/// This is the implementation of the API predating `Frame`, the states of the window
+/// and the requests to refresh the decorations are not reported to it. It is deprecated,
+/// use a `FrameImplementation` instead.
+#[deprecated(note = "use `FrameImplementation` with `DecorationManager::create_frame`")]
+pub struct DecoratedSurfaceImplementation<ID> {
+    /// Called whenever the surface has been resized, see `FrameImplementation::configure`
+    pub configure:
+        fn(evqh: &mut EventQueueHandle, idata: &mut ID, cfg: Configure, newsize: Option<(i32, i32)>),
+    /// Called when the user asked to close the surface
+    pub close: fn(evqh: &mut EventQueueHandle, idata: &mut ID),
+}
+
+impl<ID> Copy for DecoratedSurfaceImplementation<ID> {}
+impl<ID> Clone for DecoratedSurfaceImplementation<ID> {
+    fn clone(&self) -> DecoratedSurfaceImplementation<ID> {
+        *self
+    }
+}
+
+/// A decorated surface, as created by `init_decorated_surface`
+///
+/// This is a `Frame` with its own `DecorationManager`, which it dereferences to: the
+/// methods of the older API that `Frame` kept, like `set_title`, `resize` or
+/// `set_state`, work unchanged. The decorations now react to the pointer, call
+/// `refresh` after each dispatch of the event queue to draw them again when needed.
+///
+/// This is deprecated, use a `Frame` created from a `DecorationManager` instead, see
+/// `into_frame` to migrate an existing one.
+#[deprecated(note = "use `Frame`, created from a `DecorationManager`")]
+pub struct DecoratedSurface {
+    // declared first, the frame must be dropped before its manager
+    frame: Frame,
+    manager: DecorationManager,
+}
+
+impl DecoratedSurface {
+    /// Set the class of the surface, now called its app id
+    pub fn set_class(&self, class: String) {
+        self.frame.set_app_id(class)
+    }
+
+    /// Unwrap the frame, to stop using this compatibility layer
+    ///
+    /// The manager of the frame is returned with it, keep it around for the frame to
+    /// follow the capabilities of the seat.
+    pub fn into_frame(self) -> (Frame, DecorationManager) {
+        (self.frame, self.manager)
+    }
+}
+
+impl Deref for DecoratedSurface {
+    type Target = Frame;
+
+    fn deref(&self) -> &Frame {
+        &self.frame
+    }
+}
+
+impl DerefMut for DecoratedSurface {
+    fn deref_mut(&mut self) -> &mut Frame {
+        &mut self.frame
+    }
+}
+
+fn compat_implementation<ID>() -> FrameImplementation<(DecoratedSurfaceImplementation<ID>, ID)> {
+    FrameImplementation {
+        configure: |evqh, &mut (implementation, ref mut idata), cfg, newsize| {
+            (implementation.configure)(evqh, idata, cfg, newsize)
+        },
+        // the surface is closed by the application, by dropping it
+        close: |evqh, &mut (implementation, ref mut idata)| {
+            (implementation.close)(evqh, idata);
+            CloseDecision::Ignore
+        },
+        // the decorations wait for the next call to `refresh`
+        refresh: |_, _| {},
+        state_changed: |_, _, _, _| {},
+    }
+}
+
+/// Create a decorated surface, the way of the API predating `Frame`
+///
+/// This creates a `DecorationManager` for the surface alone, with the cursor theme of the
+/// environment, then its frame, decorated if `decorate` is set.
+///
+/// This is deprecated, use `DecorationManager::create_frame` or a `FrameBuilder` instead.
+#[deprecated(note = "use `DecorationManager::create_frame` or `FrameBuilder`")]
+#[allow(clippy::too_many_arguments, clippy::result_unit_err)]
+pub fn init_decorated_surface<ID: 'static>(evqh: &mut EventQueueHandle,
+                                           implementation: DecoratedSurfaceImplementation<ID>, idata: ID,
+                                           surface: &wl_surface::WlSurface, width: i32, height: i32,
+                                           compositor: &wl_compositor::WlCompositor,
+                                           subcompositor: &wl_subcompositor::WlSubcompositor,
+                                           shm: &wl_shm::WlShm, shell: &Shell, seat: Option<wl_seat::WlSeat>,
+                                           decorate: bool)
+                                           -> Result<DecoratedSurface, ()> {
+    let manager = DecorationManager::new(compositor, Some(subcompositor), shm, shell, seat, None)?;
+    let frame = manager.create_frame_with(
+        evqh,
+        compat_implementation(),
+        (implementation, idata),
+        (surface, None),
+        (width, height),
+        |frame| frame.set_decorate(decorate),
+    )?;
+    Ok(DecoratedSurface {
+        frame,
+        manager,
+    })
+}
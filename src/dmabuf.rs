@@ -0,0 +1,292 @@
+use memmap::{MmapMut, MmapOptions};
+use std::cell::Cell;
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::rc::Rc;
+use wayland_client::{EventQueueHandle, Proxy};
+use wayland_client::protocol::{wl_buffer, wl_shm};
+use wayland_protocols::unstable::linux_dmabuf::v1::client::zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1;
+
+/// Modifier of the buffers whose pixels are laid out linearly, row after row
+pub const DRM_FORMAT_MOD_LINEAR: u64 = 0;
+
+/// Modifier of the buffers whose layout is implicitly agreed upon with the driver
+pub const DRM_FORMAT_MOD_INVALID: u64 = 0x00ff_ffff_ffff_ffff;
+
+// fourcc codes of the formats of the decorations, wl_shm uses other values for them
+const DRM_FORMAT_ARGB8888: u32 = 0x3432_5241;
+const DRM_FORMAT_XRGB8888: u32 = 0x3432_5258;
+
+// synchronization of the CPU accesses to a dmabuf, from linux/dma-buf.h
+const DMA_BUF_IOCTL_SYNC: ::libc::c_ulong = 0x4008_6200;
+const DMA_BUF_SYNC_RW: u64 = 3;
+const DMA_BUF_SYNC_START: u64 = 0;
+const DMA_BUF_SYNC_END: u64 = 4;
+
+/// A dmabuf allocated for the decorations, see `DmabufImplementation`
+pub struct Dmabuf {
+    /// The dmabuf, closed once its buffer is not needed anymore
+    pub fd: File,
+    /// Offset of the first pixel in the dmabuf, in bytes
+    pub offset: u32,
+    /// Length of a row of pixels, in bytes
+    pub stride: u32,
+    /// Format modifier of the buffer
+    pub modifier: u64,
+}
+
+/// For allocating the buffers of the decorations, see `DecorationManager::set_dmabuf_allocator`
+pub struct DmabufImplementation<ID> {
+    /// Allocate a buffer of given size and DRM fourcc format, for example with gbm
+    ///
+    /// The decorations are drawn on the CPU in a mapping of the dmabuf, its pixels must be
+    /// laid out linearly: use `DRM_FORMAT_MOD_LINEAR`, or `DRM_FORMAT_MOD_INVALID` if the
+    /// buffer is implicitly linear. Return `None` to use shared memory instead.
+    pub allocate: fn(idata: &mut ID, width: i32, height: i32, format: u32) -> Option<Dmabuf>,
+}
+
+impl<ID> Copy for DmabufImplementation<ID> {}
+impl<ID> Clone for DmabufImplementation<ID> {
+    fn clone(&self) -> DmabufImplementation<ID> {
+        *self
+    }
+}
+
+/// An implementation with its implementation data, without its type
+trait Allocator {
+    fn allocate(&mut self, width: i32, height: i32, format: u32) -> Option<Dmabuf>;
+}
+
+impl<ID> Allocator for (DmabufImplementation<ID>, ID) {
+    fn allocate(&mut self, width: i32, height: i32, format: u32) -> Option<Dmabuf> {
+        (self.0.allocate)(&mut self.1, width, height, format)
+    }
+}
+
+/// A dmabuf of the pool, kept until the compositor releases it
+struct Buffer {
+    buffer: wl_buffer::WlBuffer,
+    fd: File,
+    mmap: MmapMut,
+    offset: usize,
+    stride: usize,
+    dimensions: (i32, i32),
+    owner: usize,
+    // whether this is the last buffer drawn for its owner
+    latest: bool,
+    released: Rc<Cell<bool>>,
+}
+
+impl Buffer {
+    /// Copy the pixels of the buffer in `pixels`, without the padding of its rows
+    fn read(&mut self, pixels: &mut Vec<u8>) {
+        let (width, height) = (self.dimensions.0 as usize * 4, self.dimensions.1 as usize);
+        pixels.clear();
+        sync(&self.fd, DMA_BUF_SYNC_START);
+        for row in 0..height {
+            let start = self.offset + row * self.stride;
+            pixels.extend_from_slice(&self.mmap[start..start + width]);
+        }
+        sync(&self.fd, DMA_BUF_SYNC_END);
+    }
+
+    /// Let `draw` write the pixels of the buffer
+    ///
+    /// With `copied`, the buffer starts with the contents read in `pixels`, otherwise
+    /// with its current contents if `has_previous` is set. `pixels` is reused as scratch
+    /// memory for the buffers whose rows are padded.
+    fn draw<F>(&mut self, pixels: &mut Vec<u8>, copied: bool, has_previous: bool, draw: F)
+    where
+        F: FnOnce(&mut [u8], bool),
+    {
+        let (width, height) = (self.dimensions.0 as usize * 4, self.dimensions.1 as usize);
+        if self.stride == width {
+            sync(&self.fd, DMA_BUF_SYNC_START);
+            let target = &mut self.mmap[self.offset..self.offset + width * height];
+            if copied {
+                target.copy_from_slice(pixels);
+            }
+            draw(target, has_previous);
+            sync(&self.fd, DMA_BUF_SYNC_END);
+            return;
+        }
+        // the canvas has no padding, draw aside and copy the rows
+        if !copied && has_previous {
+            self.read(pixels);
+        } else if !copied {
+            pixels.clear();
+            pixels.resize(width * height, 0);
+        }
+        draw(pixels, has_previous);
+        sync(&self.fd, DMA_BUF_SYNC_START);
+        for (row, pixels) in pixels.chunks(width).enumerate() {
+            let start = self.offset + row * self.stride;
+            self.mmap[start..start + width].copy_from_slice(pixels);
+        }
+        sync(&self.fd, DMA_BUF_SYNC_END);
+    }
+}
+
+/// The dmabufs in which the decorations are drawn, instead of the shm pool
+///
+/// Buffers are handled like the ones of the shm pool: they are only drawn again once
+/// released by the compositor, and the last one of each owner is kept so that the next
+/// one can be drawn incrementally.
+pub(crate) struct DmabufPool {
+    dmabuf: ZwpLinuxDmabufV1,
+    allocator: Box<dyn Allocator>,
+    buffers: Vec<Buffer>,
+    // pixels copied between buffers, kept to avoid allocating them on each redraw
+    scratch: Vec<u8>,
+}
+
+impl DmabufPool {
+    /// Fails if the buffers cannot be created without a roundtrip, before version 2
+    pub(crate) fn new<ID: 'static>(dmabuf: &ZwpLinuxDmabufV1, implementation: DmabufImplementation<ID>,
+                                   idata: ID)
+                                   -> Result<DmabufPool, ()> {
+        if dmabuf.version() < 2 {
+            return Err(());
+        }
+        Ok(DmabufPool {
+            dmabuf: dmabuf.clone().ok_or(())?,
+            allocator: Box::new((implementation, idata)),
+            buffers: Vec::new(),
+            scratch: Vec::new(),
+        })
+    }
+
+    /// Get a buffer of given dimensions for `owner` like `Pool::buffer`, returns its index
+    ///
+    /// `draw` is given back if no dmabuf could be allocated.
+    pub(crate) fn buffer<F>(&mut self, evqh: &mut EventQueueHandle, owner: usize, width: i32, height: i32,
+                            format: wl_shm::Format, draw: F)
+                            -> Result<usize, F>
+    where
+        F: FnOnce(&mut [u8], bool),
+    {
+        let previous = self.buffers.iter().position(|b| b.owner == owner && b.latest);
+        let mut source = None;
+        if let Some(idx) = previous {
+            if self.buffers[idx].dimensions == (width, height) {
+                if self.buffers[idx].released.get() {
+                    // the compositor is done with the previous buffer, draw in place
+                    self.buffers[idx].released.set(false);
+                    self.buffers[idx].draw(&mut self.scratch, false, true, draw);
+                    return Ok(idx);
+                }
+                // the previous buffer is still busy, its contents are copied
+                source = Some(idx);
+            }
+            self.buffers[idx].latest = false;
+        }
+        if let Some(idx) = source {
+            self.buffers[idx].read(&mut self.scratch);
+        }
+
+        // a buffer the compositor is done with is drawn again rather than allocating one
+        let spare = self.buffers.iter().position(|b| {
+            b.owner == owner && !b.latest && b.released.get() && b.dimensions == (width, height)
+        });
+        if let Some(idx) = spare {
+            self.buffers[idx].released.set(false);
+            self.buffers[idx].latest = true;
+            self.buffers[idx].draw(&mut self.scratch, source.is_some(), source.is_some(), draw);
+            return Ok(idx);
+        }
+        self.forget_released();
+
+        let format = fourcc(format);
+        log_debug!("allocating a dmabuf of size {:?}, format {:#x}", (width, height), format);
+        let dmabuf = match self.allocator.allocate(width, height, format) {
+            Some(dmabuf) => dmabuf,
+            None => return Err(draw),
+        };
+        let len = dmabuf.offset as usize + dmabuf.stride as usize * height as usize;
+        let mmap = match unsafe { MmapOptions::new().len(len).map_mut(&dmabuf.fd) } {
+            Ok(mmap) => mmap,
+            Err(_) => {
+                log_debug!("the allocated dmabuf cannot be mapped");
+                return Err(draw);
+            }
+        };
+        let params = self.dmabuf
+            .create_params()
+            .expect("The dmabuf global cannot be destroyed.");
+        let (modifier_hi, modifier_lo) = ((dmabuf.modifier >> 32) as u32, dmabuf.modifier as u32);
+        params.add(dmabuf.fd.as_raw_fd(), 0, dmabuf.offset, dmabuf.stride, modifier_hi, modifier_lo);
+        let buffer = params
+            .create_immed(width, height, format, 0)
+            .expect("The buffer params cannot be destroyed.");
+        params.destroy();
+        let released = Rc::new(Cell::new(false));
+        evqh.register(&buffer, ::pool::buffer_implementation(), released.clone());
+
+        let mut buffer = Buffer {
+            buffer,
+            fd: dmabuf.fd,
+            mmap,
+            offset: dmabuf.offset as usize,
+            stride: dmabuf.stride as usize,
+            dimensions: (width, height),
+            owner,
+            latest: true,
+            released,
+        };
+        buffer.draw(&mut self.scratch, source.is_some(), source.is_some(), draw);
+        self.buffers.push(buffer);
+        Ok(self.buffers.len() - 1)
+    }
+
+    pub(crate) fn wl_buffer(&self, idx: usize) -> &wl_buffer::WlBuffer {
+        &self.buffers[idx].buffer
+    }
+
+    /// Forget about the buffers the compositor is done with, except the last
+    /// one of each owner
+    fn forget_released(&mut self) {
+        self.buffers.retain(|b| {
+            let forget = b.released.get() && !b.latest;
+            if forget {
+                b.buffer.destroy();
+            }
+            !forget
+        });
+    }
+
+    /// Destroy all the buffers of an owner
+    pub(crate) fn release_owner(&mut self, owner: usize) {
+        self.buffers.retain(|b| {
+            if b.owner == owner {
+                b.buffer.destroy();
+            }
+            b.owner != owner
+        });
+    }
+}
+
+impl Drop for DmabufPool {
+    fn drop(&mut self) {
+        for b in self.buffers.drain(..) {
+            b.buffer.destroy();
+        }
+    }
+}
+
+/// The DRM fourcc code of a format of the decorations
+fn fourcc(format: wl_shm::Format) -> u32 {
+    match format {
+        wl_shm::Format::Argb8888 => DRM_FORMAT_ARGB8888,
+        wl_shm::Format::Xrgb8888 => DRM_FORMAT_XRGB8888,
+        // the other codes of wl_shm are the DRM ones
+        format => format.to_raw(),
+    }
+}
+
+/// Start or end an access of the CPU to a dmabuf
+fn sync(fd: &File, flags: u64) {
+    let flags = flags | DMA_BUF_SYNC_RW;
+    unsafe {
+        ::libc::ioctl(fd.as_raw_fd(), DMA_BUF_IOCTL_SYNC, &flags as *const u64);
+    }
+}
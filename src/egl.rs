@@ -0,0 +1,122 @@
+use UIButton;
+use frame::FrameMetadata;
+use wayland_client::EventQueueHandle;
+use wayland_client::egl::WlEglSurface;
+
+/// For drawing the decorations of a frame on the GPU, see `Frame::set_egl_renderer`
+pub struct EglImplementation<ID> {
+    /// Draw the decorations in `window`, then swap its buffers
+    ///
+    /// `window` already has the size of the decorations. The frame surface is committed
+    /// by the swap, which applies the state set by the frame for this redraw, like the
+    /// acknowledged configure: always swap, even if nothing changed since the last call.
+    pub draw: fn(evqh: &mut EventQueueHandle, idata: &mut ID, window: &WlEglSurface, layout: &DecorationLayout),
+}
+
+impl<ID> Copy for EglImplementation<ID> {}
+impl<ID> Clone for EglImplementation<ID> {
+    fn clone(&self) -> EglImplementation<ID> {
+        *self
+    }
+}
+
+/// What to draw in the decorations of a frame
+///
+/// Rectangles are given as `(x, y, width, height)` in the coordinates of the frame
+/// surface.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecorationLayout {
+    /// Size of the frame surface
+    pub size: (i32, i32),
+    /// The rectangle covered by your contents, leave it transparent
+    pub contents: (i32, i32, i32, i32),
+    /// Whether the window is activated, the decorations of inactive windows are dimmed
+    pub activated: bool,
+    /// Whether the window is maximized
+    pub maximized: bool,
+    /// The visible buttons of the titlebar
+    pub buttons: Vec<TitlebarButton>,
+    /// The progress to show along the bottom of the titlebar, in percent
+    pub progress: Option<u8>,
+    /// The color tinting the decorations, see `Frame::set_accent_color`
+    pub accent_color: [u8; 4],
+    /// The title of the window, see `Frame::set_title`
+    pub title: Option<String>,
+}
+
+/// A button of the titlebar, see `DecorationLayout`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TitlebarButton {
+    /// The button
+    pub button: UIButton,
+    /// Its rectangle
+    pub rect: (i32, i32, i32, i32),
+    /// How much it is hovered, from 0 to 1 as the hover fades in
+    pub hover: f32,
+    /// Whether its action is available, it is greyed otherwise
+    pub enabled: bool,
+    /// Whether its state is on, like the pin of a window kept above
+    pub pressed: bool,
+    /// Whether it is focused by the keyboard navigation
+    pub focused: bool,
+}
+
+impl DecorationLayout {
+    pub(crate) fn new() -> DecorationLayout {
+        DecorationLayout {
+            size: (0, 0),
+            contents: (0, 0, 0, 0),
+            activated: false,
+            maximized: false,
+            buttons: Vec::with_capacity(UIButton::ALL.len()),
+            progress: None,
+            accent_color: [0; 4],
+            title: None,
+        }
+    }
+
+    /// Describe the decorations of given state, reusing the memory of the previous layout
+    pub(crate) fn update(&mut self, meta: &FrameMetadata, title: Option<&str>) {
+        let (left, top) = ::geometry::METRICS.contents_offset(&meta.config);
+        let (w, h) = meta.dimensions;
+        self.size = meta.config.add_borders(w, h);
+        self.contents = (left, top, w, h);
+        self.activated = meta.activated;
+        self.maximized = meta.maximized;
+        self.buttons.clear();
+        for &(button, rect) in ::theme::button_rects(meta).iter().flatten() {
+            self.buttons.push(TitlebarButton {
+                button,
+                rect,
+                hover: meta.hover_level(button),
+                enabled: button != UIButton::Maximize || meta.max_size.is_none(),
+                pressed: button == UIButton::Pin && meta.keep_above,
+                focused: meta.focused_button == Some(button),
+            });
+        }
+        self.progress = meta.progress;
+        self.accent_color = meta.accent_color;
+        if self.title.as_deref() != title {
+            self.title = title.map(str::to_owned);
+        }
+    }
+}
+
+/// The EGL window of a frame surface, with the implementation drawing in it
+pub(crate) struct EglRenderer {
+    pub(crate) window: WlEglSurface,
+    pub(crate) handler: Box<dyn EglHandler>,
+    // the layout given to the last draw, updated in place
+    pub(crate) layout: DecorationLayout,
+}
+
+/// An implementation with its implementation data, without its type
+pub(crate) trait EglHandler {
+    fn draw(&mut self, evqh: &mut EventQueueHandle, window: &WlEglSurface, layout: &DecorationLayout);
+}
+
+impl<ID> EglHandler for (EglImplementation<ID>, ID) {
+    fn draw(&mut self, evqh: &mut EventQueueHandle, window: &WlEglSurface, layout: &DecorationLayout) {
+        (self.0.draw)(evqh, &mut self.1, window, layout);
+    }
+}
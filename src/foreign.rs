@@ -0,0 +1,24 @@
+use wayland_client::EventQueueHandle;
+use wayland_protocols::unstable::xdg_foreign::v2::client::{zxdg_exported_v2, zxdg_imported_v2};
+
+pub(crate) struct HandleIData<ID> {
+    pub(crate) done: fn(&mut EventQueueHandle, &mut ID, String),
+    pub(crate) idata: ID,
+}
+
+pub(crate) fn exported_implementation<ID>() -> zxdg_exported_v2::Implementation<HandleIData<ID>> {
+    zxdg_exported_v2::Implementation {
+        handle: |evqh, idata, _, handle| {
+            (idata.done)(evqh, &mut idata.idata, handle);
+        },
+    }
+}
+
+pub(crate) fn imported_implementation() -> zxdg_imported_v2::Implementation<()> {
+    zxdg_imported_v2::Implementation {
+        destroyed: |_, _, imported| {
+            // the handle is invalid or its window is gone, the parent is already unset
+            imported.destroy();
+        },
+    }
+}
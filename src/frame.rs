@@ -1,12 +1,58 @@
-use Location;
-use shell;
-use std::fs::File;
-use std::io::{Seek, SeekFrom, Write};
-use std::os::unix::io::AsRawFd;
+use {Location, UIButton};
+use activation::{self, TokenIData};
+use axis::{AxisHandler, AxisHandlerSlot, AxisImplementation};
+#[cfg(feature = "egl")]
+use egl::{DecorationLayout, EglHandler, EglImplementation, EglRenderer};
+#[cfg(feature = "wayland-protocols")]
+use foreign::{self, HandleIData};
+use geometry::METRICS;
+use shell::{self, StateSet, WindowState};
+use pointer::InputDevice;
+use pool::Pool;
+#[cfg(feature = "wayland-protocols")]
+use presentation::PresentationFeedback;
+use region::Region;
+use resize_overlay::ResizeOverlay;
+use seat::FramePointer;
+use handle::{FrameHandle, PendingOps};
+use keyboard::KeyboardTarget;
+#[cfg(feature = "wayland-protocols")]
+use tablet::ToolTarget;
+#[cfg(feature = "cursor_theme")]
+use themed_pointer::ThemeSlot;
+use theme::{Damage, Scratch};
+use timer::{Timer, Timers, TOOLTIP_DELAY};
+use tooltip::Tooltip;
+use widget::{WidgetError, WidgetHandler, WidgetHandlers, WidgetId, WidgetImplementation, WidgetSide,
+             WidgetSlots, MAX_WIDGETS};
+use protocols::blur::client::org_kde_kwin_blur::OrgKdeKwinBlur;
+use protocols::blur::client::org_kde_kwin_blur_manager::OrgKdeKwinBlurManager;
+use protocols::plasma_shell::client::org_kde_plasma_surface::OrgKdePlasmaSurface;
+use protocols::xdg_activation::v1::client::xdg_activation_v1::XdgActivationV1;
+#[cfg(feature = "wayland-protocols")]
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
-use tempfile::tempfile;
-use wayland_client::Proxy;
+use std::time::{Duration, Instant};
+use wayland_client::{EventQueueHandle, Proxy};
+#[cfg(feature = "egl")]
+use wayland_client::egl::WlEglSurface;
 use wayland_client::protocol::*;
+#[cfg(feature = "wayland-protocols")]
+use wayland_protocols::presentation_time::client::wp_presentation::WpPresentation;
+#[cfg(feature = "wayland-protocols")]
+use wayland_protocols::unstable::xdg_foreign::v2::client::{zxdg_exported_v2::ZxdgExportedV2,
+                                                           zxdg_exporter_v2::ZxdgExporterV2,
+                                                           zxdg_imported_v2::ZxdgImportedV2,
+                                                           zxdg_importer_v2::ZxdgImporterV2};
+#[cfg(feature = "wayland-protocols")]
+use wayland_protocols::viewporter::client::wp_viewport;
+#[cfg(feature = "misc_protocols")]
+use wayland_protocols::misc::server_decoration::client::org_kde_kwin_server_decoration::{self as kde_decoration,
+                                                                                         OrgKdeKwinServerDecoration};
 
 #[derive(Copy, Clone)]
 pub(crate) struct FrameMetadata {
@@ -17,21 +63,346 @@ pub(crate) struct FrameMetadata {
     pub(crate) min_size: Option<(i32, i32)>,
     pub(crate) max_size: Option<(i32, i32)>,
     pub(crate) old_size: Option<(i32, i32)>,
+    // size of the contents to give back to the user once a wl_shell window is unmaximized
+    pub(crate) restore_size: Option<(i32, i32)>,
     pub(crate) activated: bool,
+    // the compositor does not show the window, see `Frame::is_suspended`
+    pub(crate) suspended: bool,
+    pub(crate) resizing: bool,
     pub(crate) ready: bool,
     pub(crate) need_redraw: bool,
     pub(crate) ptr_location: Location,
+    // configuration of the drawn decorations, without the borders of the tiled edges
+    pub(crate) config: FrameConfig,
+    // configuration requested by the user
+    pub(crate) user_config: FrameConfig,
+    pub(crate) tiled: TiledEdges,
+    // recommended maximum size of the window given by the shell
+    pub(crate) bounds: Option<(i32, i32)>,
+    pub(crate) capabilities: WmCapabilities,
+    // whether configure events are only acked when asked by the user
+    pub(crate) manual_ack: bool,
+    pub(crate) pending_serial: Option<u32>,
+    pub(crate) hover_levels: [f32; 4],
+    pub(crate) frame_callback_pending: bool,
+    // whether the compositor has yet to show the last commit of the decorations
+    pub(crate) paint_pending: bool,
+    pub(crate) last_frame_time: Option<u32>,
+    pub(crate) decoration_mode: DecorationMode,
+    // whether only the titlebar is shown, see `is_shaded`
+    pub(crate) shaded: bool,
+    // whether the compositor may not have seen yet that the window was unshaded
+    pub(crate) unshading: bool,
+    // button whose tooltip is shown
+    pub(crate) tooltip: Option<UIButton>,
+    // the window was closed, it is withdrawn and not shown again
+    pub(crate) closed: bool,
+    pub(crate) timers: Timers,
+    // button focused by the keyboard navigation, drawn with a focus ring
+    pub(crate) focused_button: Option<UIButton>,
+    // increments and base size the contents size is rounded to
+    pub(crate) resize_increments: Option<((i32, i32), (i32, i32))>,
+    // ratio between the width and height of the contents
+    pub(crate) aspect_ratio: Option<(u32, u32)>,
+    // whether the window is kept above the others, the pin button is then drawn pressed
+    pub(crate) keep_above: bool,
+    // whether the frame has a surface of its own to draw the decorations, which
+    // needs a subcompositor
+    pub(crate) drawable: bool,
+    // whether the contents can be hidden, they cannot when they are the toplevel
+    pub(crate) shadable: bool,
+    // side and width of the widgets of the titlebar
+    pub(crate) widgets: WidgetSlots,
+    // progress shown along the bottom of the titlebar, in percent
+    pub(crate) progress: Option<u8>,
+    // color tinting the decorations of the active window, as ARGB
+    pub(crate) accent_color: [u8; 4],
+}
+
+/// Edges of a window adjacent to other windows or to the screen border
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub(crate) struct TiledEdges {
+    pub(crate) left: bool,
+    pub(crate) top: bool,
+    pub(crate) right: bool,
+    pub(crate) bottom: bool,
 }
 
 impl FrameMetadata {
+    /// State of a new frame, undecorated
+    ///
+    /// `needs_readiness` tells if the shell configures the window before it can be
+    /// drawn, and `drawable` if the frame has a surface to draw the decorations.
+    pub(crate) fn new(dimensions: (i32, i32), needs_readiness: bool, drawable: bool) -> FrameMetadata {
+        FrameMetadata {
+            dimensions,
+            decorate: false,
+            fullscreen: false,
+            maximized: false,
+            min_size: None,
+            max_size: None,
+            old_size: None,
+            restore_size: None,
+            activated: true,
+            suspended: false,
+            resizing: false,
+            ready: !needs_readiness,
+            need_redraw: needs_readiness,
+            ptr_location: Location::None,
+            config: FrameConfig::default(),
+            user_config: FrameConfig::default(),
+            tiled: TiledEdges::default(),
+            bounds: None,
+            capabilities: WmCapabilities::default(),
+            manual_ack: false,
+            pending_serial: None,
+            hover_levels: [0.; 4],
+            frame_callback_pending: false,
+            paint_pending: false,
+            last_frame_time: None,
+            decoration_mode: DecorationMode::Client,
+            shaded: false,
+            unshading: false,
+            tooltip: None,
+            closed: false,
+            timers: Timers::default(),
+            focused_button: None,
+            resize_increments: None,
+            aspect_ratio: None,
+            keep_above: false,
+            drawable,
+            shadable: true,
+            widgets: [None; MAX_WIDGETS],
+            progress: None,
+            accent_color: [0; 4],
+        }
+    }
+
+    pub(crate) fn set_config(&mut self, config: FrameConfig) {
+        self.user_config = config;
+        self.update_config();
+    }
+
+    pub(crate) fn set_tiled(&mut self, tiled: TiledEdges) {
+        self.tiled = tiled;
+        self.update_config();
+    }
+
+    pub(crate) fn set_capabilities(&mut self, capabilities: WmCapabilities) {
+        self.capabilities = capabilities;
+        self.update_config();
+    }
+
+    fn update_config(&mut self) {
+        let mut config = self.user_config;
+        // there is nothing to resize on tiled edges, their borders are not drawn
+        config.left_border &= !self.tiled.left;
+        config.right_border &= !self.tiled.right;
+        config.bottom_border &= !self.tiled.bottom;
+        // nor buttons for actions the compositor does not support
+        config.buttons.minimize &= self.capabilities.minimize;
+        config.buttons.maximize &= self.capabilities.maximize;
+        config.buttons.pin &= self.capabilities.keep_above;
+        self.config = config;
+    }
+
+    /// Whether this crate draws the decorations
+    pub(crate) fn decorated(&self) -> bool {
+        self.decorate && self.decoration_mode == DecorationMode::Client && self.drawable
+    }
+
+    /// Whether the borders are currently drawn, they are hidden in fullscreen
+    pub(crate) fn draws_borders(&self) -> bool {
+        self.decorated() && !self.fullscreen
+    }
+
+    /// Whether only the titlebar is shown, which needs the borders to be drawn
+    pub(crate) fn is_shaded(&self) -> bool {
+        self.shaded && self.draws_borders()
+    }
+
+    /// Forget the state of the decorations once they were shown or hidden
+    pub(crate) fn decorations_toggled(&mut self) {
+        self.set_ptr_location(Location::None);
+        self.focused_button = None;
+        self.need_redraw = true;
+    }
+
+    /// Shade or unshade the window
+    pub(crate) fn set_shaded(&mut self, shaded: bool) {
+        let shaded = shaded && self.shadable;
+        if self.shaded != shaded {
+            self.unshading = !shaded;
+            self.shaded = shaded;
+            self.need_redraw = true;
+        }
+    }
+
+    /// Apply a configure of an xdg toplevel, returns the size of the contents for the user
+    ///
+    /// `size` is the size of the whole window, `(0, 0)` if we get to choose it.
+    pub(crate) fn toplevel_configure(&mut self, (width, height): (i32, i32), states: StateSet)
+                                     -> Option<(i32, i32)> {
+        let has = |state: WindowState| states.contains(state);
+        // the borders depend on the tiled edges and fullscreen state
+        self.set_tiled(TiledEdges {
+            left: has(WindowState::TiledLeft),
+            top: has(WindowState::TiledTop),
+            right: has(WindowState::TiledRight),
+            bottom: has(WindowState::TiledBottom),
+        });
+        self.fullscreen = has(WindowState::Fullscreen);
+        // the sizes are only rounded up during resizes
+        self.resizing = has(WindowState::Resizing);
+        let mut newsize = if width == 0 || height == 0 {
+            // if either w or h is zero, then we get to choose our size
+            None
+        } else {
+            Some(self.clamp_to_limits((width, height)))
+        };
+        self.need_redraw = true;
+        self.activated = has(WindowState::Activated);
+        self.suspended = has(WindowState::Suspended);
+        match (has(WindowState::Maximized), self.maximized) {
+            (false, true) => {
+                // we got de-maximized
+                self.maximized = false;
+                if newsize.is_none() {
+                    newsize = self.old_size;
+                }
+                self.old_size = None;
+            }
+            (true, false) => {
+                // we are being maximized, which unshades the window
+                self.maximized = true;
+                self.set_shaded(false);
+                self.old_size = Some(self.dimensions);
+            }
+            _ => { /* nothing changed */ }
+        }
+        self.shaded_size(newsize, height)
+    }
+
+    /// Apply a configure of a wl_shell surface, returns the size of the contents for the user
+    ///
+    /// `size` is the size of the whole surface, borders included.
+    pub(crate) fn wl_configure(&mut self, (width, height): (i32, i32)) -> Option<(i32, i32)> {
+        self.need_redraw = true;
+        if !self.maximized {
+            // the compositor chose the size of the unmaximized window itself
+            self.restore_size = None;
+        }
+        let newsize = self.clamp_to_limits((width, height));
+        self.shaded_size(Some(newsize), height)
+    }
+
+    /// Follow a maximize or unmaximize requested on a wl_shell surface
+    ///
+    /// wl_shell does not report the state of the window, and does not configure it once
+    /// unmaximized: the size of the contents from before maximizing is kept, to be given
+    /// back to the user, see `restore_size`.
+    pub(crate) fn wl_set_maximized(&mut self, maximized: bool) {
+        match (maximized, self.maximized) {
+            (false, true) => {
+                self.maximized = false;
+                self.restore_size = self.old_size.take();
+            }
+            (true, false) => {
+                self.maximized = true;
+                self.set_shaded(false);
+                self.old_size = Some(self.dimensions);
+                self.restore_size = None;
+            }
+            _ => { /* nothing changed */ }
+        }
+        self.need_redraw = true;
+    }
+
+    /// Follow a state requested on a wl_shell surface, see `Frame::set_state`
+    ///
+    /// Returns whether wl_shell applies it: it cannot minimize the windows, which are
+    /// then left maximized or fullscreen if they were.
+    pub(crate) fn wl_set_state(&mut self, state: &State) -> bool {
+        let (maximized, fullscreen) = match *state {
+            State::Minimized => return false,
+            State::Regular => (false, false),
+            State::Maximized => (true, false),
+            State::Fullscreen(_) => (self.maximized, true),
+        };
+        self.wl_set_maximized(maximized);
+        self.fullscreen = fullscreen;
+        self.need_redraw = true;
+        true
+    }
+
+    /// Size of the contents for a configure of the toplevel
+    ///
+    /// Only the width applies to the hidden contents of shaded windows. Configures
+    /// following the unshading of the window may still have the height of the titlebar,
+    /// the previous height of the contents is then restored.
+    pub(crate) fn shaded_size(&mut self, newsize: Option<(i32, i32)>, height: i32) -> Option<(i32, i32)> {
+        let titlebar = self.config.border_sizes().top;
+        let unshading = ::std::mem::replace(&mut self.unshading, false);
+        if self.is_shaded() || (unshading && height > 0 && height <= titlebar) {
+            newsize.map(|(w, _)| (w, self.dimensions.1))
+        } else {
+            newsize
+        }
+    }
+
+    /// State of the decorations as they are drawn, only the titlebar of shaded windows
+    pub(crate) fn drawn(&self) -> FrameMetadata {
+        let mut drawn = *self;
+        if self.is_shaded() {
+            drawn.dimensions.1 = 0;
+            drawn.config.bottom_border = false;
+        }
+        drawn
+    }
+
+    /// Sizes of the borders drawn around the contents, see `Frame::border_sizes`
+    pub(crate) fn border_sizes(&self) -> BorderSizes {
+        if self.draws_borders() {
+            self.drawn().config.border_sizes()
+        } else {
+            BorderSizes::default()
+        }
+    }
+
+    /// Size of the window given to the shell for given contents size
+    pub(crate) fn outer_size(&self, size: (i32, i32)) -> (i32, i32) {
+        if self.decorated() {
+            self.config.add_borders(size.0, size.1)
+        } else {
+            size
+        }
+    }
+
+    /// Size of the contents for given window size
+    pub(crate) fn inner_size(&self, size: (i32, i32)) -> (i32, i32) {
+        if self.draws_borders() {
+            self.config.subtract_borders(size.0, size.1)
+        } else {
+            size
+        }
+    }
+
+    /// Recommended maximum size of the contents
+    pub(crate) fn inner_bounds(&self) -> Option<(i32, i32)> {
+        self.bounds.map(|size| self.inner_size(size))
+    }
+
+    /// Size of the contents for a size suggested by the shell, `size` being the size of
+    /// the whole window
+    ///
+    /// The contents are kept at least one pixel large when the suggested size is smaller
+    /// than the borders.
     pub(crate) fn clamp_to_limits(&self, size: (i32, i32)) -> (i32, i32) {
         use std::cmp::{max, min};
-        let (mut w, mut h) = size;
-        if self.decorate {
-            let (ww, hh) = ::subtract_borders(w, h);
-            w = ww;
-            h = hh;
-        }
+        let (w, h) = self.inner_size(size);
+        // an increment step from a zero base size can round down to 0
+        let (w, h) = self.round_to_increments((max(w, 1), max(h, 1)));
+        let (mut w, mut h) = self.match_aspect_ratio((max(w, 1), max(h, 1)));
         if let Some((minw, minh)) = self.min_size {
             w = max(minw, w);
             h = max(minh, h);
@@ -42,6 +413,115 @@ impl FrameMetadata {
         }
         (w, h)
     }
+
+    /// Round a contents size to the resize increments
+    ///
+    /// The size is rounded to the nearest increment during interactive resizes, and down
+    /// otherwise so that the window fits in the size suggested by the compositor.
+    fn round_to_increments(&self, (w, h): (i32, i32)) -> (i32, i32) {
+        let ((inc_w, inc_h), (base_w, base_h)) = match self.resize_increments {
+            Some(increments) => increments,
+            None => return (w, h),
+        };
+        let resizing = self.resizing;
+        let round = |size: i32, inc: i32, base: i32| {
+            if inc <= 1 || size <= base {
+                return size;
+            }
+            let steps = (size - base) as f64 / inc as f64;
+            let steps = if resizing { steps.round() } else { steps.floor() };
+            base + steps as i32 * inc
+        };
+        (round(w, inc_w, base_w), round(h, inc_h, base_h))
+    }
+
+    /// Adjust a contents size to the aspect ratio
+    ///
+    /// During interactive resizes, the closest of the sizes keeping either the width or the
+    /// height is used. Otherwise, the size is shrunk to fit in the size chosen by the compositor.
+    /// A ratio with a zero side is ignored, and the adjusted side is kept at least one pixel large.
+    fn match_aspect_ratio(&self, (w, h): (i32, i32)) -> (i32, i32) {
+        let (ratio_w, ratio_h) = match self.aspect_ratio {
+            Some((ratio_w, ratio_h)) if ratio_w > 0 && ratio_h > 0 => (ratio_w as f64, ratio_h as f64),
+            _ => return (w, h),
+        };
+        let keep_width = (w, ((w as f64 * ratio_h / ratio_w).round() as i32).max(1));
+        let keep_height = (((h as f64 * ratio_w / ratio_h).round() as i32).max(1), h);
+        let use_width = if self.resizing {
+            (keep_width.1 - h).abs() <= (keep_height.0 - w).abs()
+        } else {
+            keep_width.1 <= h
+        };
+        if use_width {
+            keep_width
+        } else {
+            keep_height
+        }
+    }
+
+    /// Hover level a button is fading towards, 1 when hovered, 0 otherwise
+    fn hover_target(&self, button: UIButton) -> f32 {
+        if self.ptr_location == Location::Button(button) {
+            1.
+        } else {
+            0.
+        }
+    }
+
+    /// Current hover level of a button, between 0 and 1
+    pub(crate) fn hover_level(&self, button: UIButton) -> f32 {
+        if self.config.animates_hover() {
+            self.hover_levels[button as usize]
+        } else {
+            self.hover_target(button)
+        }
+    }
+
+    pub(crate) fn is_animating(&self) -> bool {
+        let fading = self.config.animates_hover()
+            && UIButton::ALL
+                .iter()
+                .any(|&b| self.hover_levels[b as usize] != self.hover_target(b));
+        fading || self.timers.is_polled()
+    }
+
+    /// Update the location of the pointer, hiding the tooltip if it left the button
+    pub(crate) fn set_ptr_location(&mut self, location: Location) {
+        if location != self.ptr_location {
+            if self.tooltip.take().is_some() {
+                self.need_redraw = true;
+            }
+            match location {
+                Location::Button(_) => self.timers.start(Timer::Tooltip, TOOLTIP_DELAY),
+                _ => self.timers.cancel(Timer::Tooltip),
+            }
+        }
+        self.ptr_location = location;
+    }
+
+    /// Fire the timers due at `now`
+    pub(crate) fn fire_timers(&mut self, now: Instant) {
+        if self.timers.fire(Timer::Tooltip, now) {
+            if let Location::Button(button) = self.ptr_location {
+                self.tooltip = Some(button);
+                self.need_redraw = true;
+            }
+        }
+    }
+
+    /// Advance the animations by `elapsed` milliseconds
+    pub(crate) fn step_animations(&mut self, elapsed: u32) {
+        let step = elapsed as f32 / ::animation::HOVER_FADE_DURATION as f32;
+        for &button in &UIButton::ALL {
+            let target = self.hover_target(button);
+            let level = &mut self.hover_levels[button as usize];
+            *level = if *level < target {
+                (*level + step).min(target)
+            } else {
+                (*level - step).max(target)
+            };
+        }
+    }
 }
 
 /// A decorated frame for a window
@@ -55,14 +535,442 @@ impl FrameMetadata {
 /// Dropping it will remove your window and unmap your wl_surface.
 pub struct Frame {
     pub(crate) surface: wl_surface::WlSurface,
-    contents: wl_subsurface::WlSubsurface,
+    // the subsurface of the contents, destroyed to hide them while shaded
+    contents: Option<wl_subsurface::WlSubsurface>,
+    // whether the contents are synchronized with the decorations
+    sync_contents: bool,
+    // the subsurface placing the decorations below an adopted toplevel
+    decorations: Option<wl_subsurface::WlSubsurface>,
+    // whether the shell objects belong to the surface of the user, see `Toplevel::Adopted`
+    pub(crate) adopted: bool,
+    pub(crate) user_surface: wl_surface::WlSurface,
+    compositor: wl_compositor::WlCompositor,
+    subcompositor: Option<wl_subcompositor::WlSubcompositor>,
     pub(crate) shell_surface: shell::Surface,
-    buffer: Option<wl_buffer::WlBuffer>,
-    tempfile: File,
-    pool: wl_shm_pool::WlShmPool,
-    pub(crate) pointer: Option<wl_pointer::WlPointer>,
+    pool: Option<Rc<RefCell<Pool>>>,
+    pool_owner: usize,
+    // state of the decorations in the last drawn buffer
+    last_drawn: Option<FrameMetadata>,
+    pub(crate) pointer: Option<Rc<RefCell<FramePointer>>>,
+    // the frame as seen by the tablet tools, kept alive by the frame
+    #[cfg(feature = "wayland-protocols")]
+    pub(crate) tool_target: Option<Rc<RefCell<dyn ToolTarget>>>,
+    pub(crate) keyboard_target: Option<Rc<RefCell<dyn KeyboardTarget>>>,
+    #[cfg(feature = "wayland-protocols")]
+    pub(crate) viewport: Option<wp_viewport::WpViewport>,
+    // whether the decorations are currently stretched by the viewport
+    #[cfg(feature = "wayland-protocols")]
+    stretched: bool,
+    // position of the contents relative to the decorations
+    pub(crate) contents_offset: (i32, i32),
+    // window geometry last given to the shell
+    geometry: Option<(i32, i32, i32, i32)>,
+    tooltip: Option<Tooltip>,
+    resize_overlay: Option<ResizeOverlay>,
+    // buffers reused by the drawings of the decorations
+    scratch: RefCell<Scratch>,
+    // the widgets of the titlebar, drawn and clicked by the user
+    pub(crate) widgets: WidgetHandlers,
+    // the area of the surface of the user moving the window, shared with the pointers
+    pub(crate) drag_region: Rc<RefCell<Region>>,
+    // handles the scrolls over the decorations, shared with the pointers
+    pub(crate) axis_handler: AxisHandlerSlot,
+    // the cursor theme of the pointers, if they are themed
+    #[cfg(feature = "cursor_theme")]
+    pub(crate) cursor_theme: Option<ThemeSlot>,
+    // draws the decorations on the GPU instead of in the buffers of the pool
+    #[cfg(feature = "egl")]
+    egl: Option<EglRenderer>,
+    #[cfg(feature = "misc_protocols")]
+    pub(crate) kde_decoration: Option<OrgKdeKwinServerDecoration>,
+    pub(crate) activation: Option<XdgActivationV1>,
+    #[cfg(feature = "wayland-protocols")]
+    pub(crate) xdg_exporter: Option<ZxdgExporterV2>,
+    #[cfg(feature = "wayland-protocols")]
+    pub(crate) xdg_importer: Option<ZxdgImporterV2>,
+    // the window as exported to other applications, revoked once destroyed
+    #[cfg(feature = "wayland-protocols")]
+    exported: Option<ZxdgExportedV2>,
+    // the window of another application set as parent
+    #[cfg(feature = "wayland-protocols")]
+    foreign_parent: Option<ZxdgImportedV2>,
+    pub(crate) plasma_surface: Option<OrgKdePlasmaSurface>,
+    pub(crate) blur_manager: Option<OrgKdeKwinBlurManager>,
+    blur: Option<OrgKdeKwinBlur>,
+    // area last given to the blur, `Some(None)` when nothing is blurred
+    blurred: Option<Option<DecorationArea>>,
+    // area whose borders were last given as the opaque region, `None` if it is unset
+    opaque: Option<DecorationArea>,
+    #[cfg(feature = "wayland-protocols")]
+    pub(crate) presentation: Option<WpPresentation>,
+    // asks the user to refresh the frame, once a throttled redraw can be done
+    pub(crate) refresh_callback: Option<RefreshCallback>,
+    // gives the size from before maximizing back to the user, with wl_shell
+    pub(crate) restore_callback: Option<RefreshCallback>,
+    // feedback of the last presented redraw of the decorations
+    #[cfg(feature = "wayland-protocols")]
+    last_presentation: Rc<Cell<Option<PresentationFeedback>>>,
+    // outputs the window is displayed on, the last entered one first
+    pub(crate) outputs: Rc<RefCell<Vec<wl_output::WlOutput>>>,
+    // operations queued by the handles of the frame
+    pending: Arc<Mutex<PendingOps>>,
     pub(crate) meta: Arc<Mutex<FrameMetadata>>,
-    buffer_capacity: i32,
+    // the surface and shell objects were given to the user by `into_undecorated`
+    detached: bool,
+    // the window is unmapped by `hide`
+    hidden: bool,
+    // sent again to the shell when the window is shown, which forgets them on unmap
+    title: RefCell<Option<String>>,
+    app_id: RefCell<Option<String>>,
+}
+
+// the decorations as the window size and the rectangle of the contents, whose borders
+// are given to the compositor as regions
+type DecorationArea = ((i32, i32), (i32, i32, i32, i32));
+
+/// A window whose decorations were removed by `Frame::into_undecorated`
+///
+/// The shell objects still send their events to the implementation of the frame, the
+/// configures then give the size of the whole surface and are acknowledged as soon as
+/// they are received.
+pub struct UndecoratedWindow {
+    /// The toplevel surface of the window, draw your contents and decorations in it
+    ///
+    /// This is the surface of the decorations, or your surface if the frame had no
+    /// subcompositor. Destroy it after the shell objects.
+    pub surface: wl_surface::WlSurface,
+    /// The shell objects giving its role to `surface`
+    pub shell: shell::ShellObjects,
+}
+
+/// Delay between two clicks for them to be a double-click, in milliseconds
+const DEFAULT_DOUBLE_CLICK_INTERVAL: u32 = 400;
+
+/// Distance the pointer has to travel with the button held before the window is moved
+const DEFAULT_DRAG_THRESHOLD: u32 = 4;
+
+/// How far the resize zones extend for the tablet tools
+const DEFAULT_TABLET_GRAB_MARGIN: u32 = 8;
+
+/// How far the resize zones extend for the touch, about the width of a finger with the
+/// borders, leaving the lower part of the titlebar to move the window
+const DEFAULT_TOUCH_GRAB_MARGIN: u32 = 12;
+
+/// Configuration of the decorations drawn around a frame
+///
+/// The titlebar is always drawn at the top of the window, the other
+/// borders can be individually disabled. The default configuration
+/// draws all borders.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FrameConfig {
+    /// Whether the border on the left side of the window is drawn
+    pub left_border: bool,
+    /// Whether the border on the right side of the window is drawn
+    pub right_border: bool,
+    /// Whether the border at the bottom of the window is drawn
+    pub bottom_border: bool,
+    /// Buttons displayed in the titlebar
+    pub buttons: ButtonSet,
+    /// Placement and ordering of the buttons in the titlebar
+    pub button_layout: ButtonLayout,
+    /// Whether the buttons fade in and out when hovered
+    ///
+    /// Disable it to instantly switch colors instead, for users
+    /// who prefer reduced motion.
+    pub hover_animations: bool,
+    /// How the decorations react to the pointer
+    pub pointer: PointerConfig,
+    /// Accessibility options, applied whatever the other options
+    pub accessibility: FrameAccessibility,
+    /// Whether the size of the contents is shown during interactive resizes
+    ///
+    /// It is shown in the bottom-right corner of the window, in pixels, or in increments
+    /// if the window has some, see `Frame::set_resize_increments`.
+    pub resize_overlay: bool,
+}
+
+/// How the decorations react to the pointer, see `FrameConfig`
+///
+/// Applications can set these from the input settings of the desktop, like the
+/// `org.gnome.desktop.wm.preferences` ones.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PointerConfig {
+    /// Longest delay between two clicks on the titlebar for them to toggle the
+    /// maximized state, in milliseconds
+    ///
+    /// `None` disables maximizing on double-clicks.
+    pub double_click_interval: Option<u32>,
+    /// What a middle click on the titlebar does
+    pub titlebar_middle_action: TitlebarAction,
+    /// What a right click on the titlebar does
+    pub titlebar_right_action: TitlebarAction,
+    /// Distance the pointer has to travel with the left button held on the titlebar, or
+    /// on the drag region of the contents, before the window is moved, in pixels
+    pub drag_threshold: u32,
+    /// How far the resize zones extend for the tablet tools, in pixels
+    ///
+    /// A pen is harder to place precisely than a mouse: its resize zones are enlarged
+    /// over the decorations, the corners along the titlebar and the bottom border, and
+    /// the top edge down the titlebar, around its buttons.
+    pub tablet_grab_margin: u32,
+    /// How far the resize zones extend for the touch, in pixels
+    ///
+    /// Like `tablet_grab_margin`, for a finger, which is even less precise.
+    pub touch_grab_margin: u32,
+}
+
+impl Default for PointerConfig {
+    fn default() -> PointerConfig {
+        PointerConfig {
+            double_click_interval: Some(DEFAULT_DOUBLE_CLICK_INTERVAL),
+            titlebar_middle_action: TitlebarAction::None,
+            titlebar_right_action: TitlebarAction::Menu,
+            drag_threshold: DEFAULT_DRAG_THRESHOLD,
+            tablet_grab_margin: DEFAULT_TABLET_GRAB_MARGIN,
+            touch_grab_margin: DEFAULT_TOUCH_GRAB_MARGIN,
+        }
+    }
+}
+
+impl PointerConfig {
+    /// How far the resize zones extend for given device, in pixels
+    pub(crate) fn grab_margin(&self, device: InputDevice) -> i32 {
+        match device {
+            InputDevice::Mouse => 0,
+            InputDevice::Tablet => self.tablet_grab_margin as i32,
+            InputDevice::Touch => self.touch_grab_margin as i32,
+        }
+    }
+}
+
+/// An action triggered by clicking the titlebar, see `PointerConfig`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TitlebarAction {
+    /// Nothing happens
+    None,
+    /// Show the window menu, the one of the compositor if it has one
+    Menu,
+    /// Maximize or unmaximize the window
+    ToggleMaximize,
+    /// Minimize the window
+    Minimize,
+    /// Shade or unshade the window, see `Frame::set_shaded`
+    ToggleShade,
+}
+
+/// Accessibility options of the decorations, see `FrameConfig`
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct FrameAccessibility {
+    /// Whether all the animations of the decorations are disabled
+    ///
+    /// This takes precedence over `FrameConfig::hover_animations`.
+    pub reduced_motion: bool,
+    /// How far the clickable area of the buttons extends beyond their drawn size, in pixels
+    ///
+    /// The area extends on their sides and below them, but not over the top border
+    /// which resizes the window. Between two buttons, the nearest one is clicked.
+    pub button_hit_margin: u32,
+}
+
+/// Sizes of the borders drawn around the contents of a window, in pixels
+///
+/// The titlebar is the top border.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct BorderSizes {
+    /// Height of the titlebar
+    pub top: i32,
+    /// Height of the bottom border
+    pub bottom: i32,
+    /// Width of the left border
+    pub left: i32,
+    /// Width of the right border
+    pub right: i32,
+}
+
+/// Set of buttons displayed in the titlebar
+///
+/// Hidden buttons are neither drawn nor clickable, the remaining
+/// ones are packed together on the right of the titlebar.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ButtonSet {
+    /// Whether the minimize button is displayed
+    pub minimize: bool,
+    /// Whether the maximize button is displayed
+    pub maximize: bool,
+    /// Whether the close button is displayed
+    pub close: bool,
+    /// Whether the pin button is displayed, see `Frame::set_keep_above`
+    ///
+    /// It is hidden by default, and when the compositor cannot keep windows above.
+    pub pin: bool,
+}
+
+impl Default for ButtonSet {
+    fn default() -> ButtonSet {
+        ButtonSet {
+            minimize: true,
+            maximize: true,
+            close: true,
+            pin: false,
+        }
+    }
+}
+
+impl Default for FrameConfig {
+    fn default() -> FrameConfig {
+        FrameConfig {
+            left_border: true,
+            right_border: true,
+            bottom_border: true,
+            buttons: ButtonSet::default(),
+            button_layout: ButtonLayout::default(),
+            hover_animations: true,
+            pointer: PointerConfig::default(),
+            accessibility: FrameAccessibility::default(),
+            resize_overlay: false,
+        }
+    }
+}
+
+impl ButtonSet {
+    pub(crate) fn contains(&self, button: UIButton) -> bool {
+        match button {
+            UIButton::Minimize => self.minimize,
+            UIButton::Maximize => self.maximize,
+            UIButton::Close => self.close,
+            UIButton::Pin => self.pin,
+        }
+    }
+}
+
+/// Placement and ordering of the buttons in the titlebar
+///
+/// Buttons can be placed on either side of the titlebar, in any order.
+/// A button absent from the layout is never displayed, regardless of
+/// the `ButtonSet`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ButtonLayout {
+    pub(crate) left: [Option<UIButton>; 4],
+    pub(crate) right: [Option<UIButton>; 4],
+}
+
+impl Default for ButtonLayout {
+    fn default() -> ButtonLayout {
+        ButtonLayout::new(":pin,minimize,maximize,close")
+    }
+}
+
+impl ButtonLayout {
+    /// Parse a layout in the format of GNOME's `button-layout` setting
+    ///
+    /// Buttons listed before the colon are placed on the left of the titlebar,
+    /// buttons listed after it on the right, in the given order. For example
+    /// `"close,minimize,maximize:"` gives a macOS-like layout.
+    ///
+    /// The pin button, which GNOME does not have, is named `pin`. Unknown names (like
+    /// `appmenu` or `spacer`) and repeated buttons are ignored.
+    pub fn new(layout: &str) -> ButtonLayout {
+        let mut parsed = ButtonLayout {
+            left: [None; 4],
+            right: [None; 4],
+        };
+        let mut sides = layout.splitn(2, ':');
+        let left = sides.next().unwrap_or("");
+        let right = sides.next().unwrap_or("");
+        let mut seen = Vec::new();
+        parse_layout_side(left, &mut parsed.left, &mut seen);
+        parse_layout_side(right, &mut parsed.right, &mut seen);
+        parsed
+    }
+}
+
+fn parse_layout_side(names: &str, buttons: &mut [Option<UIButton>; 4], seen: &mut Vec<UIButton>) {
+    let mut slots = buttons.iter_mut();
+    for name in names.split(',') {
+        let button = match name.trim() {
+            "minimize" => UIButton::Minimize,
+            "maximize" => UIButton::Maximize,
+            "close" => UIButton::Close,
+            "pin" => UIButton::Pin,
+            _ => continue,
+        };
+        if seen.contains(&button) {
+            continue;
+        }
+        seen.push(button);
+        if let Some(slot) = slots.next() {
+            *slot = Some(button);
+        }
+    }
+}
+
+impl FrameConfig {
+    /// Subtracts the dimensions of the borders of this configuration from the
+    /// given dimensions.
+    pub fn subtract_borders(&self, width: i32, height: i32) -> (i32, i32) {
+        METRICS.subtract_borders((width, height), self)
+    }
+
+    /// Adds the dimensions of the borders of this configuration to the
+    /// given dimensions.
+    pub fn add_borders(&self, width: i32, height: i32) -> (i32, i32) {
+        METRICS.add_borders((width, height), self)
+    }
+
+    /// Sizes of the borders of this configuration, as drawn by the theme
+    pub fn border_sizes(&self) -> BorderSizes {
+        METRICS.border_sizes(self)
+    }
+
+    /// Whether the buttons fade when hovered
+    pub(crate) fn animates_hover(&self) -> bool {
+        self.hover_animations && !self.accessibility.reduced_motion
+    }
+}
+
+/// Window management actions supported by the compositor
+///
+/// Only compositors using the stable xdg_shell report them, all actions but
+/// `keep_above` are assumed to be supported otherwise, except on wl_shell which
+/// has no window menu and cannot minimize windows.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WmCapabilities {
+    /// Whether the compositor can show a window menu
+    pub window_menu: bool,
+    /// Whether windows can be maximized
+    pub maximize: bool,
+    /// Whether windows can be made fullscreen
+    pub fullscreen: bool,
+    /// Whether windows can be minimized
+    pub minimize: bool,
+    /// Whether windows can be kept above the others, see `Frame::set_keep_above`
+    ///
+    /// This is not part of xdg_shell, it needs the KDE Plasma shell extension.
+    pub keep_above: bool,
+}
+
+impl Default for WmCapabilities {
+    fn default() -> WmCapabilities {
+        WmCapabilities {
+            window_menu: true,
+            maximize: true,
+            fullscreen: true,
+            minimize: true,
+            keep_above: false,
+        }
+    }
+}
+
+/// Who draws the decorations of a window
+///
+/// The compositor may draw the decorations itself if it supports the
+/// xdg-decoration protocol, see `DecorationManager::set_xdg_decoration_manager`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DecorationMode {
+    /// The decorations are drawn by this crate
+    Client,
+    /// The decorations are drawn by the compositor
+    Server,
 }
 
 /// Possible requested state for a window
@@ -74,217 +982,1274 @@ pub enum State<'output> {
     /// Maximized window
     Maximized,
     /// Fullscreen, with optional specification of an output to maximize over
+    ///
+    /// The output can be found by name with an `OutputList`, or be the one the
+    /// window is on with `Frame::current_output`.
     Fullscreen(Option<&'output wl_output::WlOutput>),
 }
 
+/// Error enabling an optional feature of a frame
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FeatureError {
+    /// The compositor or the shell of the window does not support what this feature relies
+    /// on, or its global was not given to the `DecorationManager`
+    Unsupported,
+    /// The frame draws no decorations, having no surface of its own without a subcompositor,
+    /// or the `DecorationManager` was created with `shell_only`
+    Undecorated,
+}
+
+impl fmt::Display for FeatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FeatureError::Unsupported => f.write_str("the compositor does not support this feature"),
+            FeatureError::Undecorated => f.write_str("no decorations are drawn to apply this feature to"),
+        }
+    }
+}
+
+impl Error for FeatureError {}
+
+/// Error drawing the decorations in a buffer, see `Frame::draw_decorations_into`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DrawError {
+    /// The scale is 0
+    ZeroScale,
+    /// The stride is smaller than a row of the scaled decorations
+    StrideTooSmall,
+    /// The buffer is too short to hold all the rows of the scaled decorations
+    BufferTooSmall,
+}
+
+impl fmt::Display for DrawError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DrawError::ZeroScale => f.write_str("the decorations cannot be drawn at scale 0"),
+            DrawError::StrideTooSmall => f.write_str("the stride is smaller than a row of the decorations"),
+            DrawError::BufferTooSmall => f.write_str("the buffer is too short to hold the decorations"),
+        }
+    }
+}
+
+impl Error for DrawError {}
+
+/// Error removing the decorations of a frame, see `Frame::into_undecorated`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UndecorateError {
+    /// The shell objects of the window are already destroyed
+    Defunct,
+}
+
+impl fmt::Display for UndecorateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UndecorateError::Defunct => f.write_str("the shell objects of the window are destroyed"),
+        }
+    }
+}
+
+impl Error for UndecorateError {}
+
+/// Where the shell role of a frame comes from
+pub(crate) enum Toplevel<'a> {
+    /// A toplevel created for the surface of the decorations, the contents being one of
+    /// its subsurfaces
+    New(&'a shell::Shell),
+    /// A toplevel already given to the surface of the user, the decorations are then
+    /// a subsurface placed below it
+    Adopted(shell::Surface),
+}
+
 impl Frame {
     pub(crate) fn new(user_surface: &wl_surface::WlSurface, width: i32, height: i32,
                       compositor: &wl_compositor::WlCompositor,
-                      subcompositor: &wl_subcompositor::WlSubcompositor, shm: &wl_shm::WlShm,
-                      shell: &shell::Shell)
+                      subcompositor: Option<&wl_subcompositor::WlSubcompositor>,
+                      pool: Option<Rc<RefCell<Pool>>>, toplevel: Toplevel)
                       -> Result<Frame, ()> {
         if width <= 0 || height <= 0 {
             return Err(());
         }
 
-        let tempfile = match tempfile() {
-            Ok(t) => t,
-            Err(_) => return Err(()),
-        };
+        let pool_owner = pool.as_ref().map_or(0, |pool| pool.borrow_mut().new_owner());
 
-        match tempfile.set_len(100) {
-            Ok(()) => {}
-            Err(_) => return Err(()),
+        // an adopted toplevel was already configured by the user
+        let needs_readiness = match toplevel {
+            Toplevel::New(shell) => shell.needs_readiness(),
+            Toplevel::Adopted(_) => false,
         };
+        let mut meta = FrameMetadata::new((width, height), needs_readiness, subcompositor.is_some());
 
-        let pool = shm.create_pool(tempfile.as_raw_fd(), 100);
-
-        let meta = Arc::new(Mutex::new(FrameMetadata {
-            dimensions: (width, height),
-            decorate: false,
-            fullscreen: false,
-            maximized: false,
-            min_size: None,
-            max_size: None,
-            old_size: None,
-            activated: true,
-            ready: !shell.needs_readiness(),
-            need_redraw: shell.needs_readiness(),
-            ptr_location: Location::None,
-        }));
-
-        let frame_surface = compositor.create_surface();
-        let contents = subcompositor
-            .get_subsurface(&user_surface, &frame_surface)
-            .expect("Provided Subcompositor was defunct");
-        contents.set_position(0, 0);
-        contents.set_desync();
-
-        let shell_surface = shell::Surface::from_shell(&frame_surface, shell);
+        let (frame_surface, contents, decorations, shell_surface, adopted) = match (subcompositor, toplevel) {
+            (Some(subcompositor), Toplevel::New(shell)) => {
+                let frame_surface = compositor.create_surface();
+                let contents = subcompositor
+                    .get_subsurface(user_surface, &frame_surface)
+                    .expect("Provided Subcompositor was defunct");
+                contents.set_position(0, 0);
+                contents.set_desync();
+                let shell_surface = shell::Surface::from_shell(&frame_surface, shell);
+                (frame_surface, Some(contents), None, shell_surface, false)
+            }
+            (Some(subcompositor), Toplevel::Adopted(shell_surface)) => {
+                let frame_surface = compositor.create_surface();
+                let decorations = subcompositor
+                    .get_subsurface(&frame_surface, user_surface)
+                    .expect("Provided Subcompositor was defunct");
+                decorations.place_below(user_surface);
+                decorations.set_desync();
+                // the contents are the window itself, they cannot be hidden
+                meta.shadable = false;
+                (frame_surface, None, Some(decorations), shell_surface, true)
+            }
+            // the surface of the user is then the window itself, without decorations
+            (None, Toplevel::New(shell)) => {
+                let shell_surface = shell::Surface::from_shell(user_surface, shell);
+                (user_surface.clone().ok_or(())?, None, None, shell_surface, false)
+            }
+            (None, Toplevel::Adopted(shell_surface)) => {
+                (user_surface.clone().ok_or(())?, None, None, shell_surface, true)
+            }
+        };
+        // there is no minimize button on wl_shell, windows cannot be minimized there
+        meta.set_capabilities(shell_surface.capabilities());
 
-        let mut frame = Frame {
+        Ok(Frame {
             surface: frame_surface,
-            contents: contents,
+            contents,
+            sync_contents: false,
+            decorations,
+            adopted,
+            user_surface: user_surface.clone().ok_or(())?,
+            compositor: compositor.clone().ok_or(())?,
+            subcompositor: match subcompositor {
+                Some(subcompositor) => Some(subcompositor.clone().ok_or(())?),
+                None => None,
+            },
             shell_surface: shell_surface,
-            buffer: None,
-            tempfile: tempfile,
             pool: pool,
+            pool_owner,
+            last_drawn: None,
             pointer: None,
-            meta: meta,
-            buffer_capacity: 100,
-        };
-
-        frame.redraw();
-
-        Ok(frame)
+            #[cfg(feature = "wayland-protocols")]
+            tool_target: None,
+            keyboard_target: None,
+            #[cfg(feature = "wayland-protocols")]
+            viewport: None,
+            #[cfg(feature = "wayland-protocols")]
+            stretched: false,
+            contents_offset: (0, 0),
+            geometry: None,
+            tooltip: None,
+            resize_overlay: None,
+            scratch: RefCell::new(Scratch::default()),
+            widgets: Rc::new(RefCell::new(Default::default())),
+            drag_region: Rc::new(RefCell::new(Region::new())),
+            axis_handler: Rc::new(RefCell::new(None)),
+            #[cfg(feature = "cursor_theme")]
+            cursor_theme: None,
+            #[cfg(feature = "egl")]
+            egl: None,
+            #[cfg(feature = "misc_protocols")]
+            kde_decoration: None,
+            activation: None,
+            #[cfg(feature = "wayland-protocols")]
+            xdg_exporter: None,
+            #[cfg(feature = "wayland-protocols")]
+            xdg_importer: None,
+            #[cfg(feature = "wayland-protocols")]
+            exported: None,
+            #[cfg(feature = "wayland-protocols")]
+            foreign_parent: None,
+            plasma_surface: None,
+            blur_manager: None,
+            blur: None,
+            blurred: None,
+            opaque: None,
+            #[cfg(feature = "wayland-protocols")]
+            presentation: None,
+            refresh_callback: None,
+            restore_callback: None,
+            #[cfg(feature = "wayland-protocols")]
+            last_presentation: Rc::new(Cell::new(None)),
+            outputs: Rc::new(RefCell::new(Vec::new())),
+            pending: Arc::new(Mutex::new(PendingOps::default())),
+            meta: Arc::new(Mutex::new(meta)),
+            detached: false,
+            hidden: false,
+            title: RefCell::new(None),
+            app_id: RefCell::new(None),
+        })
     }
 
-    pub(crate) fn redraw(&mut self) {
+    /// Redraw the decorations, returns whether their surface was committed
+    pub(crate) fn redraw(&mut self, evqh: &mut EventQueueHandle) -> bool {
+        if self.hidden {
+            log_trace!("redraw skipped, the window is hidden");
+            return false;
+        }
         let mut meta = self.meta.lock().unwrap();
         if !meta.ready {
-            return;
+            log_trace!("redraw skipped, the surface is not configured yet");
+            return false;
+        }
+        // interactive resizes configure the window faster than the output refreshes, the
+        // decorations are then painted at most once per output frame
+        if meta.resizing && meta.paint_pending {
+            log_trace!("redraw deferred to the next output frame");
+            return false;
         }
 
-        if !meta.decorate || meta.fullscreen {
-            // setup a dummy surface so that the subsurface does all
-            // write a transparent buffer
-            self.tempfile.seek(SeekFrom::Start(0)).unwrap();
-            let _ = self.tempfile.write_all(&[0, 0, 0, 0]).unwrap();
-            self.tempfile.flush().unwrap();
-            if let Some(buffer) = self.buffer.take() {
-                // TODO: better handling of buffer release
-                buffer.destroy();
-            }
-            let buffer = self.pool
-                .create_buffer(0, 1, 1, 4, wl_shm::Format::Argb8888)
-                .expect("The pool cannot be defunct!");
-            self.surface.attach(Some(&buffer), 0, 0);
-            self.surface.commit();
-            return
+        // the configure is acked with the commit
+        if let Some(serial) = meta.pending_serial.take() {
+            self.shell_surface.ack_configure(serial);
         }
 
-        let (w, h) = meta.dimensions;
-        let pxcount = ::theme::pxcount(w, h);
+        let (subcompositor, pool) = match (self.subcompositor.as_ref(), self.pool.clone()) {
+            (Some(subcompositor), Some(pool)) => (subcompositor, pool),
+            _ => {
+                // the surface belongs to the user, who commits it
+                log_trace!("redraw skipped, the user commits the surface");
+                self.request_restore(evqh, &meta);
+                meta.need_redraw = false;
+                return false;
+            }
+        };
 
-        if pxcount * 4 > self.buffer_capacity {
-            // realloc needed!
-            self.tempfile.set_len((pxcount * 4) as u64).unwrap();
-            self.pool.resize(pxcount * 4);
-            self.buffer_capacity = pxcount * 4;
+        // the contents of shaded windows are unmapped with their subsurface
+        if !self.adopted && meta.is_shaded() == self.contents.is_some() {
+            log_debug!("contents {}", if meta.is_shaded() { "unmapped" } else { "mapped" });
+            if let Some(contents) = self.contents.take() {
+                contents.destroy();
+            } else {
+                let contents = subcompositor
+                    .get_subsurface(&self.user_surface, &self.surface)
+                    .expect("Provided Subcompositor was defunct");
+                if !self.sync_contents {
+                    contents.set_desync();
+                }
+                self.contents = Some(contents);
+                self.contents_offset = (0, 0);
+            }
+            self.shell_surface.set_size_limits(&meta);
         }
-        // rewrite the data
-        let mut mmap = unsafe {
-            ::memmap::MmapOptions::new()
-                .len(pxcount as usize * 4)
-                .map_mut(&self.tempfile)
-                .unwrap()
+
+        // the position of the contents is applied with the next commit
+        let offset = if meta.draws_borders() {
+            METRICS.contents_offset(&meta.config)
+        } else {
+            (0, 0)
         };
-        let _ = ::theme::draw_contents(
-            &mut *mmap,
-            w as u32,
-            h as u32,
-            meta.activated,
-            meta.maximized,
-            meta.max_size.is_none(),
-            meta.ptr_location,
+        if let Some(ref contents) = self.contents {
+            if offset != self.contents_offset {
+                contents.set_position(offset.0, offset.1);
+                self.contents_offset = offset;
+            }
+        }
+        // an adopted toplevel stays in place, the decorations are moved around it
+        if let Some(ref decorations) = self.decorations {
+            if offset != self.contents_offset {
+                decorations.set_position(-offset.0, -offset.1);
+                self.contents_offset = offset;
+            }
+        }
+
+        let drawn = meta.drawn();
+        let (full_w, full_h) = drawn.config.add_borders(drawn.dimensions.0, drawn.dimensions.1);
+        let area = {
+            let (left, top) = METRICS.contents_offset(&drawn.config);
+            ((full_w, full_h), (left, top, drawn.dimensions.0, drawn.dimensions.1))
+        };
+        log_trace!(
+            "redraw: contents {:?}, decorations {:?}, borders drawn: {}",
+            drawn.dimensions,
+            (full_w, full_h),
+            meta.draws_borders()
         );
-        mmap.flush().unwrap();
-        drop(mmap);
-
-        // commit a new buffer
-        if let Some(buffer) = self.buffer.take() {
-            // TODO: better handling of buffer release
-            buffer.destroy();
-        }
-        let (full_w, full_h) = ::theme::add_borders(w, h);
-        let buffer = self.pool
-            .create_buffer(0, full_w, full_h, full_w * 4, wl_shm::Format::Argb8888)
-            .expect("The pool cannot be defunct!");
-        self.surface.attach(Some(&buffer), 0, 0);
-        // damage the surface
-        if self.surface.version() >= 4 {
-            self.surface.damage_buffer(0, 0, full_w, full_h);
+
+        // tell the shell which part of the surfaces is the window, also applied with
+        // the next commit
+        let geometry = if meta.draws_borders() && self.adopted {
+            (-offset.0, -offset.1, full_w, full_h)
+        } else if meta.draws_borders() {
+            (0, 0, full_w, full_h)
         } else {
-            // surface is old and does not support damage_buffer, so we damage
-            // in surface coordinates and hope it is not rescaled
-            self.surface.damage(0, 0, full_w, full_h);
+            (0, 0, meta.dimensions.0, meta.dimensions.1)
+        };
+        if self.geometry != Some(geometry) {
+            self.shell_surface.set_window_geometry(geometry);
+            self.geometry = Some(geometry);
         }
-        self.surface.commit();
-        self.buffer = Some(buffer);
+        // blur behind the borders, also applied with the next commit
+        if let Some(ref blur) = self.blur {
+            let area = if meta.draws_borders() { Some(area) } else { None };
+            if self.blurred != Some(area) {
+                let region = borders_region(&self.compositor, area);
+                blur.set_region(Some(&region)).expect("The blur is destroyed with the frame.");
+                blur.commit().expect("The blur is destroyed with the frame.");
+                region.destroy();
+                self.blurred = Some(area);
+            }
+        }
+        // the tooltip of the hovered button, also applied with the next commit
+        let tooltip = if meta.draws_borders() { meta.tooltip } else { None };
+        if self.tooltip.as_ref().map(|tooltip| tooltip.button) != tooltip {
+            self.tooltip = tooltip.and_then(|button| {
+                Tooltip::new(
+                    evqh,
+                    button,
+                    &drawn,
+                    &self.surface,
+                    &self.compositor,
+                    subcompositor,
+                    &pool,
+                )
+            });
+        }
+        // the size of the contents during interactive resizes, also applied with the next commit
+        match (::resize_overlay::resize_label(&drawn), self.resize_overlay.as_mut()) {
+            (Some(label), Some(overlay)) => {
+                if overlay.label != label {
+                    overlay.set_label(evqh, label, &drawn);
+                }
+            }
+            (Some(label), None) => {
+                self.resize_overlay = Some(ResizeOverlay::new(
+                    evqh,
+                    label,
+                    &drawn,
+                    &self.surface,
+                    &self.compositor,
+                    subcompositor,
+                    &pool,
+                ));
+            }
+            (None, _) => self.resize_overlay = None,
+        }
+
+        #[cfg(feature = "wayland-protocols")]
+        {
+            if let Some(ref viewport) = self.viewport {
+                let resized = self.last_drawn
+                    .map(|previous| previous.dimensions != drawn.dimensions)
+                    .unwrap_or(false);
+                if meta.resizing && resized && meta.draws_borders() {
+                    // during an interactive resize, cheaply stretch the previous decorations,
+                    // they are drawn again once the resize ends
+                    log_trace!("previous decorations stretched to {:?}", (full_w, full_h));
+                    viewport.set_destination(full_w, full_h);
+                    if self.opaque.take().is_some() {
+                        self.surface.set_opaque_region(None);
+                    }
+                    self.commit_decorations(evqh, &mut meta);
+                    self.stretched = true;
+                    meta.need_redraw = false;
+                    return true;
+                }
+                if self.stretched {
+                    viewport.set_destination(-1, -1);
+                    self.stretched = false;
+                }
+            }
+        }
+
+        if !meta.draws_borders() {
+            // setup a dummy surface so that the subsurface does all
+            // write a transparent buffer
+            log_trace!("decorations hidden behind a transparent buffer");
+            let mut pool = pool.borrow_mut();
+            let buffer = pool.transparent_buffer(evqh, self.pool_owner);
+            self.surface.attach(Some(buffer), 0, 0);
+            if self.opaque.take().is_some() {
+                self.surface.set_opaque_region(None);
+            }
+            self.commit_decorations(evqh, &mut meta);
+            self.last_drawn = None;
+            meta.need_redraw = false;
+            return true;
+        }
+
+        #[cfg(feature = "egl")]
+        {
+            if self.egl.is_some() {
+                if self.opaque.take().is_some() {
+                    self.surface.set_opaque_region(None);
+                }
+                self.request_paint_callbacks(evqh, &mut meta);
+                if let Some(ref mut egl) = self.egl {
+                    // the swap of the user commits the surface
+                    egl.layout.update(&drawn, self.title.borrow().as_deref());
+                    log_trace!("decorations drawn with EGL");
+                    egl.window.resize(full_w, full_h, 0, 0);
+                    egl.handler.draw(evqh, &egl.window, &egl.layout);
+                }
+                self.last_drawn = Some(drawn);
+                meta.need_redraw = false;
+                return true;
+            }
+        }
+
+        // draw the decorations, only repainting the buttons if their
+        // hover state is all that changed since the last buffer
+        let last_drawn = self.last_drawn;
+        let mut damage = Damage::default();
+        let mut full_redraw = false;
+        let widgets = self.widgets.borrow().clone();
+        let mut scratch = self.scratch.borrow_mut();
+        let mut pool = pool.borrow_mut();
+        let buffer = pool.buffer(evqh, self.pool_owner, full_w, full_h, |canvas, has_previous| {
+            let partial = match last_drawn {
+                Some(ref previous) if has_previous => ::theme::draw_hover_changes(canvas, &drawn, previous),
+                _ => None,
+            };
+            match partial {
+                Some(drawn_rects) => damage = drawn_rects,
+                None => {
+                    damage = ::theme::draw_contents(canvas, &drawn);
+                    ::theme::draw_widgets(canvas, &drawn, scratch.widget(), |id, pixels, size, state| {
+                        if let Some(ref widget) = widgets[id] {
+                            widget.borrow_mut().draw(pixels, size, state);
+                        }
+                    });
+                    full_redraw = true;
+                }
+            }
+        });
+        self.surface.attach(Some(buffer), 0, 0);
+        if self.opaque != Some(area) {
+            // the borders are fully opaque, let the compositor know so that it does
+            // not need to draw what lies behind them
+            let region = borders_region(&self.compositor, Some(area));
+            self.surface.set_opaque_region(Some(&region));
+            region.destroy();
+            self.opaque = Some(area);
+        }
+        // damage the parts of the surface that were drawn
+        log_trace!("decorations drawn, full: {}, damage {:?}", full_redraw, damage.rects());
+        for &(x, y, w, h) in damage.rects() {
+            if self.surface.version() >= 4 {
+                self.surface.damage_buffer(x, y, w, h);
+            } else {
+                // surface is old and does not support damage_buffer, so we damage
+                // in surface coordinates and hope it is not rescaled
+                self.surface.damage(x, y, w, h);
+            }
+        }
+        self.commit_decorations(evqh, &mut meta);
+        self.last_drawn = Some(drawn);
         meta.need_redraw = false;
+        true
+    }
+
+    /// Commit the decoration surface, asking for its presentation feedback
+    fn commit_decorations(&self, evqh: &mut EventQueueHandle, meta: &mut FrameMetadata) {
+        self.request_paint_callbacks(evqh, meta);
+        self.surface.commit();
+    }
+
+    /// Ask for the presentation feedback of the next commit of the decorations
+    ///
+    /// During interactive resizes, a frame callback tells when the next redraw can be
+    /// done.
+    fn request_paint_callbacks(&self, evqh: &mut EventQueueHandle, meta: &mut FrameMetadata) {
+        self.request_restore(evqh, meta);
+        #[cfg(feature = "wayland-protocols")]
+        {
+            if let Some(ref presentation) = self.presentation {
+                ::presentation::request_feedback(evqh, presentation, &self.surface, &self.last_presentation);
+            }
+        }
+        let throttle = meta.resizing && !meta.paint_pending;
+        if let (true, Some(refresh)) = (throttle, self.refresh_callback.as_ref()) {
+            let callback = self.surface
+                .frame()
+                .expect("The frame surface cannot be destroyed.");
+            evqh.register(
+                &callback,
+                paint_callback_implementation(),
+                (self.meta.clone(), refresh.clone()),
+            );
+            meta.paint_pending = true;
+        }
+    }
+
+    /// Give the size from before maximizing back to the user once the next commit is shown
+    ///
+    /// The configure is sent from the frame callback rather than right away, as the user
+    /// may be refreshing the frame from its own callbacks.
+    fn request_restore(&self, evqh: &mut EventQueueHandle, meta: &FrameMetadata) {
+        if let (Some(_), Some(restore)) = (meta.restore_size, self.restore_callback.as_ref()) {
+            let callback = self.surface
+                .frame()
+                .expect("The frame surface cannot be destroyed.");
+            evqh.register(&callback, restore_callback_implementation(), restore.clone());
+        }
+    }
+
+    /// Refreshes the frame
+    ///
+    /// Redraws the frame to match its requested state (dimensions, presence/
+    /// absence of decorations, ...)
+    ///
+    /// If the frame does not need a redraw, this method will do nothing,
+    /// so don't be afraid to call it frequently.
+    ///
+    /// You need to call this method after every change to the dimensions or state
+    /// of the decorations of your window, otherwise the drawn decorations may go
+    /// out of sync with the state of your content.
+    ///
+    /// A window closed by the user, its close callback having returned
+    /// `CloseDecision::Close`, is withdrawn by this method instead.
+    pub fn refresh(&mut self, evqh: &mut EventQueueHandle) {
+        let (need_redraw, closed) = {
+            let meta = self.meta.lock().unwrap();
+            (meta.need_redraw, meta.closed)
+        };
+        if closed {
+            self.hide();
+        } else if need_redraw {
+            self.redraw(evqh);
+        }
+    }
+
+    /// Fire the timers of the decorations that are due, then refresh the frame
+    ///
+    /// The decorations have time-based behaviors, like the delay before the tooltip of a
+    /// hovered button is shown. By default their timers are polled on each frame of the
+    /// compositor, which redraws the decorations while one is running. Applications
+    /// with their own event loop can instead wake up once `next_timeout` elapsed and
+    /// call this method: the first call hands the timers over to the application.
+    pub fn tick(&mut self, evqh: &mut EventQueueHandle) {
+        {
+            let mut meta = self.meta.lock().unwrap();
+            meta.timers.external = true;
+            meta.fire_timers(Instant::now());
+        }
+        self.refresh(evqh);
+    }
+
+    /// Time left before the next timer of the decorations is due, see `tick`
+    ///
+    /// Returns `None` if no timer is running, and a zero duration if one is already due.
+    pub fn next_timeout(&self) -> Option<Duration> {
+        self.meta.lock().unwrap().timers.next_timeout(Instant::now())
+    }
+
+    /// Commit your surface and the decorations together
+    ///
+    /// Your surface is committed, then the frame is refreshed. With synchronized contents,
+    /// see `set_contents_sync`, the decorations are committed even if they do not need a
+    /// redraw, which applies the state of your surface: commit it with this method rather
+    /// than directly.
+    ///
+    /// Without a subcompositor or with an adopted toplevel, your surface is the window: the
+    /// frame is refreshed first, so that the acknowledged configure and the placement of the
+    /// decorations are applied with your commit. Your surface is then not committed while
+    /// the window is hidden, see `hide`.
+    pub fn commit(&mut self, evqh: &mut EventQueueHandle) {
+        if self.subcompositor.is_none() || self.adopted {
+            if self.hidden {
+                return;
+            }
+            self.refresh(evqh);
+            self.user_surface.commit();
+            return;
+        }
+        self.user_surface.commit();
+        let need_redraw = self.meta.lock().unwrap().need_redraw;
+        let committed = need_redraw && self.redraw(evqh);
+        if self.sync_contents && !committed {
+            self.surface.commit();
+        }
+    }
+
+    /// Choose whether the contents are synchronized with the decorations
+    ///
+    /// By default, the subsurface of the contents is desynchronized: the commits of your
+    /// surface are shown right away, and the borders catch up with the next redraw of the
+    /// decorations. Synchronized, the state of your surface is only applied with the next
+    /// commit of the decorations, so that the contents and the borders are resized at once.
+    /// Commit your surface with `commit()` then.
+    ///
+    /// This has no effect without a subcompositor, nor with an adopted toplevel.
+    pub fn set_contents_sync(&mut self, sync: bool) {
+        self.sync_contents = sync;
+        if let Some(ref contents) = self.contents {
+            if sync {
+                contents.set_sync();
+            } else {
+                contents.set_desync();
+            }
+        }
+    }
+
+    /// Whether the contents are synchronized with the decorations, see `set_contents_sync`
+    pub fn is_contents_sync(&self) -> bool {
+        self.sync_contents
+    }
+
+    /// The surface of the decorations
+    ///
+    /// This is the toplevel surface of the window given to the shell, the surface of the
+    /// contents is one of its subsurfaces. Changing its buffer, input region or geometry
+    /// conflicts with the frame.
+    ///
+    /// Without a subcompositor, this is the surface of the contents. With an adopted toplevel,
+    /// this is a subsurface placed below the contents, see `toplevel_surface`.
+    pub fn frame_surface(&self) -> &wl_surface::WlSurface {
+        &self.surface
+    }
+
+    /// The surface given the shell role of the window
+    ///
+    /// This is your surface when the toplevel was adopted or without a subcompositor, and the
+    /// surface of the decorations otherwise.
+    pub fn toplevel_surface(&self) -> &wl_surface::WlSurface {
+        if self.adopted {
+            &self.user_surface
+        } else {
+            &self.surface
+        }
+    }
+
+    /// The shell objects of the window
+    ///
+    /// The frame destroys them when it is dropped, and keeps track of their configures and
+    /// states.
+    pub fn shell_surface(&self) -> ::ShellSurface<'_> {
+        self.shell_surface.objects()
+    }
+
+    /// The subsurface placing the contents in the decorations
+    ///
+    /// It is `None` while the window is shaded, as the contents are then hidden, and
+    /// without a subcompositor.
+    pub fn contents_subsurface(&self) -> Option<&wl_subsurface::WlSubsurface> {
+        self.contents.as_ref()
+    }
+
+    /// Remove the decorations, keeping the toplevel of the window
+    ///
+    /// The buffer of the decorations, their subsurfaces and the handling of the pointer,
+    /// keyboard and tablets by the frame are dropped, as well as the negotiation of the
+    /// decorations with the compositor. The window is left for you to draw, with its own
+    /// decorations: the role of a surface cannot be moved, so this gives you the surface
+    /// of the decorations, no longer showing your surface. Attach your contents to it and
+    /// set its window geometry, the buffer of the decorations stays until then. An adopted
+    /// toplevel is given back with your surface, see `FrameBuilder::adopt`.
+    ///
+    /// A window kept above the others is put back among them. Fails with
+    /// `UndecorateError::Defunct` if the shell objects are already destroyed.
+    pub fn into_undecorated(mut self) -> Result<UndecoratedWindow, UndecorateError> {
+        let surface = self.toplevel_surface().clone().ok_or(UndecorateError::Defunct)?;
+        let shell = self.shell_surface.detach().ok_or(UndecorateError::Defunct)?;
+        {
+            let mut meta = self.meta.lock().unwrap();
+            // configures then give the size of the whole window
+            meta.drawable = false;
+            meta.set_shaded(false);
+            meta.manual_ack = false;
+            if let Some(serial) = meta.pending_serial.take() {
+                self.shell_surface.ack_configure(serial);
+            }
+            self.shell_surface.set_size_limits(&meta);
+        }
+        #[cfg(feature = "misc_protocols")]
+        {
+            if let Some(decoration) = self.kde_decoration.take() {
+                decoration.release();
+            }
+        }
+        if let Some(ref contents) = self.contents.take() {
+            contents.destroy();
+        }
+        #[cfg(feature = "egl")]
+        {
+            self.egl = None;
+        }
+        if let (Some(manager), Some(blur)) = (self.blur_manager.as_ref(), self.blur.take()) {
+            manager.unset(&self.surface);
+            blur.release();
+        }
+        // applied with your next commit
+        if self.subcompositor.is_some() && !self.adopted {
+            self.opaque = None;
+            self.surface.set_opaque_region(None);
+            self.surface.set_input_region(None);
+        }
+        if self.is_kept_above() {
+            let _ = self.set_keep_above(false);
+        }
+        self.detached = true;
+        Ok(UndecoratedWindow { surface, shell })
+    }
+
+    /// A handle to this frame, to request changes from other threads
+    pub fn handle(&self) -> FrameHandle {
+        FrameHandle::new(self.pending.clone())
+    }
+
+    /// Apply the operations queued by the handles of this frame
+    ///
+    /// Call it after each dispatch of the event queue if the frame has handles in other
+    /// threads, see `FrameHandle`.
+    pub fn process_pending(&mut self, evqh: &mut EventQueueHandle) {
+        let pending = ::std::mem::take(&mut *self.pending.lock().unwrap());
+        if let Some(title) = pending.title {
+            self.set_title(title);
+        }
+        if let Some(progress) = pending.progress {
+            self.set_progress(progress);
+        }
+        if let Some((w, h)) = pending.size {
+            self.resize(w, h);
+        }
+        if pending.refresh {
+            self.refresh(evqh);
+        }
+    }
+
+    /// Set a short title for the window.
+    ///
+    /// This string may be used to identify the surface in a task bar, window list, or other user
+    /// interface elements provided by the compositor.
+    ///
+    /// If the decorations show the title, like the ones drawn with `set_egl_renderer`,
+    /// you need to call `refresh()` afterwards for this to properly take effect.
+    pub fn set_title(&self, title: String) {
+        let changed = self.title.borrow().as_ref() != Some(&title);
+        *self.title.borrow_mut() = Some(title.clone());
+        self.shell_surface.set_title(title);
+        if changed && self.draws_title() {
+            self.meta.lock().unwrap().need_redraw = true;
+        }
+    }
+
+    /// The title of the window, as last set by `set_title`
+    pub fn title(&self) -> Option<String> {
+        self.title.borrow().clone()
+    }
+
+    /// Whether the decorations show the title, they are drawn again when it changes
+    fn draws_title(&self) -> bool {
+        #[cfg(feature = "egl")]
+        {
+            // the title is given to the renderer with the layout
+            if self.egl.is_some() {
+                return true;
+            }
+        }
+        ::theme::DRAWS_TITLE
+    }
+
+    /// Set an app id for the surface.
+    ///
+    /// The surface class identifies the general class of applications to which the surface
+    /// belongs.
+    ///
+    /// Several wayland compositors will try to find a `.desktop` file matching this name
+    /// to find metadata about your apps.
+    pub fn set_app_id(&self, app_id: String) {
+        *self.app_id.borrow_mut() = Some(app_id.clone());
+        self.shell_surface.set_app_id(app_id)
+    }
+
+    /// The app id of the window, as last set by `set_app_id`
+    pub fn app_id(&self) -> Option<String> {
+        self.app_id.borrow().clone()
+    }
+
+    /// Set wether the window should be decorated or not
+    ///
+    /// You need to call `refresh()` afterwards for this to properly
+    /// take effect.
+    ///
+    /// If the compositor supports drawing the decorations itself, it is asked to
+    /// do so, see `decoration_mode()`.
+    pub fn set_decorate(&mut self, decorate: bool) {
+        let mut meta = self.meta.lock().unwrap();
+        if meta.decorate != decorate {
+            self.shell_surface.set_server_decorations(decorate);
+            #[cfg(feature = "misc_protocols")]
+            self.request_kde_decoration(decorate);
+            let decorated = meta.decorated();
+            meta.decorate = decorate;
+            if meta.decorated() != decorated {
+                meta.decorations_toggled();
+                // the size limits given to the shell include the borders we draw
+                self.shell_surface.set_size_limits(&meta);
+            }
+        }
+        meta.need_redraw = true;
+    }
+
+    #[cfg(feature = "misc_protocols")]
+    pub(crate) fn request_kde_decoration(&self, server_side: bool) {
+        if let Some(ref decoration) = self.kde_decoration {
+            let mode = if server_side {
+                kde_decoration::Mode::Server
+            } else {
+                kde_decoration::Mode::Client
+            };
+            decoration
+                .request_mode(mode.to_raw())
+                .expect("The decoration is destroyed with the frame.");
+        }
+    }
+
+    /// Whether the window is maximized
+    ///
+    /// With xdg_shell, this reflects the configure events. wl_shell compositors never
+    /// confirm the state of windows, this then reflects the state the frame requested.
+    pub fn is_maximized(&self) -> bool {
+        self.meta.lock().unwrap().maximized
+    }
+
+    /// Whether the window is fullscreen
+    ///
+    /// With xdg_shell, this reflects the configure events. wl_shell compositors never
+    /// confirm the state of windows, this then reflects the state the frame requested.
+    pub fn is_fullscreen(&self) -> bool {
+        self.meta.lock().unwrap().fullscreen
+    }
+
+    /// Whether the window is activated, its decorations are then drawn as such
+    pub fn is_activated(&self) -> bool {
+        self.meta.lock().unwrap().activated
+    }
+
+    /// Whether the compositor reported the window as not visible at all
+    ///
+    /// It can be hidden by other windows, on another workspace, or the screen may be
+    /// locked. Stop rendering your contents until it is unset, the frame callbacks of your
+    /// surface may not be called meanwhile. The change is also reported to
+    /// `FrameImplementation::state_changed` as `WindowState::Suspended`. Only the stable
+    /// xdg_shell from its version 6 reports this state.
+    pub fn is_suspended(&self) -> bool {
+        self.meta.lock().unwrap().suspended
+    }
+
+    /// Current size of the contents of the window, as given to `resize`
+    pub fn dimensions(&self) -> (i32, i32) {
+        self.meta.lock().unwrap().dimensions
+    }
+
+    /// Sizes of the borders currently drawn around the contents
+    ///
+    /// They are all zero when the frame does not draw its decorations, for example when
+    /// the window is fullscreen or decorated by the compositor, and only the titlebar is
+    /// drawn for shaded windows. Use it to place your contents, or to convert between
+    /// the size of the window and the size of the contents.
+    pub fn border_sizes(&self) -> BorderSizes {
+        self.meta.lock().unwrap().border_sizes()
+    }
+
+    /// Size of the decorations drawn around the current contents, borders included
+    ///
+    /// Only the titlebar is drawn for shaded windows.
+    pub fn decorations_size(&self) -> (i32, i32) {
+        let drawn = self.meta.lock().unwrap().drawn();
+        drawn.config.add_borders(drawn.dimensions.0, drawn.dimensions.1)
+    }
+
+    /// Draw the decorations in a buffer of yours
+    ///
+    /// This is for applications compositing the whole window in a single surface: the
+    /// decorations are drawn as the frame would draw them, titlebar widgets included, but
+    /// nothing is displayed by the frame. `canvas` holds native-endian ARGB8888 pixels
+    /// with premultiplied alpha, in rows of `stride` bytes, for the `decorations_size()`
+    /// multiplied by `scale`. The part covered by your contents is cleared to transparent,
+    /// draw them afterwards.
+    ///
+    /// Fails if `scale` is 0, or if `stride` or the canvas is too small.
+    pub fn draw_decorations_into(&self, canvas: &mut [u8], stride: usize, scale: u32)
+                                 -> Result<(), DrawError> {
+        let drawn = self.meta.lock().unwrap().drawn();
+        let (full_w, full_h) = drawn.config.add_borders(drawn.dimensions.0, drawn.dimensions.1);
+        let scale = scale as usize;
+        let (width, height) = (full_w as usize * scale, full_h as usize * scale);
+        if scale == 0 {
+            return Err(DrawError::ZeroScale);
+        }
+        if stride < width * 4 {
+            return Err(DrawError::StrideTooSmall);
+        }
+        if canvas.len() < stride * (height - 1) + width * 4 {
+            return Err(DrawError::BufferTooSmall);
+        }
+        let widgets = self.widgets.borrow().clone();
+        let mut scratch = self.scratch.borrow_mut();
+        ::theme::draw_scaled(canvas, stride, scale, &drawn, &mut scratch, |id, pixels, size, state| {
+            if let Some(ref widget) = widgets[id] {
+                widget.borrow_mut().draw(pixels, size, state);
+            }
+        });
+        Ok(())
+    }
+
+    /// Whether the window is decorated, as set with `set_decorate`
+    ///
+    /// See `decoration_mode()` for who draws the decorations.
+    pub fn is_decorated(&self) -> bool {
+        self.meta.lock().unwrap().decorate
+    }
+
+    /// Recommended maximum size of the contents of this window
+    ///
+    /// Compositors using the stable xdg_shell can tell windows how large they can
+    /// be, for example the size of the screen without its panels, which helps
+    /// choosing an initial size. `None` if unknown.
+    pub fn bounds(&self) -> Option<(i32, i32)> {
+        self.meta.lock().unwrap().inner_bounds()
+    }
+
+    /// Window management actions supported by the compositor
+    ///
+    /// The buttons of unsupported actions are hidden.
+    pub fn wm_capabilities(&self) -> WmCapabilities {
+        self.meta.lock().unwrap().capabilities
+    }
+
+    /// Who currently draws the decorations of this window
+    ///
+    /// This is negotiated with the compositor, and may change at any time. The frame then
+    /// asks to be refreshed, which draws or removes the borders and moves the contents
+    /// accordingly. The size of the contents is kept, so the window grows or shrinks by
+    /// the size of the borders.
+    pub fn decoration_mode(&self) -> DecorationMode {
+        self.meta.lock().unwrap().decoration_mode
+    }
+
+    /// Choose when configure events are acknowledged
+    ///
+    /// By default, the configure events of xdg_shell are acknowledged as soon as they
+    /// are received. With manual acknowledgement, they are acknowledged when you
+    /// call `ack_configure()`, or with the next redraw of the decorations. Acknowledge
+    /// them once your contents have been resized to avoid tearing during resizes.
+    pub fn set_manual_ack(&mut self, manual: bool) {
+        let pending = {
+            let mut meta = self.meta.lock().unwrap();
+            meta.manual_ack = manual;
+            if manual {
+                None
+            } else {
+                meta.pending_serial.take()
+            }
+        };
+        if let Some(serial) = pending {
+            self.shell_surface.ack_configure(serial);
+        }
+    }
+
+    /// Acknowledge the last configure event
+    ///
+    /// Only needed with manual acknowledgement, see `set_manual_ack`. It takes effect
+    /// with the next commit of the decorations, call `refresh()` afterwards.
+    pub fn ack_configure(&self) {
+        let pending = self.meta.lock().unwrap().pending_serial.take();
+        if let Some(serial) = pending {
+            self.shell_surface.ack_configure(serial);
+        }
+    }
+
+    /// Set the configuration of the decorations
+    ///
+    /// You need to call `refresh()` afterwards for this to properly
+    /// take effect.
+    pub fn set_config(&mut self, config: FrameConfig) {
+        let (min_size, max_size) = {
+            let mut meta = self.meta.lock().unwrap();
+            meta.set_config(config);
+            meta.need_redraw = true;
+            (meta.min_size, meta.max_size)
+        };
+        // the size limits given to the shell include the borders
+        self.set_min_size(min_size);
+        self.set_max_size(max_size);
+    }
+
+    /// Set which buttons are displayed in the titlebar
+    ///
+    /// You need to call `refresh()` afterwards for this to properly
+    /// take effect.
+    pub fn set_buttons(&mut self, buttons: ButtonSet) {
+        let mut meta = self.meta.lock().unwrap();
+        let mut config = meta.user_config;
+        config.buttons = buttons;
+        meta.set_config(config);
+        meta.need_redraw = true;
+    }
+
+    /// Resize the decorations
+    ///
+    /// You should call this whenever you change the size of the contents
+    /// of your window, with the new _inner size_ of your window.
+    ///
+    /// You need to call `refresh()` afterwards for this to properly
+    /// take effect.
+    pub fn resize(&mut self, w: i32, h: i32) {
+        use std::cmp::max;
+        let w = max(w, 1);
+        let h = max(h, 1);
+        let mut meta = self.meta.lock().unwrap();
+        meta.dimensions = (w, h);
+        meta.need_redraw = true;
+    }
+
+    /// Resize your contents and the decorations at once
+    ///
+    /// `draw` attaches a buffer of the new size to your surface, and damages it. The frame
+    /// then commits your surface and redraws the decorations like `commit` does, with the
+    /// contents synchronized for this commit: the new contents, their position, the window
+    /// geometry and the acknowledged configure are all applied by the commit of the
+    /// decorations. During fast interactive resizes, this keeps the borders from lagging
+    /// behind the contents, whether the contents are synchronized or not otherwise.
+    ///
+    /// The decorations are redrawn even if their redraws are throttled during interactive
+    /// resizes, pace your redraws on the frame callbacks of your surface instead.
+    pub fn resize_synced<F>(&mut self, evqh: &mut EventQueueHandle, w: i32, h: i32, draw: F)
+    where
+        F: FnOnce(&wl_surface::WlSurface),
+    {
+        self.resize(w, h);
+        self.meta.lock().unwrap().paint_pending = false;
+        draw(&self.user_surface);
+        let sync = self.sync_contents;
+        self.set_contents_sync(true);
+        self.commit(evqh);
+        self.set_contents_sync(sync);
+    }
+
+    /// Shade the window, only showing its titlebar
+    ///
+    /// The contents are hidden until the window is unshaded, their size is kept: the
+    /// configures of a shaded window only change the width, and those still having the
+    /// height of the titlebar once the window is unshaded suggest the previous height.
+    /// Maximizing the window unshades it.
+    ///
+    /// Users can also shade the window by scrolling up on the titlebar, and unshade it by
+    /// scrolling down. Only windows whose decorations are drawn by this crate can be
+    /// shaded, and not those with an adopted toplevel, whose contents cannot be hidden.
+    ///
+    /// You need to call `refresh()` afterwards for this to properly take effect, the
+    /// contents are shown again with their next commit.
+    pub fn set_shaded(&mut self, shaded: bool) {
+        self.meta.lock().unwrap().set_shaded(shaded);
+    }
+
+    /// Whether the window is shaded, see `set_shaded`
+    pub fn is_shaded(&self) -> bool {
+        self.meta.lock().unwrap().is_shaded()
+    }
+
+    /// Show the progress of a task of the application in the titlebar, in percent
+    ///
+    /// It is drawn as a thin bar along the bottom of the titlebar, `None` hides it.
+    /// Values above 100 are shown as 100%. None of the protocols supported by this crate
+    /// tell the taskbar about the progress, applications have to do it themselves, for
+    /// example with the `com.canonical.Unity.LauncherEntry` D-Bus interface.
+    ///
+    /// You need to call `refresh()` afterwards for this to properly take effect.
+    pub fn set_progress(&mut self, progress: Option<u8>) {
+        let mut meta = self.meta.lock().unwrap();
+        let progress = progress.map(|progress| progress.min(100));
+        if meta.progress != progress {
+            meta.progress = progress;
+            meta.need_redraw = true;
+        }
+    }
+
+    /// The progress shown in the titlebar, see `set_progress`
+    pub fn progress(&self) -> Option<u8> {
+        self.meta.lock().unwrap().progress
+    }
+
+    /// Tint the decorations of this window with a color, given as `[a, r, g, b]`
+    ///
+    /// The borders of the active window and the hovered buttons are blended with the color,
+    /// its alpha giving how strongly: `[0; 4]`, the default, leaves them untouched. The
+    /// inactive decorations are not tinted.
+    ///
+    /// You need to call `refresh()` afterwards for this to properly take effect.
+    pub fn set_accent_color(&mut self, color: [u8; 4]) {
+        let mut meta = self.meta.lock().unwrap();
+        if meta.accent_color != color {
+            meta.accent_color = color;
+            meta.need_redraw = true;
+        }
+    }
+
+    /// The color tinting the decorations, see `set_accent_color`
+    pub fn accent_color(&self) -> [u8; 4] {
+        self.meta.lock().unwrap().accent_color
+    }
+
+    /// Add a widget to the titlebar, drawn and handled by the application
+    ///
+    /// The widget has the given width and the height of the titlebar buttons. It is
+    /// placed next to the buttons of `side`, and hidden when it does not fit in the
+    /// titlebar. The pointer hovering it redraws the decorations, and left clicks on
+    /// it call the `click` function of its implementation.
+    ///
+    /// Fails if `width` is not positive, or if the titlebar already has `MAX_WIDGETS`
+    /// widgets. You need to call `refresh()` afterwards for this to properly take effect.
+    pub fn add_titlebar_widget<ID: 'static>(&mut self, side: WidgetSide, width: i32,
+                                            implementation: WidgetImplementation<ID>, idata: ID)
+                                            -> Result<WidgetId, WidgetError> {
+        if width <= 0 {
+            return Err(WidgetError::InvalidWidth);
+        }
+        let mut widgets = self.widgets.borrow_mut();
+        let id = widgets
+            .iter()
+            .position(|widget| widget.is_none())
+            .ok_or(WidgetError::TooManyWidgets)?;
+        let handler: Rc<RefCell<dyn WidgetHandler>> = Rc::new(RefCell::new((implementation, idata)));
+        widgets[id] = Some(handler);
+        let mut meta = self.meta.lock().unwrap();
+        meta.widgets[id] = Some((side, width));
+        meta.need_redraw = true;
+        Ok(WidgetId(id))
+    }
+
+    /// Remove a widget from the titlebar
+    ///
+    /// Its id may then be given to another widget. You need to call `refresh()`
+    /// afterwards for this to properly take effect.
+    pub fn remove_titlebar_widget(&mut self, widget: WidgetId) {
+        self.widgets.borrow_mut()[widget.0] = None;
+        let mut meta = self.meta.lock().unwrap();
+        meta.widgets[widget.0] = None;
+        meta.need_redraw = true;
     }
 
-    /// Refreshes the frame
+    /// Draw the widgets of the titlebar again, after their appearance changed
     ///
-    /// Redraws the frame to match its requested state (dimensions, presence/
-    /// absence of decorations, ...)
+    /// You need to call `refresh()` afterwards for this to properly take effect.
+    pub fn redraw_titlebar_widgets(&mut self) {
+        // only a full redraw of the decorations draws the widgets
+        self.last_drawn = None;
+        self.meta.lock().unwrap().need_redraw = true;
+    }
+
+    /// Set the area of your surface that moves the window when dragged
     ///
-    /// If the frame does not need a redraw, this method will do nothing,
-    /// so don't be afraid to call it frequently.
+    /// The region is in the coordinates of your surface, like an in-app header bar. The
+    /// window is moved when the pointer is dragged after a left click in it, simple clicks
+    /// are left to your application. Give an empty region to remove it.
     ///
-    /// You need to call this method after every change to the dimensions or state
-    /// of the decorations of your window, otherwise the drawn decorations may go
-    /// out of sync with the state of your content.
-    pub fn refresh(&mut self) {
-        let need_redraw = self.meta.lock().unwrap().need_redraw;
-        if need_redraw {
-            self.redraw();
-        }
+    /// This needs the frame to handle the pointers, which is only the case when the
+    /// compositor supports subsurfaces.
+    pub fn set_drag_region(&mut self, region: Region) {
+        *self.drag_region.borrow_mut() = region;
     }
 
-    /// Set a short title for the window.
+    /// Handle the scrolls of the pointers over the decorations
     ///
-    /// This string may be used to identify the surface in a task bar, window list, or other user
-    /// interface elements provided by the compositor.
-    pub fn set_title(&self, title: String) {
-        self.shell_surface.set_title(title)
+    /// The `axis`, `axis_discrete` and `frame` events of the pointers hovering the
+    /// decorations are given to `implementation`, to react to scrolls over the titlebar
+    /// for example. This replaces the previous handler.
+    ///
+    /// Like `set_drag_region`, this needs the frame to handle the pointers.
+    pub fn set_axis_handler<ID: 'static>(&mut self, implementation: AxisImplementation<ID>, idata: ID) {
+        let handler: Rc<RefCell<dyn AxisHandler>> = Rc::new(RefCell::new((implementation, idata)));
+        *self.axis_handler.borrow_mut() = Some(handler);
     }
 
-    /// Set an app id for the surface.
+    /// Remove the handler of the scrolls, see `set_axis_handler`
+    pub fn remove_axis_handler(&mut self) {
+        *self.axis_handler.borrow_mut() = None;
+    }
+
+    /// Replace the cursor theme used over the decorations, after the user changed it
     ///
-    /// The surface class identifies the general class of applications to which the surface
-    /// belongs.
+    /// The theme of given name and size is loaded for the pointers and tablet tools of
+    /// this frame only, the other frames of the manager keep theirs. Hovering cursors
+    /// take it as soon as they move.
     ///
-    /// Several wayland compositors will try to find a `.desktop` file matching this name
-    /// to find metadata about your apps.
-    pub fn set_app_id(&self, app_id: String) {
-        self.shell_surface.set_app_id(app_id)
+    /// Fails with `FeatureError::Unsupported` if the cursors of this frame are not themed:
+    /// it does not handle the pointers, or libwayland-cursor was not available when it was
+    /// created.
+    #[cfg(feature = "cursor_theme")]
+    pub fn set_cursor_theme(&mut self, name: &str, size: u32) -> Result<(), FeatureError> {
+        let slot = self.cursor_theme.as_ref().ok_or(FeatureError::Unsupported)?;
+        log_debug!("cursor theme replaced by {:?} of size {}", name, size);
+        slot.borrow_mut().set_theme(name, size);
+        Ok(())
     }
 
-    /// Set wether the window should be decorated or not
+    /// Draw the decorations on the GPU, with an EGL window
     ///
-    /// You need to call `refresh()` afterwards for this to properly
-    /// take effect.
-    pub fn set_decorate(&mut self, decorate: bool) {
+    /// The frame surface is given a `wl_egl_window`, in which `implementation` draws the
+    /// decorations whenever they need a redraw, instead of this crate drawing them in
+    /// shared memory buffers. Create your EGL surface from `egl_window()`, and destroy it
+    /// before removing the renderer, dropping the frame or calling `into_undecorated`.
+    /// The titlebar widgets are not drawn.
+    ///
+    /// Fails with `FeatureError::Undecorated` if the frame has no surface of its own, which
+    /// needs a subcompositor, and with `FeatureError::Unsupported` if `libwayland-egl` is
+    /// not available. You need to call `refresh()` afterwards for this to properly take
+    /// effect.
+    #[cfg(feature = "egl")]
+    pub fn set_egl_renderer<ID: 'static>(&mut self, implementation: EglImplementation<ID>, idata: ID)
+                                         -> Result<(), FeatureError> {
+        if self.subcompositor.is_none() {
+            return Err(FeatureError::Undecorated);
+        }
+        if !::wayland_client::egl::is_available() {
+            return Err(FeatureError::Unsupported);
+        }
         let mut meta = self.meta.lock().unwrap();
-        meta.decorate = decorate;
+        let drawn = meta.drawn();
+        let (full_w, full_h) = drawn.config.add_borders(drawn.dimensions.0, drawn.dimensions.1);
+        let handler: Box<dyn EglHandler> = Box::new((implementation, idata));
+        self.egl = Some(EglRenderer {
+            window: WlEglSurface::new(&self.surface, full_w, full_h),
+            handler,
+            layout: DecorationLayout::new(),
+        });
+        self.last_drawn = None;
         meta.need_redraw = true;
-        if decorate {
-            let (dx, dy) = ::theme::subsurface_offset();
-            self.contents.set_position(dx, dy);
-        } else {
-            self.contents.set_position(0, 0);
-        }
+        Ok(())
     }
 
-    /// Resize the decorations
+    /// Draw the decorations in shared memory buffers again
     ///
-    /// You should call this whenever you change the size of the contents
-    /// of your window, with the new _inner size_ of your window.
+    /// You need to call `refresh()` afterwards for this to properly take effect.
+    #[cfg(feature = "egl")]
+    pub fn remove_egl_renderer(&mut self) {
+        if self.egl.take().is_some() {
+            self.last_drawn = None;
+            self.meta.lock().unwrap().need_redraw = true;
+        }
+    }
+
+    /// The EGL window of the frame surface, see `set_egl_renderer`
+    #[cfg(feature = "egl")]
+    pub fn egl_window(&self) -> Option<&WlEglSurface> {
+        self.egl.as_ref().map(|egl| &egl.window)
+    }
+
+    /// Whether the keyboard is navigating the decorations
     ///
-    /// You need to call `refresh()` afterwards for this to properly
-    /// take effect.
-    pub fn resize(&mut self, w: i32, h: i32) {
-        use std::cmp::max;
-        let w = max(w, 1);
-        let h = max(h, 1);
-        let mut meta = self.meta.lock().unwrap();
-        meta.dimensions = (w, h);
-        meta.need_redraw = true;
+    /// See `DecorationManager::set_keyboard_navigation`, the application should ignore
+    /// the keys it receives meanwhile.
+    pub fn is_navigating(&self) -> bool {
+        self.meta.lock().unwrap().focused_button.is_some()
     }
 
     /// Sets the requested state of this surface
+    ///
+    /// wl_shell does not configure the windows it unmaximizes: the frame then gives the size
+    /// of the contents from before maximizing back to your configure callback. Call
+    /// `refresh()` afterwards, the configure is sent once the decorations are redrawn.
+    /// wl_shell cannot minimize the windows, `State::Minimized` does nothing then.
     pub fn set_state(&mut self, state: State) {
+        if let shell::Surface::Wl(_) = self.shell_surface {
+            // wl_shell applies the state without telling us
+            if !self.meta.lock().unwrap().wl_set_state(&state) {
+                return;
+            }
+        }
         match state {
             State::Regular => {
                 self.shell_surface.unset_fullscreen();
@@ -304,6 +2269,262 @@ impl Frame {
         }
     }
 
+    /// Withdraw the window from the screen, keeping the frame
+    ///
+    /// The window is unmapped, to be shown again with `show`, like applications minimized
+    /// to a tray do. The decorations are not redrawn meanwhile. When your surface is the
+    /// window, without a subcompositor or with an adopted toplevel, do not commit it until
+    /// the window is shown again: `commit` skips it.
+    pub fn hide(&mut self) {
+        if self.hidden {
+            return;
+        }
+        log_debug!("window hidden");
+        self.hidden = true;
+        {
+            let mut meta = self.meta.lock().unwrap();
+            // an unmapped xdg toplevel is configured again before being mapped
+            if !matches!(self.shell_surface, shell::Surface::Wl(_)) {
+                meta.ready = false;
+                meta.pending_serial = None;
+            }
+            // the frame callbacks of an unmapped surface are not called
+            meta.paint_pending = false;
+        }
+        let surface = self.toplevel_surface();
+        surface.attach(None, 0, 0);
+        surface.commit();
+    }
+
+    /// Show the window again after `hide`
+    ///
+    /// The shell forgets the window while it is hidden: its title, app id, size limits and
+    /// decoration mode are sent again, but its parent and its states like maximized are
+    /// not. With xdg_shell, the window is then configured again like a new one, redraw
+    /// your contents in the configure callback. With wl_shell, the decorations are
+    /// redrawn right away, and your contents need to be attached again.
+    ///
+    /// A closed window is not shown again.
+    pub fn show(&mut self, evqh: &mut EventQueueHandle) {
+        if !self.hidden || self.is_closed() {
+            return;
+        }
+        log_debug!("window shown");
+        self.hidden = false;
+        if let Some(ref title) = *self.title.borrow() {
+            self.shell_surface.set_title(title.clone());
+        }
+        if let Some(ref app_id) = *self.app_id.borrow() {
+            self.shell_surface.set_app_id(app_id.clone());
+        }
+        {
+            let mut meta = self.meta.lock().unwrap();
+            self.shell_surface.set_size_limits(&meta);
+            self.shell_surface.set_server_decorations(meta.decorate);
+            meta.need_redraw = true;
+        }
+        if let shell::Surface::Wl(_) = self.shell_surface {
+            self.refresh(evqh);
+        } else {
+            // the initial commit, answered by a configure
+            let surface = self.toplevel_surface();
+            surface.attach(None, 0, 0);
+            surface.commit();
+        }
+    }
+
+    /// Whether the window is hidden, see `hide`
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Close the window, after its close callback returned `CloseDecision::Ignore`
+    ///
+    /// For example once the user confirmed, in a dialog of your application, that their
+    /// changes can be discarded. The window is withdrawn like with `hide`, for good: drop
+    /// the frame once you are done with it.
+    pub fn close(&mut self) {
+        self.meta.lock().unwrap().closed = true;
+        self.hide();
+    }
+
+    /// Whether the window was closed, by `close` or its close callback
+    pub fn is_closed(&self) -> bool {
+        self.meta.lock().unwrap().closed
+    }
+
+    /// Destroy the frame and its shell objects now
+    ///
+    /// This is the same as dropping the frame, which applications holding it in a field
+    /// or a collection may do at a less obvious point. Your surface is not destroyed, the
+    /// requests are sent with the next flush of the display.
+    pub fn destroy(self) {}
+
+    /// Set the parent of this window
+    ///
+    /// Dialogs should have the window they belong to as parent, they are
+    /// then kept above it. `None` makes this window independent again.
+    ///
+    /// Both frames need to use the same shell.
+    pub fn set_parent(&self, parent: Option<&Frame>) {
+        self.shell_surface
+            .set_parent(parent.map(|parent| (&parent.shell_surface, parent.toplevel_surface())));
+    }
+
+    /// Export this window, so that other applications can set it as the parent of theirs
+    ///
+    /// `done` is called with the handle of the window once the compositor sends it. Give
+    /// it to the other application, which passes it to `set_foreign_parent`, through a
+    /// portal for example. The handle stays valid until `unexport` is called or the frame
+    /// is dropped, exporting the window again revokes the previous handle.
+    ///
+    /// Fails with `FeatureError::Unsupported` if the `DecorationManager` this frame was
+    /// created from has no xdg_exporter global, or if the window does not use the stable
+    /// xdg_shell.
+    #[cfg(feature = "wayland-protocols")]
+    pub fn export<ID: 'static>(&mut self, evqh: &mut EventQueueHandle,
+                               done: fn(&mut EventQueueHandle, &mut ID, String), idata: ID)
+                               -> Result<(), FeatureError> {
+        let exporter = match (self.xdg_exporter.as_ref(), &self.shell_surface) {
+            (Some(exporter), &shell::Surface::XdgStable(_)) => exporter,
+            _ => return Err(FeatureError::Unsupported),
+        };
+        let exported = exporter
+            .export_toplevel(self.toplevel_surface())
+            .expect("The exporter global cannot be destroyed.");
+        evqh.register(
+            &exported,
+            foreign::exported_implementation(),
+            HandleIData { done, idata },
+        );
+        self.unexport();
+        self.exported = Some(exported);
+        Ok(())
+    }
+
+    /// Revoke the handle given by `export`
+    ///
+    /// The windows of other applications having this one as parent become independent.
+    #[cfg(feature = "wayland-protocols")]
+    pub fn unexport(&mut self) {
+        if let Some(exported) = self.exported.take() {
+            exported.destroy();
+        }
+    }
+
+    /// Set the window of another application as the parent of this one
+    ///
+    /// `handle` was exported by the other application, see `export`. Like with
+    /// `set_parent`, this window is then kept above its parent, as the dialogs of
+    /// portals should. `None` makes this window independent again. The parent is
+    /// unset by the compositor if the handle is invalid, or once it is revoked.
+    ///
+    /// Fails with `FeatureError::Unsupported` if the `DecorationManager` this frame was
+    /// created from has no xdg_importer global, or if the window does not use the stable
+    /// xdg_shell.
+    #[cfg(feature = "wayland-protocols")]
+    pub fn set_foreign_parent(&mut self, evqh: &mut EventQueueHandle, handle: Option<String>)
+                              -> Result<(), FeatureError> {
+        let importer = match (self.xdg_importer.as_ref(), &self.shell_surface) {
+            (Some(importer), &shell::Surface::XdgStable(_)) => importer,
+            _ => return Err(FeatureError::Unsupported),
+        };
+        let imported = match handle {
+            Some(handle) => importer
+                .import_toplevel(handle)
+                .expect("The importer global cannot be destroyed."),
+            None => {
+                if let Some(imported) = self.foreign_parent.take() {
+                    imported.destroy();
+                }
+                return Ok(());
+            }
+        };
+        evqh.register(&imported, foreign::imported_implementation(), ());
+        imported.set_parent_of(self.toplevel_surface());
+        if let Some(previous) = self.foreign_parent.replace(imported) {
+            previous.destroy();
+        }
+        Ok(())
+    }
+
+    /// Start an interactive move of the window
+    ///
+    /// The decorations do this when their titlebar is dragged. Call it when the user
+    /// drags a part of your contents acting as a titlebar, with the serial of the button
+    /// press that started the drag.
+    pub fn start_move(&self, seat: &wl_seat::WlSeat, serial: u32) {
+        self.shell_surface._move(seat, serial);
+    }
+
+    /// Start an interactive resize of the window from given edge
+    ///
+    /// Like `start_move`, with the serial of the button press that started the resize.
+    /// `Location::resize_direction` gives the edge matching a part of the window. The
+    /// compositor then sends configures with the new sizes.
+    pub fn start_resize(&self, seat: &wl_seat::WlSeat, serial: u32, direction: wl_shell_surface::Resize) {
+        self.shell_surface.resize(seat, serial, direction);
+    }
+
+    /// The output this window is on
+    ///
+    /// If the window spans several outputs, this is the one it entered last.
+    /// This is `None` until the compositor has shown the window.
+    pub fn current_output(&self) -> Option<wl_output::WlOutput> {
+        self.outputs.borrow().first().and_then(|output| output.clone())
+    }
+
+    /// Ask the compositor to focus this window
+    ///
+    /// `token` proves that the user asked for it, it is either given by the application
+    /// that launched this one (see `activation_token_from_env`) or obtained with
+    /// `request_activation_token`. The compositor may decide to only mark the window as
+    /// urgent instead.
+    ///
+    /// This does nothing if the `DecorationManager` this frame was created from has no
+    /// xdg_activation global.
+    pub fn request_activation(&self, token: String) {
+        if let Some(ref activation) = self.activation {
+            activation.activate(token, self.toplevel_surface());
+        }
+    }
+
+    /// Obtain an activation token from the compositor
+    ///
+    /// The token can be given to `request_activation`, or to another application so
+    /// that it can raise its own window. The compositor only gives valid tokens for
+    /// recent user actions, given by `seat` and the serial of the input event.
+    ///
+    /// `done` is called with the token once the compositor sends it.
+    ///
+    /// Fails with `FeatureError::Unsupported` if the `DecorationManager` this frame was
+    /// created from has no xdg_activation global.
+    pub fn request_activation_token<ID: 'static>(&self, evqh: &mut EventQueueHandle,
+                                                 seat: Option<(&wl_seat::WlSeat, u32)>,
+                                                 app_id: Option<String>,
+                                                 done: fn(&mut EventQueueHandle, &mut ID, String),
+                                                 idata: ID)
+                                                 -> Result<(), FeatureError> {
+        let activation = self.activation.as_ref().ok_or(FeatureError::Unsupported)?;
+        let token = activation
+            .get_activation_token()
+            .expect("The activation global cannot be destroyed.");
+        evqh.register(
+            &token,
+            activation::token_implementation(),
+            TokenIData { done, idata },
+        );
+        if let Some((seat, serial)) = seat {
+            token.set_serial(serial, seat);
+        }
+        if let Some(app_id) = app_id {
+            token.set_app_id(app_id);
+        }
+        token.set_surface(self.toplevel_surface());
+        token.commit();
+        Ok(())
+    }
+
     /// Sets the minimum possible size for this window
     ///
     /// Provide either a tuple `Some((width, height))` or `None` to unset the
@@ -311,17 +2532,104 @@ impl Frame {
     ///
     /// The provided size is the interior size, not counting decorations
     pub fn set_min_size(&mut self, size: Option<(i32, i32)>) {
-        let decorate = {
-            let mut meta = self.meta.lock().unwrap();
-            meta.min_size = size;
-            meta.decorate
-        };
+        let mut meta = self.meta.lock().unwrap();
+        meta.min_size = size;
         self.shell_surface
-            .set_min_size(size.map(|(w, h)| if decorate {
-                ::theme::add_borders(w, h)
-            } else {
-                (w, h)
-            }));
+            .set_min_size(size.map(|size| meta.outer_size(size)));
+    }
+
+    /// Round the sizes suggested to the configure callback to whole increments
+    ///
+    /// The suggested contents sizes are then `base_w` plus a multiple of `w` wide, and `base_h`
+    /// plus a multiple of `h` high, like the grid of cells of a terminal emulator. During
+    /// interactive resizes, the sizes are rounded to the nearest increment, otherwise they
+    /// are rounded down to fit in the size chosen by the compositor. The minimum and maximum
+    /// sizes still apply after the rounding.
+    ///
+    /// Increments of 1 or less disable the rounding on their axis.
+    pub fn set_resize_increments(&mut self, w: i32, h: i32, base_w: i32, base_h: i32) {
+        let mut meta = self.meta.lock().unwrap();
+        meta.resize_increments = if w > 1 || h > 1 {
+            Some(((w, h), (base_w, base_h)))
+        } else {
+            None
+        };
+    }
+
+    /// Lock the ratio between the width and height of the contents
+    ///
+    /// The sizes suggested to the configure callback are then adjusted to the nearest size
+    /// with this aspect ratio, after the resize increments and before the minimum and
+    /// maximum sizes are applied. `None`, or a ratio with a zero side, unlocks it.
+    pub fn set_aspect_ratio(&mut self, ratio: Option<(u32, u32)>) {
+        let mut meta = self.meta.lock().unwrap();
+        meta.aspect_ratio = ratio.and_then(|(w, h)| if w > 0 && h > 0 { Some((w, h)) } else { None });
+    }
+
+    /// Keep this window above the others
+    ///
+    /// This is done with the KDE Plasma shell extension, see
+    /// `DecorationManager::set_plasma_shell`. KWin then treats the window like a panel,
+    /// which is not listed in the taskbar, until it is no longer kept above.
+    ///
+    /// Fails with `FeatureError::Unsupported` if the compositor cannot keep windows above, as
+    /// reported by `WmCapabilities::keep_above`. The pin button of the titlebar does the same.
+    pub fn set_keep_above(&mut self, keep_above: bool) -> Result<(), FeatureError> {
+        let surface = self.plasma_surface.as_ref().ok_or(FeatureError::Unsupported)?;
+        let mut meta = self.meta.lock().unwrap();
+        if meta.keep_above != keep_above {
+            meta.keep_above = keep_above;
+            meta.need_redraw = true;
+            ::plasma::set_keep_above(surface, keep_above);
+        }
+        Ok(())
+    }
+
+    /// Blur what lies behind the decorations
+    ///
+    /// This is done with KDE's blur protocol, see `DecorationManager::set_blur_manager`. The
+    /// blur only shows through translucent decorations, like ones drawn with EGL, and not
+    /// behind the contents.
+    ///
+    /// Fails with `FeatureError::Unsupported` if the compositor cannot blur windows, and with
+    /// `FeatureError::Undecorated` if the frame has no surface of its own, which needs a
+    /// subcompositor. You need to call `refresh()` afterwards for this to properly take effect.
+    pub fn set_blur(&mut self, blur: bool) -> Result<(), FeatureError> {
+        let manager = self.blur_manager.as_ref().ok_or(FeatureError::Unsupported)?;
+        if self.subcompositor.is_none() {
+            return Err(FeatureError::Undecorated);
+        }
+        if blur == self.blur.is_some() {
+            return Ok(());
+        }
+        if blur {
+            self.blur = Some(manager.create(&self.surface));
+            self.blurred = None;
+        } else if let Some(blur) = self.blur.take() {
+            manager.unset(&self.surface);
+            blur.release();
+        }
+        self.meta.lock().unwrap().need_redraw = true;
+        Ok(())
+    }
+
+    /// Whether what lies behind the decorations is blurred, see `set_blur`
+    pub fn is_blurred(&self) -> bool {
+        self.blur.is_some()
+    }
+
+    /// When the last redraw of the decorations was shown on screen
+    ///
+    /// This needs the presentation-time protocol, see `DecorationManager::set_presentation`.
+    /// `None` if no redraw was presented yet.
+    #[cfg(feature = "wayland-protocols")]
+    pub fn last_presentation(&self) -> Option<PresentationFeedback> {
+        self.last_presentation.get()
+    }
+
+    /// Whether this window is kept above the others
+    pub fn is_kept_above(&self) -> bool {
+        self.meta.lock().unwrap().keep_above
     }
 
     /// Sets the maximum possible size for this window
@@ -331,33 +2639,430 @@ impl Frame {
     ///
     /// The provided size is the interior size, not counting decorations
     pub fn set_max_size(&mut self, size: Option<(i32, i32)>) {
-        let decorate = {
-            let mut meta = self.meta.lock().unwrap();
-            meta.max_size = size;
-            meta.decorate
-        };
+        let mut meta = self.meta.lock().unwrap();
+        meta.max_size = size;
         self.shell_surface
-            .set_max_size(size.map(|(w, h)| if decorate {
-                ::theme::add_borders(w, h)
-            } else {
-                (w, h)
-            }));
+            .set_max_size(size.map(|size| meta.outer_size(size)));
+    }
+}
+
+type RefreshCallback = Rc<dyn Fn(&mut EventQueueHandle)>;
+type PaintIData = (Arc<Mutex<FrameMetadata>>, RefreshCallback);
+
+/// A region covering the borders of the decorations, or nothing
+fn borders_region(compositor: &wl_compositor::WlCompositor, area: Option<DecorationArea>)
+                  -> wl_region::WlRegion {
+    let region = compositor.create_region();
+    if let Some(((w, h), (x, y, contents_w, contents_h))) = area {
+        region.add(0, 0, w, h);
+        region.subtract(x, y, contents_w, contents_h);
+    }
+    region
+}
+
+fn paint_callback_implementation() -> wl_callback::Implementation<PaintIData> {
+    wl_callback::Implementation {
+        done: |evqh, &mut (ref meta, ref refresh), _, _| {
+            let need_redraw = {
+                let mut meta = meta.lock().unwrap();
+                meta.paint_pending = false;
+                meta.need_redraw
+            };
+            // the redraws skipped in the meantime can now be done
+            if need_redraw {
+                refresh(evqh);
+            }
+        },
+    }
+}
+
+fn restore_callback_implementation() -> wl_callback::Implementation<RefreshCallback> {
+    wl_callback::Implementation {
+        done: |evqh, restore, _, _| {
+            restore(evqh);
+        },
+    }
+}
+
+pub(crate) fn surface_implementation() -> wl_surface::Implementation<Rc<RefCell<Vec<wl_output::WlOutput>>>> {
+    wl_surface::Implementation {
+        enter: |_, outputs, _, output| {
+            if let Some(output) = output.clone() {
+                outputs.borrow_mut().insert(0, output);
+            }
+        },
+        leave: |_, outputs, _, output| {
+            outputs.borrow_mut().retain(|o| !o.equals(output));
+        },
     }
 }
 
 impl Drop for Frame {
     fn drop(&mut self) {
-        self.shell_surface.destroy();
-        self.surface.destroy();
-        self.contents.destroy();
-        if let Some(buffer) = self.buffer.take() {
-            buffer.destroy();
+        self.tooltip = None;
+        self.resize_overlay = None;
+        #[cfg(feature = "egl")]
+        {
+            // the EGL window must go before its surface
+            self.egl = None;
+        }
+        if !self.detached {
+            self.shell_surface.destroy();
+        }
+        #[cfg(feature = "wayland-protocols")]
+        {
+            if let Some(ref viewport) = self.viewport {
+                viewport.destroy();
+            }
+        }
+        #[cfg(feature = "misc_protocols")]
+        {
+            if let Some(ref decoration) = self.kde_decoration {
+                decoration.release();
+            }
+        }
+        if let Some(ref blur) = self.blur {
+            blur.release();
         }
-        self.pool.destroy();
-        if let Some(ref pointer) = self.pointer {
-            if pointer.version() >= 3 {
-                pointer.release();
+        #[cfg(feature = "wayland-protocols")]
+        {
+            self.unexport();
+            if let Some(ref imported) = self.foreign_parent {
+                imported.destroy();
             }
         }
+        // destroying the plasma surface may unmap the surface given to the user
+        if let (Some(surface), false) = (self.plasma_surface.as_ref(), self.detached) {
+            surface.destroy();
+        }
+        if let Some(ref decorations) = self.decorations {
+            decorations.destroy();
+        }
+        // without a subcompositor, the surface is the one of the user
+        if self.subcompositor.is_some() && (self.adopted || !self.detached) {
+            self.surface.destroy();
+        }
+        if let Some(ref contents) = self.contents {
+            contents.destroy();
+        }
+        if let Some(ref pool) = self.pool {
+            pool.borrow_mut().release_owner(self.pool_owner);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::{decorated, hover, FakeShell};
+
+    // both shells give the size of the whole window to `clamp_to_limits`, then
+    // `shaded_size`, before the size reaches the user
+    fn configure(meta: &mut FrameMetadata, size: (i32, i32)) -> Option<(i32, i32)> {
+        let newsize = meta.clamp_to_limits(size);
+        meta.shaded_size(Some(newsize), size.1)
+    }
+
+    #[test]
+    fn decorated_configure_excludes_borders() {
+        let mut meta = decorated((100, 100));
+        let outer = meta.outer_size((300, 200));
+        assert_ne!(outer, (300, 200));
+        assert_eq!(configure(&mut meta, outer), Some((300, 200)));
+    }
+
+    #[test]
+    fn undecorated_configure_is_kept() {
+        let mut meta = FrameMetadata::new((100, 100), false, true);
+        assert_eq!(meta.outer_size((300, 200)), (300, 200));
+        assert_eq!(configure(&mut meta, (300, 200)), Some((300, 200)));
+    }
+
+    #[test]
+    fn server_side_decorations_are_not_subtracted() {
+        let mut meta = decorated((100, 100));
+        meta.decoration_mode = DecorationMode::Server;
+        assert_eq!(configure(&mut meta, (300, 200)), Some((300, 200)));
+    }
+
+    #[test]
+    fn fullscreen_configure_is_kept() {
+        let mut meta = decorated((100, 100));
+        meta.fullscreen = true;
+        assert_eq!(configure(&mut meta, (300, 200)), Some((300, 200)));
+    }
+
+    #[test]
+    fn undrawable_configure_is_kept() {
+        let mut meta = FrameMetadata::new((100, 100), false, false);
+        meta.decorate = true;
+        assert_eq!(configure(&mut meta, (300, 200)), Some((300, 200)));
+    }
+
+    #[test]
+    fn limits_apply_to_contents() {
+        let mut meta = decorated((100, 100));
+        meta.min_size = Some((200, 150));
+        meta.max_size = Some((400, 300));
+        let (small, large) = (meta.outer_size((100, 100)), meta.outer_size((500, 500)));
+        assert_eq!(configure(&mut meta, small), Some((200, 150)));
+        assert_eq!(configure(&mut meta, large), Some((400, 300)));
+    }
+
+    #[test]
+    fn configure_smaller_than_borders() {
+        let mut meta = decorated((100, 100));
+        assert_eq!(configure(&mut meta, (4, 4)), Some((1, 1)));
+    }
+
+    #[test]
+    fn shaded_configure_keeps_height() {
+        let mut meta = decorated((100, 100));
+        meta.set_shaded(true);
+        let titlebar = meta.config.border_sizes().top;
+        let width = meta.inner_size((300, titlebar)).0;
+        assert_eq!(configure(&mut meta, (300, titlebar)), Some((width, 100)));
+    }
+
+    #[test]
+    fn border_sizes_follow_the_drawn_borders() {
+        let mut meta = decorated((100, 100));
+        meta.config.left_border = false;
+        let sizes = meta.border_sizes();
+        assert_eq!(sizes.left, 0);
+        assert!(sizes.top > 0 && sizes.right > 0 && sizes.bottom > 0);
+        assert_eq!(
+            meta.config.add_borders(100, 100),
+            (100 + sizes.left + sizes.right, 100 + sizes.top + sizes.bottom)
+        );
+        meta.set_shaded(true);
+        assert_eq!(meta.border_sizes().bottom, 0);
+        meta.fullscreen = true;
+        assert_eq!(meta.border_sizes(), BorderSizes::default());
+    }
+
+    #[test]
+    fn unmaximize_restores_size() {
+        let mut shell = FakeShell::xdg((300, 200));
+        let window = shell.window_size();
+        assert_eq!(shell.configure(window, &[WindowState::Activated]), Some((300, 200)));
+        let maximized = shell.configure((1000, 800), &[WindowState::Maximized]).unwrap();
+        assert_ne!(maximized, (300, 200));
+        assert_eq!(shell.configure((0, 0), &[]), Some((300, 200)));
+        assert!(!shell.meta.maximized);
+    }
+
+    #[test]
+    fn suspended_follows_configures() {
+        let mut shell = FakeShell::xdg((300, 200));
+        let window = shell.window_size();
+        shell.configure(window, &[WindowState::Suspended]);
+        assert!(shell.meta.suspended);
+        shell.configure(window, &[WindowState::Activated]);
+        assert!(!shell.meta.suspended);
+    }
+
+    #[test]
+    fn wl_unmaximize_restores_size() {
+        let mut shell = FakeShell::wl((300, 200));
+        let window = shell.window_size();
+        assert_eq!(shell.configure(window, &[]), Some((300, 200)));
+        shell.meta.wl_set_maximized(true);
+        let maximized = shell.configure((1000, 800), &[]).unwrap();
+        assert_ne!(maximized, (300, 200));
+        shell.meta.wl_set_maximized(false);
+        assert_eq!(shell.meta.restore_size, Some((300, 200)));
+        // the size chosen by the compositor wins
+        assert_eq!(shell.configure(window, &[]), Some((300, 200)));
+        assert_eq!(shell.meta.restore_size, None);
+    }
+
+    #[test]
+    fn wl_minimize_keeps_state() {
+        let mut shell = FakeShell::wl((300, 200));
+        assert!(shell.meta.wl_set_state(&State::Maximized));
+        assert!(shell.meta.wl_set_state(&State::Fullscreen(None)));
+        assert!(!shell.meta.wl_set_state(&State::Minimized));
+        assert!(shell.meta.maximized);
+        assert!(shell.meta.fullscreen);
+        assert_eq!(shell.meta.restore_size, None);
+        assert!(shell.meta.wl_set_state(&State::Regular));
+        assert!(!shell.meta.maximized);
+        assert_eq!(shell.meta.restore_size, Some((300, 200)));
+    }
+
+    #[test]
+    fn maximizing_unshades() {
+        let mut shell = FakeShell::xdg((300, 200));
+        shell.meta.set_shaded(true);
+        shell.configure((1000, 800), &[WindowState::Maximized]);
+        assert!(!shell.meta.is_shaded());
+    }
+
+    #[test]
+    fn tiled_edges_have_no_borders() {
+        let mut shell = FakeShell::xdg((300, 200));
+        let tiled = [WindowState::TiledLeft, WindowState::TiledRight, WindowState::TiledBottom];
+        let titlebar = shell.meta.config.border_sizes().top;
+        assert_eq!(shell.configure((300, 200 + titlebar), &tiled), Some((300, 200)));
+        assert_eq!(shell.window_size(), (300, 200 + titlebar));
+    }
+
+    #[test]
+    fn wl_configure_excludes_borders() {
+        let mut shell = FakeShell::wl((100, 100));
+        let outer = shell.meta.outer_size((300, 200));
+        assert_eq!(shell.configure(outer, &[]), Some((300, 200)));
+        assert_eq!(shell.window_size(), outer);
+    }
+
+    #[test]
+    fn tooltip_shown_after_delay() {
+        let mut meta = decorated((100, 100));
+        assert_eq!(hover(&mut meta, (96., 16.)), Location::Button(UIButton::Close));
+        meta.fire_timers(Instant::now());
+        assert_eq!(meta.tooltip, None);
+        let delay = Duration::from_millis(u64::from(TOOLTIP_DELAY));
+        assert!(meta.timers.next_timeout(Instant::now()).unwrap() <= delay);
+        meta.fire_timers(Instant::now() + delay);
+        assert_eq!(meta.tooltip, Some(UIButton::Close));
+        assert_eq!(meta.timers.next_timeout(Instant::now()), None);
+        // leaving the button hides the tooltip
+        hover(&mut meta, (50., 60.));
+        assert_eq!(meta.tooltip, None);
+    }
+
+    #[test]
+    fn resize_overlay_label() {
+        let mut meta = decorated((100, 60));
+        let mut config = meta.user_config;
+        config.resize_overlay = true;
+        meta.set_config(config);
+        assert_eq!(::resize_overlay::resize_label(&meta), None);
+        meta.toplevel_configure((0, 0), [WindowState::Resizing].iter().cloned().collect());
+        let label = |meta: &FrameMetadata| {
+            ::resize_overlay::resize_label(meta).map(|label| label.as_str().to_owned())
+        };
+        assert_eq!(label(&meta).as_deref(), Some("100x60"));
+        meta.resize_increments = Some(((8, 16), (4, 12)));
+        assert_eq!(label(&meta).as_deref(), Some("12x3"));
+        meta.dimensions = (1_000_000_000, 0);
+        meta.resize_increments = None;
+        assert_eq!(label(&meta).as_deref(), Some("1000000000x0"));
+        meta.toplevel_configure((0, 0), StateSet::default());
+        assert_eq!(::resize_overlay::resize_label(&meta), None);
+    }
+
+    fn layout(left: &[UIButton], right: &[UIButton]) -> ButtonLayout {
+        let mut layout = ButtonLayout {
+            left: [None; 4],
+            right: [None; 4],
+        };
+        for (slot, &button) in layout.left.iter_mut().zip(left) {
+            *slot = Some(button);
+        }
+        for (slot, &button) in layout.right.iter_mut().zip(right) {
+            *slot = Some(button);
+        }
+        layout
+    }
+
+    #[test]
+    fn button_layout_sides() {
+        use UIButton::*;
+        assert_eq!(ButtonLayout::new("close,minimize:maximize"), layout(&[Close, Minimize], &[Maximize]));
+        assert_eq!(ButtonLayout::default(), layout(&[], &[Pin, Minimize, Maximize, Close]));
+        // an empty or missing side has no buttons
+        assert_eq!(ButtonLayout::new(":close"), layout(&[], &[Close]));
+        assert_eq!(ButtonLayout::new("close:"), layout(&[Close], &[]));
+        assert_eq!(ButtonLayout::new("close"), layout(&[Close], &[]));
+        assert_eq!(ButtonLayout::new(""), layout(&[], &[]));
+    }
+
+    #[test]
+    fn button_layout_ignored_names() {
+        use UIButton::*;
+        // unknown names, including GNOME's appmenu and spacer, are skipped
+        assert_eq!(ButtonLayout::new("appmenu:close"), layout(&[], &[Close]));
+        assert_eq!(ButtonLayout::new("spacer,foo,minimize:Close"), layout(&[Minimize], &[]));
+        // a button is only placed where it first appears
+        assert_eq!(ButtonLayout::new("close,close:minimize,close"), layout(&[Close], &[Minimize]));
+        // whitespace around the names is trimmed
+        let spaced = ButtonLayout::new(" close , minimize : maximize ");
+        assert_eq!(spaced, layout(&[Close, Minimize], &[Maximize]));
+    }
+
+    #[test]
+    fn resize_increments_rounding() {
+        let mut meta = FrameMetadata::new((100, 100), false, true);
+        assert_eq!(meta.round_to_increments((37, 58)), (37, 58));
+        meta.resize_increments = Some(((10, 20), (5, 4)));
+        // rounded down to fit in the suggested size, to the nearest step while resizing
+        assert_eq!(meta.round_to_increments((37, 58)), (35, 44));
+        assert_eq!(meta.round_to_increments((41, 50)), (35, 44));
+        meta.resizing = true;
+        assert_eq!(meta.round_to_increments((37, 58)), (35, 64));
+        assert_eq!(meta.round_to_increments((41, 50)), (45, 44));
+        // sizes up to the base size are kept
+        assert_eq!(meta.round_to_increments((5, 2)), (5, 2));
+        // increments of 0 or 1 do not constrain the size
+        meta.resize_increments = Some(((0, 1), (5, 4)));
+        assert_eq!(meta.round_to_increments((37, 58)), (37, 58));
+    }
+
+    #[test]
+    fn resize_increments_before_limits() {
+        let mut meta = FrameMetadata::new((100, 100), false, true);
+        meta.resize_increments = Some(((10, 20), (5, 4)));
+        assert_eq!(configure(&mut meta, (37, 58)), Some((35, 44)));
+        // the limits win over the increments
+        meta.min_size = Some((40, 50));
+        meta.max_size = Some((60, 60));
+        assert_eq!(configure(&mut meta, (37, 58)), Some((40, 50)));
+        assert_eq!(configure(&mut meta, (90, 90)), Some((60, 60)));
+        // the contents stay one pixel large, below the base size
+        meta.min_size = None;
+        assert_eq!(configure(&mut meta, (0, 0)), Some((1, 1)));
+    }
+
+    #[test]
+    fn aspect_ratio_matching() {
+        let mut meta = FrameMetadata::new((100, 100), false, true);
+        meta.aspect_ratio = Some((2, 1));
+        // shrunk to fit in the suggested size
+        assert_eq!(meta.match_aspect_ratio((300, 100)), (200, 100));
+        assert_eq!(meta.match_aspect_ratio((100, 300)), (100, 50));
+        assert_eq!(meta.match_aspect_ratio((300, 140)), (280, 140));
+        // the closest size while resizing, which may grow a side
+        meta.resizing = true;
+        assert_eq!(meta.match_aspect_ratio((300, 140)), (300, 150));
+        meta.aspect_ratio = Some((1, 2));
+        assert_eq!(meta.match_aspect_ratio((100, 300)), (150, 300));
+        // a side is never adjusted to 0
+        meta.aspect_ratio = Some((100, 1));
+        assert_eq!(meta.match_aspect_ratio((10, 10)), (10, 1));
+        // a ratio with a zero side is ignored
+        meta.aspect_ratio = Some((0, 1));
+        assert_eq!(meta.match_aspect_ratio((30, 10)), (30, 10));
+        meta.aspect_ratio = Some((1, 0));
+        assert_eq!(meta.match_aspect_ratio((30, 10)), (30, 10));
+    }
+
+    #[test]
+    fn aspect_ratio_between_increments_and_limits() {
+        let mut meta = FrameMetadata::new((100, 100), false, true);
+        meta.aspect_ratio = Some((1, 1));
+        meta.resize_increments = Some(((10, 10), (0, 0)));
+        // rounded to the increments first, then matched to the ratio
+        assert_eq!(configure(&mut meta, (57, 83)), Some((50, 50)));
+        // the limits are applied last, and can break the ratio
+        meta.min_size = Some((60, 20));
+        assert_eq!(configure(&mut meta, (57, 83)), Some((60, 50)));
+        meta.min_size = None;
+        meta.max_size = Some((40, 100));
+        assert_eq!(configure(&mut meta, (57, 83)), Some((40, 50)));
+        // a step from a zero base size does not shrink the contents to nothing
+        meta.max_size = None;
+        assert_eq!(configure(&mut meta, (5, 5)), Some((1, 1)));
     }
 }
@@ -0,0 +1,60 @@
+use frame::{BorderSizes, FrameConfig};
+
+/// The sizes of the parts of the decorations drawn by a theme, in pixels
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Metrics {
+    /// Width of the side and bottom borders, and margin above the buttons
+    pub(crate) border: i32,
+    /// Height of the titlebar, the top border
+    pub(crate) titlebar: i32,
+    pub(crate) button_width: i32,
+    pub(crate) button_height: i32,
+    /// Space left between two buttons of the titlebar
+    pub(crate) button_spacing: i32,
+}
+
+/// The metrics of the decorations drawn by this crate
+pub(crate) const METRICS: Metrics = Metrics {
+    border: 8,
+    titlebar: 32,
+    button_width: 24,
+    button_height: 16,
+    button_spacing: 8,
+};
+
+impl Metrics {
+    /// Sizes of the borders drawn for given configuration
+    pub(crate) fn border_sizes(&self, config: &FrameConfig) -> BorderSizes {
+        let side = |enabled: bool| if enabled { self.border } else { 0 };
+        BorderSizes {
+            top: self.titlebar,
+            bottom: side(config.bottom_border),
+            left: side(config.left_border),
+            right: side(config.right_border),
+        }
+    }
+
+    /// Size of the window with given contents, its borders included
+    pub(crate) fn add_borders(&self, (width, height): (i32, i32), config: &FrameConfig) -> (i32, i32) {
+        let borders = self.border_sizes(config);
+        (
+            width + borders.left + borders.right,
+            height + borders.top + borders.bottom,
+        )
+    }
+
+    /// Size of the contents of a window of given size, without its borders
+    pub(crate) fn subtract_borders(&self, (width, height): (i32, i32), config: &FrameConfig) -> (i32, i32) {
+        let borders = self.border_sizes(config);
+        (
+            width - borders.left - borders.right,
+            height - borders.top - borders.bottom,
+        )
+    }
+
+    /// Offset of the contents relative to the top-left corner of the decorations
+    pub(crate) fn contents_offset(&self, config: &FrameConfig) -> (i32, i32) {
+        let borders = self.border_sizes(config);
+        (borders.left, borders.top)
+    }
+}
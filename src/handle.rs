@@ -0,0 +1,57 @@
+use std::sync::{Arc, Mutex};
+
+/// Operations queued by the handles of a frame
+#[derive(Default)]
+pub(crate) struct PendingOps {
+    pub(crate) size: Option<(i32, i32)>,
+    pub(crate) title: Option<String>,
+    pub(crate) progress: Option<Option<u8>>,
+    pub(crate) refresh: bool,
+}
+
+/// A handle to a frame, usable from other threads
+///
+/// The `Frame` is tied to the thread of its event queue. Its handles can be sent
+/// to other threads, a render thread for example, to request changes of the frame.
+/// These requests are queued, and applied once the thread of the event queue calls
+/// `Frame::process_pending`. Only the last request of each kind is kept.
+///
+/// Handles are obtained with `Frame::handle`, and can be cloned.
+#[derive(Clone)]
+pub struct FrameHandle {
+    pending: Arc<Mutex<PendingOps>>,
+}
+
+impl FrameHandle {
+    pub(crate) fn new(pending: Arc<Mutex<PendingOps>>) -> FrameHandle {
+        FrameHandle { pending }
+    }
+
+    /// Resize the frame, see `Frame::resize`
+    ///
+    /// The frame is refreshed once the new size is applied.
+    pub fn resize(&self, w: i32, h: i32) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.size = Some((w, h));
+        pending.refresh = true;
+    }
+
+    /// Refresh the frame, see `Frame::refresh`
+    pub fn refresh(&self) {
+        self.pending.lock().unwrap().refresh = true;
+    }
+
+    /// Set the title of the window, see `Frame::set_title`
+    pub fn set_title(&self, title: String) {
+        self.pending.lock().unwrap().title = Some(title);
+    }
+
+    /// Set the progress shown in the titlebar, see `Frame::set_progress`
+    ///
+    /// The frame is refreshed once the progress is applied.
+    pub fn set_progress(&self, progress: Option<u8>) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.progress = Some(progress);
+        pending.refresh = true;
+    }
+}
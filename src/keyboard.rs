@@ -0,0 +1,166 @@
+use PointerIData;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use wayland_client::{EventQueueHandle, Proxy};
+use wayland_client::protocol::{wl_keyboard, wl_shell_surface, wl_surface};
+
+// linux input event codes
+const KEY_ESC: u32 = 1;
+const KEY_TAB: u32 = 15;
+const KEY_ENTER: u32 = 28;
+const KEY_SPACE: u32 = 57;
+const KEY_F10: u32 = 68;
+const KEY_KPENTER: u32 = 96;
+const KEY_UP: u32 = 103;
+const KEY_LEFT: u32 = 105;
+const KEY_RIGHT: u32 = 106;
+const KEY_DOWN: u32 = 108;
+
+/// A frame reacting to the keyboard of the seat
+pub(crate) trait KeyboardTarget {
+    /// Whether the keyboard focused this frame
+    fn enter(&mut self, surface: &wl_surface::WlSurface) -> bool;
+    fn leave(&mut self, evqh: &mut EventQueueHandle);
+    fn key(&mut self, evqh: &mut EventQueueHandle, serial: u32, key: u32);
+}
+
+/// Keyboard navigation of the decorations
+///
+/// F10 focuses the buttons of the titlebar, which are then cycled with Tab or the left and
+/// right arrows and triggered with Enter or Space. The up and down arrows start an
+/// interactive resize from the matching edge, Escape leaves the navigation.
+pub(crate) struct FrameKeyboard<ID> {
+    idata: PointerIData<ID>,
+}
+
+impl<ID> FrameKeyboard<ID> {
+    pub(crate) fn new(idata: PointerIData<ID>) -> FrameKeyboard<ID> {
+        FrameKeyboard { idata }
+    }
+}
+
+impl<ID: 'static> FrameKeyboard<ID> {
+    /// Move the focus to the next visible button, or the previous one if `forward` is false
+    fn cycle(&mut self, forward: bool) {
+        let mut meta = self.idata.pstate.meta.lock().unwrap();
+        let buttons = ::theme::focus_order(&meta);
+        let current = meta
+            .focused_button
+            .and_then(|focused| buttons.iter().position(|&b| b == focused));
+        let next = match (current, forward) {
+            (_, _) if buttons.is_empty() => None,
+            (Some(i), true) => Some((i + 1) % buttons.len()),
+            (Some(i), false) => Some((i + buttons.len() - 1) % buttons.len()),
+            (None, true) => Some(0),
+            (None, false) => Some(buttons.len() - 1),
+        };
+        let focused = next.map(|i| buttons[i]);
+        if focused != meta.focused_button {
+            meta.focused_button = focused;
+            meta.need_redraw = true;
+        }
+    }
+
+    fn unfocus(&mut self) {
+        let mut meta = self.idata.pstate.meta.lock().unwrap();
+        if meta.focused_button.take().is_some() {
+            meta.need_redraw = true;
+        }
+    }
+}
+
+impl<ID: 'static> KeyboardTarget for FrameKeyboard<ID> {
+    fn enter(&mut self, surface: &wl_surface::WlSurface) -> bool {
+        self.idata.pstate.frame_surface.equals(surface)
+    }
+
+    fn leave(&mut self, evqh: &mut EventQueueHandle) {
+        self.unfocus();
+        self.idata.refresh_if_needed(evqh);
+    }
+
+    fn key(&mut self, evqh: &mut EventQueueHandle, serial: u32, key: u32) {
+        let focused = {
+            let meta = self.idata.pstate.meta.lock().unwrap();
+            if !meta.draws_borders() {
+                return;
+            }
+            meta.focused_button
+        };
+        match (focused, key) {
+            (None, KEY_F10) | (Some(_), KEY_TAB) | (Some(_), KEY_RIGHT) => self.cycle(true),
+            (Some(_), KEY_LEFT) => self.cycle(false),
+            (Some(button), KEY_ENTER) | (Some(button), KEY_KPENTER) | (Some(button), KEY_SPACE) => {
+                self.unfocus();
+                self.idata.activate(evqh, button);
+            }
+            (Some(_), KEY_UP) | (Some(_), KEY_DOWN) => {
+                self.unfocus();
+                let direction = if key == KEY_UP {
+                    wl_shell_surface::Resize::Top
+                } else {
+                    wl_shell_surface::Resize::Bottom
+                };
+                self.idata.resize(serial, direction);
+            }
+            (Some(_), KEY_ESC) | (Some(_), KEY_F10) => self.unfocus(),
+            _ => return,
+        }
+        self.idata.refresh_if_needed(evqh);
+    }
+}
+
+/// The frames reacting to the keyboard of a seat
+pub(crate) struct KeyboardState {
+    frames: Vec<Weak<RefCell<dyn KeyboardTarget>>>,
+    // the frame the keyboard is focused on
+    focus: Option<Rc<RefCell<dyn KeyboardTarget>>>,
+}
+
+impl KeyboardState {
+    pub(crate) fn new() -> KeyboardState {
+        KeyboardState {
+            frames: Vec::new(),
+            focus: None,
+        }
+    }
+
+    pub(crate) fn add_frame(&mut self, frame: &Rc<RefCell<dyn KeyboardTarget>>) {
+        self.frames.push(Rc::downgrade(frame));
+    }
+}
+
+pub(crate) fn keyboard_implementation() -> wl_keyboard::Implementation<Rc<RefCell<KeyboardState>>> {
+    wl_keyboard::Implementation {
+        keymap: |_, _, _, _, fd, _| {
+            // the keys are read as linux input event codes, the keymap is not needed
+            unsafe {
+                ::libc::close(fd);
+            }
+        },
+        enter: |_, state, _, _, surface, _| {
+            let mut state = state.borrow_mut();
+            // forget the frames that were dropped
+            state.frames.retain(|frame| frame.upgrade().is_some());
+            state.focus = state
+                .frames
+                .iter()
+                .filter_map(|frame| frame.upgrade())
+                .find(|frame| frame.borrow_mut().enter(surface));
+        },
+        leave: |evqh, state, _, _, _| {
+            let focus = state.borrow_mut().focus.take();
+            if let Some(frame) = focus {
+                frame.borrow_mut().leave(evqh);
+            }
+        },
+        key: |evqh, state, _, serial, _, key, key_state| {
+            let focus = state.borrow().focus.clone();
+            if let (Some(frame), wl_keyboard::KeyState::Pressed) = (focus, key_state) {
+                frame.borrow_mut().key(evqh, serial, key);
+            }
+        },
+        modifiers: |_, _, _, _, _, _, _, _| {},
+        repeat_info: |_, _, _, _, _| {},
+    }
+}
@@ -6,18 +6,23 @@
 //!
 //! ## Creating a window with decorations
 //!
-//! Creating a decorated frame for your window is simply done using the provided init function:
+//! Creating a decorated frame for your window is simply done using the provided builder:
 //!
 //! ```ignore
-//! use wayland_window::create_frame;
+//! use wayland_window::FrameBuilder;
 //! // if using the legacy wl_shell global
 //! let shell = Shell::Wl(my_wl_shell);
-//! // if using the new not-yet-stable xdg_shell
-//! let shell = Shell::Xdg(my_xdh_shell);
-//! let frame = create_frame(
-//!        &mut event_queue, my_implementation, my_implementation_data,
-//!        &my_surface, width, height, &compositor, &subcompositor, &shm, &shell, Some(seat)
-//! ).unwrap(); // creation can fail
+//! // if using the stable xdg_shell
+//! let shell = Shell::XdgStable(my_xdg_wm_base);
+//! // if using the unstable xdg_shell v6
+//! let shell = Shell::Xdg(my_xdg_shell);
+//! let frame = FrameBuilder::new(&my_surface, my_implementation, my_implementation_data)
+//!     .globals(&compositor, Some(&subcompositor), &shm, &shell)
+//!     .seat(seat)
+//!     .size(width, height)
+//!     .decorate(true)
+//!     .build(&mut event_queue)
+//!     .unwrap(); // creation can fail
 //! ```
 //!
 //! As you can see, you need to pass several references to global objects as well as a `WlSeat`.
@@ -26,9 +31,33 @@
 //! seat you passed as argument. (So if you are on a setup with more than one pointer,
 //! only the one associated with this seat will be able to resize the window).
 //!
+//! The cursor theme name and size used over the decorations can be chosen with
+//! `FrameBuilder::cursor_theme`. Otherwise, they are read from the `XCURSOR_THEME` and
+//! `XCURSOR_SIZE` environment variables. `Frame::set_cursor_theme` replaces it at
+//! runtime, once the user changed it. Give your `OutputList` to
+//! `DecorationManager::set_output_list` for the cursors to follow the scale of the outputs.
+//!
 //! See next section for example use of the `my_implementation` and
 //! `my_implementation_data` arguments.
 //!
+//! ## Multiple windows
+//!
+//! Each frame built from globals gets its own shm pool and cursor theme.
+//! If your application has several windows, create a `DecorationManager` once
+//! and create all your frames from it, so that they share these resources:
+//!
+//! ```ignore
+//! use wayland_window::DecorationManager;
+//! let manager = DecorationManager::new(
+//!        &compositor, Some(&subcompositor), &shm, &shell, Some(seat), None
+//! ).unwrap();
+//! let frame = FrameBuilder::new(&my_surface, my_implementation, my_implementation_data)
+//!     .manager(&manager)
+//!     .size(width, height)
+//!     .build(&mut event_queue)
+//!     .unwrap();
+//! ```
+//!
 //! ## Configure events
 //!
 //! The `Frame` object will not resize your window itself, as it cannot do it.
@@ -49,7 +78,7 @@
 //! ```no_run
 //! # extern crate wayland_client;
 //! # extern crate wayland_window;
-//! use wayland_window::{Frame, create_frame, FrameImplementation};
+//! use wayland_window::{CloseDecision, Frame, FrameBuilder, FrameImplementation};
 //!
 //! // define a state to accumulate sizes
 //! struct ConfigureState {
@@ -67,18 +96,19 @@
 //!         let configure_state: &mut ConfigureState = evqh.state().get_mut(token);
 //!         configure_state.new_size = newsize;
 //!     },
-//!     close: |_, _| { /* ... */ },
-//!     refresh: |_, _| { /* ... */ }
+//!     close: |_, _| CloseDecision::Close,
+//!     refresh: |_, _| { /* ... */ },
+//!     state_changed: |_, _, _, _| { /* ... */ }
 //! };
 //!
 //! # let (my_surface,width,height,compositor,subcompositor,shm,shell,seat) = unimplemented!();
-//! // create the decorated surface:
-//! let frame = create_frame(
-//!     &mut event_queue,          // the event queue
-//!     my_implementation,         // our implementation
-//!     configure_token.clone(),   // the implementation data
-//!     &my_surface, width, height, &compositor, &subcompositor, &shm, &shell, Some(seat)
-//! ).unwrap();
+//! // create the decorated surface, with our implementation and its data:
+//! let frame = FrameBuilder::new(&my_surface, my_implementation, configure_token.clone())
+//!     .globals(&compositor, Some(&subcompositor), &shm, &shell)
+//!     .seat(seat)
+//!     .size(width, height)
+//!     .build(&mut event_queue)
+//!     .unwrap();
 //!
 //! // then, while running your event loop
 //! loop {
@@ -96,6 +126,30 @@
 //! # }
 //! ```
 //!
+//! Alternatively, the frame can send these events to a channel, drained in the main loop
+//! of your program:
+//!
+//! ```ignore
+//! use wayland_window::{FrameBuilder, FrameEvent};
+//! let (builder, events) = FrameBuilder::with_channel(&my_surface);
+//! let frame = builder
+//!     .globals(&compositor, Some(&subcompositor), &shm, &shell)
+//!     .build(&mut event_queue)
+//!     .unwrap();
+//!
+//! loop {
+//!     display.flush().unwrap();
+//!     event_queue.dispatch().unwrap();
+//!     for event in events.try_iter() {
+//!         match event {
+//!             FrameEvent::Configure(_, Some((w, h))) => { /* resize */ }
+//!             FrameEvent::Close => { /* close */ }
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//! ```
+//!
 //! ## Resizing the surface
 //!
 //! When resizing your main surface, you need to tell the `Frame` that it
@@ -108,7 +162,7 @@
 //! // update the borders size
 //! frame.resize(width, height);
 //! // refresh the frame so that it actually draws the new size
-//! frame.refresh();
+//! frame.refresh(&mut event_queue);
 //! ```
 //!
 //! If you do this as a response of a `configure` event, note the following points:
@@ -121,61 +175,275 @@
 //! - The size hint provided to your implementation is a size hint for the interior of the
 //!   window: the dimensions of the border has been subtracted from the hint the compositor
 //!   gave. If you need to compute dimensions taking into account the sizes of the borders,
-//!   you can use the `add_borders` and `subtract_borders` functions.
+//!   `Frame::border_sizes` gives the sizes of the borders currently drawn around your
+//!   contents.
+//!
+//! ## A ready-made window
+//!
+//! `Window` does all of the above for you: it owns your surface and its frame, keeps the
+//! last size suggested by the configures until you draw your contents, refreshes the
+//! frame when needed, and commits your contents together with the decorations.
+//!
+//! ```ignore
+//! use wayland_window::{Window, WindowEvent};
+//! let mut window = Window::new(&mut event_queue, &manager, width, height, |frame| {
+//!     frame.set_title("My window".into());
+//! }).unwrap();
+//!
+//! loop {
+//!     display.flush().unwrap();
+//!     event_queue.dispatch().unwrap();
+//!     for event in window.events(&mut event_queue) {
+//!         match event {
+//!             WindowEvent::Redraw => window.draw(&mut event_queue, |surface, w, h| {
+//!                 // attach a buffer of size (w, h) to the surface, and damage it
+//!             }),
+//!             WindowEvent::Close => return,
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! ## Without decorations
+//!
+//! Applications drawing their whole window can still use this crate as an abstraction
+//! over the shells: a frame built with `FrameBuilder::shell_only` follows the configures
+//! and states of its shell surface, and sets its title, size limits and state, but never
+//! creates a surface for decorations. Move and resize the window from your own
+//! contents with `Frame::start_move` and `Frame::start_resize`.
+//!
+//! ```ignore
+//! let frame = FrameBuilder::new(&my_surface, my_implementation, my_implementation_data)
+//!     .shell_only(&compositor, &shell)
+//!     .size(width, height)
+//!     .title("My window")
+//!     .build(&mut event_queue)
+//!     .unwrap();
+//! ```
+//!
+//! ## Debugging
+//!
+//! With the `log` feature, the frames log what they negotiate with the compositor
+//! using the [`log`](https://crates.io/crates/log) crate: the configures and the sizes
+//! they lead to, the decoration modes, the redraws of the decorations and the buffers
+//! they use, and the actions of the pointers. Configures, decoration modes and buffer
+//! reallocations are logged at the debug level, redraws and pointer actions at the
+//! trace level.
+//!
+//! ## Smaller builds
+//!
+//! The unstable xdg_shell v6 is only needed by old compositors, applications not
+//! targeting them can build without the default `xdg_shell_v6` feature. The stable
+//! xdg_shell and wl_shell are always supported.
+//!
+//! Without the `wayland-protocols` feature, enabled by `xdg_shell_v6`, the crate does not
+//! depend on `wayland-protocols`: the support of dmabufs, tablets, presentation-time and
+//! the viewporter is left out.
+//!
+//! Without the default `cursor_theme` feature, libwayland-cursor is not used: the cursor
+//! is left as is over the decorations, instead of showing the resize arrows over the
+//! borders.
 
 #![warn(missing_docs)]
 
+#[macro_use]
+extern crate bitflags;
+extern crate libc;
+#[cfg(feature = "log")]
+extern crate log;
 extern crate memmap;
 extern crate tempfile;
 extern crate wayland_client;
+#[cfg(feature = "wayland-protocols")]
 extern crate wayland_protocols;
+#[macro_use]
+extern crate wayland_sys;
+
+#[macro_use]
+mod macros;
 
+mod activation;
+mod animation;
+mod axis;
+mod builder;
+mod channel;
+mod compat;
+#[cfg(feature = "wayland-protocols")]
+mod dmabuf;
+#[cfg(feature = "egl")]
+mod egl;
+#[cfg(feature = "wayland-protocols")]
+mod foreign;
 mod frame;
+mod geometry;
+mod handle;
+mod keyboard;
+mod manager;
+mod output;
+mod plasma;
 mod pointer;
+mod pool;
+mod popup;
+#[cfg(feature = "wayland-protocols")]
+mod presentation;
+mod region;
+mod resize_overlay;
+mod seat;
+#[cfg(feature = "wayland-protocols")]
+mod tablet;
+pub mod protocols;
 mod theme;
+#[cfg(feature = "cursor_theme")]
 mod themed_pointer;
+mod timer;
+mod tooltip;
+mod touch;
+mod widget;
+mod window;
+mod window_menu;
 mod shell;
+mod shm;
+#[cfg(test)]
+mod test_support;
 
-pub use frame::{Frame, State};
-use pointer::{Pointer, PointerState};
-pub use shell::{Configure, Shell};
-use std::cell::RefCell;
+pub use activation::activation_token_from_env;
+pub use axis::{AxisEvent, AxisImplementation};
+pub use builder::FrameBuilder;
+pub use channel::{FrameEvent, FrameEventSender};
+#[allow(deprecated)]
+pub use compat::{init_decorated_surface, DecoratedSurface, DecoratedSurfaceImplementation};
+#[cfg(feature = "wayland-protocols")]
+pub use dmabuf::{Dmabuf, DmabufImplementation, DRM_FORMAT_MOD_INVALID, DRM_FORMAT_MOD_LINEAR};
+#[cfg(feature = "egl")]
+pub use egl::{DecorationLayout, EglImplementation, TitlebarButton};
+pub use frame::{BorderSizes, ButtonLayout, ButtonSet, DecorationMode, DrawError, FeatureError, Frame,
+                FrameAccessibility, FrameConfig, PointerConfig, State, TitlebarAction, UndecorateError,
+                UndecoratedWindow, WmCapabilities};
+pub use handle::FrameHandle;
+pub use manager::DecorationManager;
+pub use output::{OutputInfo, OutputList};
+use pointer::PointerState;
+pub use pool::ShrinkPolicy;
+pub use popup::{Popup, PopupError, PopupImplementation, PopupPosition};
+#[cfg(feature = "wayland-protocols")]
+pub use presentation::PresentationFeedback;
+pub use region::Region;
+pub use shell::{Configure, Shell, ShellObjects, ShellSurface, StateSet, WindowState};
+pub use shm::{ShmError, ShmFormats};
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
-pub use theme::{add_borders, subtract_borders};
-use themed_pointer::ThemedPointer;
-use wayland_client::{EventQueueHandle, Proxy};
-use wayland_client::protocol::*;
+#[allow(deprecated)]
+pub use theme::{add_borders, compute_location, subtract_borders};
+use wayland_client::EventQueueHandle;
+use wayland_client::protocol::wl_shell_surface;
+pub use widget::{WidgetError, WidgetId, WidgetImplementation, WidgetSide, WidgetState, MAX_WIDGETS};
+pub use window::{Window, WindowError, WindowEvent, WindowEvents};
 
+/// A part of a decorated window, as computed by `compute_location`
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub(crate) enum Location {
+pub enum Location {
+    /// Outside of the window
     None,
+    /// The top border
     Top,
+    /// The top-right corner
     TopRight,
+    /// The right border
     Right,
+    /// The bottom-right corner
     BottomRight,
+    /// The bottom border
     Bottom,
+    /// The bottom-left corner
     BottomLeft,
+    /// The left border
     Left,
+    /// The top-left corner
     TopLeft,
+    /// The titlebar, outside of its buttons, dragging it moves the window
     TopBar,
+    /// The contents of the window
     Inside,
+    /// A button of the titlebar
     Button(UIButton),
+    /// A widget of the titlebar
+    Widget(WidgetId),
+}
+
+impl Location {
+    /// The edge to resize the window from when this part is dragged, if any
+    ///
+    /// This is the value to give to `wl_shell_surface::resize`, the frames map it to
+    /// the matching xdg resize edge on the xdg shells.
+    pub fn resize_direction(&self) -> Option<wl_shell_surface::Resize> {
+        use self::wl_shell_surface::Resize;
+        match *self {
+            Location::Top => Some(Resize::Top),
+            Location::TopLeft => Some(Resize::TopLeft),
+            Location::Left => Some(Resize::Left),
+            Location::BottomLeft => Some(Resize::BottomLeft),
+            Location::Bottom => Some(Resize::Bottom),
+            Location::BottomRight => Some(Resize::BottomRight),
+            Location::Right => Some(Resize::Right),
+            Location::TopRight => Some(Resize::TopRight),
+            _ => None,
+        }
+    }
+
+    /// Whether the decorations look different when this part is hovered
+    pub(crate) fn is_hoverable(&self) -> bool {
+        matches!(*self, Location::Button(_) | Location::Widget(_))
+    }
 }
 
+/// A button of the titlebar
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub(crate) enum UIButton {
+pub enum UIButton {
+    /// Minimizes the window
     Minimize,
+    /// Toggles the maximized state of the window
     Maximize,
+    /// Requests the window to be closed
     Close,
+    /// Toggles whether the window is kept above the others
+    Pin,
+}
+
+impl UIButton {
+    pub(crate) const ALL: [UIButton; 4] = [
+        UIButton::Minimize,
+        UIButton::Maximize,
+        UIButton::Close,
+        UIButton::Pin,
+    ];
 }
 
 pub(crate) struct FrameIData<ID> {
     pub(crate) implementation: FrameImplementation<ID>,
     pub(crate) meta: Arc<Mutex<::frame::FrameMetadata>>,
     pub(crate) idata: Rc<RefCell<ID>>,
+    // configure of the xdg toplevel, given to the user with the serial of the xdg surface
+    pub(crate) pending_configure: Rc<RefCell<Option<PendingConfigure>>>,
+    // states of the window given with the last configure
+    pub(crate) states: Rc<Cell<shell::StateSet>>,
 }
 
+/// Ask the user whether to close the window, and to refresh the frame to withdraw it if so
+pub(crate) fn request_close<ID>(evqh: &mut EventQueueHandle, implementation: &FrameImplementation<ID>,
+                                idata: &RefCell<ID>, meta: &Mutex<::frame::FrameMetadata>) {
+    let mut user_idata = idata.borrow_mut();
+    if (implementation.close)(evqh, &mut *user_idata) == CloseDecision::Close {
+        log_debug!("window closed");
+        meta.lock().unwrap().closed = true;
+        (implementation.refresh)(evqh, &mut *user_idata);
+    }
+}
+
+// configure data and new size given to `FrameImplementation::configure`
+pub(crate) type PendingConfigure = (shell::Configure, Option<(i32, i32)>);
+
 pub(crate) struct PointerIData<ID> {
     pub(crate) implementation: FrameImplementation<ID>,
     pub(crate) pstate: PointerState,
@@ -185,10 +453,40 @@ pub(crate) struct PointerIData<ID> {
 impl<ID> Clone for FrameIData<ID> {
     fn clone(&self) -> FrameIData<ID> {
         FrameIData {
-            implementation: self.implementation.clone(),
+            implementation: self.implementation,
             meta: self.meta.clone(),
             idata: self.idata.clone(),
+            pending_configure: self.pending_configure.clone(),
+            states: self.states.clone(),
+        }
+    }
+}
+
+impl<ID> FrameIData<ID> {
+    /// Ask the user to refresh the frame
+    pub(crate) fn refresh(&self, evqh: &mut EventQueueHandle) {
+        let mut user_idata = self.idata.borrow_mut();
+        (self.implementation.refresh)(evqh, &mut *user_idata);
+    }
+
+    /// Ask the user whether to close the window, see `request_close`
+    pub(crate) fn close(&self, evqh: &mut EventQueueHandle) {
+        request_close(evqh, &self.implementation, &self.idata, &self.meta);
+    }
+
+    /// Give a configure to the user, after the changes of the states of the window
+    pub(crate) fn configure(&self, evqh: &mut EventQueueHandle, configure: shell::Configure,
+                            newsize: Option<(i32, i32)>) {
+        let states = configure.states();
+        let old_states = self.states.replace(states);
+        let mut user_idata = self.idata.borrow_mut();
+        for state in old_states.iter().filter(|&state| !states.contains(state)) {
+            (self.implementation.state_changed)(evqh, &mut *user_idata, state, false);
+        }
+        for state in states.iter().filter(|&state| !old_states.contains(state)) {
+            (self.implementation.state_changed)(evqh, &mut *user_idata, state, true);
         }
+        (self.implementation.configure)(evqh, &mut *user_idata, configure, newsize);
     }
 }
 
@@ -196,15 +494,35 @@ impl<ID> Clone for FrameIData<ID> {
 pub struct FrameImplementation<ID> {
     /// Called whenever the Frame has been resized.
     ///
+    /// `cfg` holds the details of the configure, like its serial and the states of the window.
+    ///
     /// **Note:** if you've not set a minimum size, `width` and `height` will not always be
     /// positive values. Values can be negative if a user attempts to resize the window past
     /// the left or top borders.
     pub configure:
         fn(evqh: &mut EventQueueHandle, idata: &mut ID, cfg: shell::Configure, newsize: Option<(i32, i32)>),
-    /// Called when the Frame is closed.
-    pub close: fn(evqh: &mut EventQueueHandle, idata: &mut ID),
+    /// Called when the user asks to close the window, with its close button or the compositor
+    ///
+    /// Return `CloseDecision::Close` to close the window right away, or `Ignore` to keep it,
+    /// to ask the user to save their changes for example. The window can then be closed
+    /// later with `Frame::close`.
+    pub close: fn(evqh: &mut EventQueueHandle, idata: &mut ID) -> CloseDecision,
     /// Called when the Frame wants to be refreshed
     pub refresh: fn(evqh: &mut EventQueueHandle, idata: &mut ID),
+    /// Called when a state of the window is set or unset by a configure
+    ///
+    /// `active` tells whether the state was set. The changes are reported just before
+    /// the `configure` call bringing them.
+    pub state_changed: fn(evqh: &mut EventQueueHandle, idata: &mut ID, state: WindowState, active: bool),
+}
+
+/// What to do when the user asks to close a window, see `FrameImplementation::close`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CloseDecision {
+    /// Close the window: it is withdrawn by the refresh of its frame, which is asked for
+    Close,
+    /// Keep the window open
+    Ignore,
 }
 
 impl<ID> Copy for FrameImplementation<ID> {}
@@ -213,63 +531,3 @@ impl<ID> Clone for FrameImplementation<ID> {
         *self
     }
 }
-
-/// Create a decoration frame for a wl_surface
-///
-/// This will create a decoration and declare it as a shell surface to
-/// the wayland compositor.
-///
-/// See crate documentations for details about how to use it.
-pub fn create_frame<ID: 'static>(evqh: &mut EventQueueHandle, implementation: FrameImplementation<ID>,
-                                 idata: ID, surface: &wl_surface::WlSurface, width: i32, height: i32,
-                                 compositor: &wl_compositor::WlCompositor,
-                                 subcompositor: &wl_subcompositor::WlSubcompositor, shm: &wl_shm::WlShm,
-                                 shell: &Shell, seat: Option<wl_seat::WlSeat>)
-                                 -> Result<Frame, ()> {
-    // create the frame
-    let mut frame = Frame::new(
-        surface,
-        width,
-        height,
-        compositor,
-        subcompositor,
-        shm,
-        shell,
-    )?;
-
-    let frame_idata = FrameIData {
-        implementation: implementation,
-        meta: frame.meta.clone(),
-        idata: Rc::new(RefCell::new(idata)),
-    };
-
-    // create the pointer
-    if let Some(seat) = seat {
-        let pointer = seat.get_pointer().expect("Received a defunct seat.");
-        frame.pointer = pointer.clone();
-        let pointer = ThemedPointer::load(pointer, None, &compositor, &shm)
-            .map(Pointer::Themed)
-            .unwrap_or_else(Pointer::Plain);
-        let pstate = PointerState::new(
-            frame.meta.clone(),
-            pointer,
-            frame.surface.clone().unwrap(),
-            frame.shell_surface.clone().unwrap(),
-            seat,
-        );
-        let pointer_idata = PointerIData {
-            implementation: implementation,
-            pstate: pstate,
-            idata: frame_idata.idata.clone(),
-        };
-        evqh.register(
-            frame.pointer.as_ref().unwrap(),
-            ::pointer::pointer_implementation(),
-            pointer_idata,
-        );
-    }
-
-    frame.shell_surface.register_to(evqh, frame_idata);
-
-    Ok(frame)
-}
@@ -0,0 +1,25 @@
+// Logging of the decisions taken by the frames, with the `log` feature
+//
+// The messages are logged with the `log` crate under the path of the module logging
+// them, like `wayland_window::frame`. Without the feature, the arguments are still
+// type-checked but nothing is evaluated.
+
+#[cfg(feature = "log")]
+macro_rules! log_debug {
+    ($($arg:tt)+) => { ::log::debug!($($arg)+) };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! log_debug {
+    ($($arg:tt)+) => { if false { let _ = format_args!($($arg)+); } };
+}
+
+#[cfg(feature = "log")]
+macro_rules! log_trace {
+    ($($arg:tt)+) => { ::log::trace!($($arg)+) };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! log_trace {
+    ($($arg:tt)+) => { if false { let _ = format_args!($($arg)+); } };
+}
@@ -0,0 +1,770 @@
+use {FrameIData, FrameImplementation, PointerIData};
+use channel::FrameEvent;
+#[cfg(feature = "wayland-protocols")]
+use dmabuf::{DmabufImplementation, DmabufPool};
+#[cfg(feature = "wayland-protocols")]
+use frame::FeatureError;
+use frame::{Frame, Toplevel};
+use keyboard::{FrameKeyboard, KeyboardState, KeyboardTarget};
+#[cfg(feature = "cursor_theme")]
+use output::OutputList;
+use pointer::{Pointer, PointerState};
+use pool::{Pool, ShrinkPolicy};
+use popup::{Popup, PopupError, PopupImplementation, PopupPosition};
+use protocols::blur::client::org_kde_kwin_blur_manager::OrgKdeKwinBlurManager;
+use protocols::plasma_shell::client::org_kde_plasma_shell::OrgKdePlasmaShell;
+use protocols::single_pixel_buffer::v1::client::wp_single_pixel_buffer_manager_v1 as single_pixel_buffer;
+use protocols::xdg_activation::v1::client::xdg_activation_v1::XdgActivationV1;
+use protocols::xdg_decoration::v1::client::zxdg_decoration_manager_v1::ZxdgDecorationManagerV1;
+use seat::{FramePointer, SeatState};
+use shell::{self, Shell, ShellObjects};
+use shm::{ShmError, ShmFormats};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::mpsc::Receiver;
+#[cfg(feature = "wayland-protocols")]
+use tablet::{FrameTool, TabletState, ToolTarget};
+#[cfg(feature = "cursor_theme")]
+use themed_pointer::{CursorThemes, ThemedPointer};
+use touch::FrameTouch;
+use wayland_client::{EventQueueHandle, Proxy};
+use wayland_client::protocol::*;
+#[cfg(feature = "wayland-protocols")]
+use wayland_protocols::unstable::linux_dmabuf::v1::client::zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1;
+#[cfg(feature = "wayland-protocols")]
+use wayland_protocols::unstable::tablet::v2::client::zwp_tablet_manager_v2::ZwpTabletManagerV2;
+#[cfg(feature = "wayland-protocols")]
+use wayland_protocols::unstable::xdg_foreign::v2::client::zxdg_exporter_v2::ZxdgExporterV2;
+#[cfg(feature = "wayland-protocols")]
+use wayland_protocols::unstable::xdg_foreign::v2::client::zxdg_importer_v2::ZxdgImporterV2;
+#[cfg(feature = "wayland-protocols")]
+use wayland_protocols::presentation_time::client::wp_presentation::WpPresentation;
+#[cfg(feature = "wayland-protocols")]
+use wayland_protocols::viewporter::client::wp_viewporter;
+use window_menu::MenuContext;
+#[cfg(feature = "misc_protocols")]
+use wayland_protocols::misc::server_decoration::client::org_kde_kwin_server_decoration_manager::OrgKdeKwinServerDecorationManager;
+
+/// A factory of decorated frames sharing their resources
+///
+/// Every frame needs a shm pool to draw its decorations and a cursor theme
+/// for the pointer hovering them. Frames created from the same manager
+/// share these, which is cheaper for applications with many windows.
+///
+/// A manager created with `shell_only` has neither, its frames only manage their
+/// shell surface.
+///
+/// The resources stay alive as long as any of the frames created from
+/// the manager does, even if the manager itself is dropped.
+pub struct DecorationManager {
+    compositor: wl_compositor::WlCompositor,
+    subcompositor: Option<wl_subcompositor::WlSubcompositor>,
+    shell: Shell,
+    // whether the shell pings and the seat capabilities are handled
+    initialized: Cell<bool>,
+    seat: Option<wl_seat::WlSeat>,
+    seat_state: Rc<RefCell<SeatState>>,
+    #[cfg(feature = "wayland-protocols")]
+    tablet_manager: Option<ZwpTabletManagerV2>,
+    #[cfg(feature = "wayland-protocols")]
+    tablet_state: Rc<RefCell<TabletState>>,
+    keyboard_state: Option<Rc<RefCell<KeyboardState>>>,
+    // the decorations of the frames are drawn in it, unless they only manage their shell surface
+    pool: Option<Rc<RefCell<Pool>>>,
+    #[cfg(feature = "cursor_theme")]
+    cursor_theme: Option<CursorThemes>,
+    #[cfg(feature = "wayland-protocols")]
+    viewporter: Option<wp_viewporter::WpViewporter>,
+    xdg_decoration_manager: Option<ZxdgDecorationManagerV1>,
+    activation: Option<XdgActivationV1>,
+    #[cfg(feature = "wayland-protocols")]
+    xdg_exporter: Option<ZxdgExporterV2>,
+    #[cfg(feature = "wayland-protocols")]
+    xdg_importer: Option<ZxdgImporterV2>,
+    plasma_shell: Option<OrgKdePlasmaShell>,
+    blur_manager: Option<OrgKdeKwinBlurManager>,
+    #[cfg(feature = "wayland-protocols")]
+    presentation: Option<WpPresentation>,
+    #[cfg(feature = "misc_protocols")]
+    kde_decoration_manager: Option<OrgKdeKwinServerDecorationManager>,
+}
+
+impl DecorationManager {
+    /// Create a new decoration manager
+    ///
+    /// The frames created from it will react to the pointer of `seat`, if any. The
+    /// manager implements the seat to follow its capabilities, the pointer of the
+    /// frames is created and released as the seat gains and loses it.
+    ///
+    /// The cursor theme used over the decorations can be specified as a name and
+    /// size with the `cursor_theme` argument, otherwise the one configured in the
+    /// environment is used. It is ignored without the `cursor_theme` feature.
+    ///
+    /// The decorations are drawn in a subsurface, compositors without `wl_subcompositor`
+    /// can be given `None`. The frames then only manage their shell surface: they follow
+    /// its configure events, title and states, but are never decorated by this crate and
+    /// do not react to the seat.
+    #[allow(clippy::result_unit_err)]
+    pub fn new(compositor: &wl_compositor::WlCompositor,
+               subcompositor: Option<&wl_subcompositor::WlSubcompositor>,
+               shm: &wl_shm::WlShm, shell: &Shell, seat: Option<wl_seat::WlSeat>,
+               cursor_theme: Option<(&str, u32)>)
+               -> Result<DecorationManager, ()> {
+        let pool = Pool::new(shm)?;
+        #[cfg(feature = "cursor_theme")]
+        let cursor_theme = if seat.is_some() {
+            CursorThemes::load(cursor_theme, shm)
+        } else {
+            None
+        };
+        #[cfg(not(feature = "cursor_theme"))]
+        let _ = cursor_theme;
+        Ok(DecorationManager {
+            compositor: compositor.clone().ok_or(())?,
+            subcompositor: match subcompositor {
+                Some(subcompositor) => Some(subcompositor.clone().ok_or(())?),
+                None => None,
+            },
+            shell: shell.clone().ok_or(())?,
+            initialized: Cell::new(false),
+            seat,
+            seat_state: Rc::new(RefCell::new(SeatState::new())),
+            #[cfg(feature = "wayland-protocols")]
+            tablet_manager: None,
+            #[cfg(feature = "wayland-protocols")]
+            tablet_state: Rc::new(RefCell::new(TabletState::new())),
+            keyboard_state: None,
+            pool: Some(Rc::new(RefCell::new(pool))),
+            #[cfg(feature = "cursor_theme")]
+            cursor_theme,
+            #[cfg(feature = "wayland-protocols")]
+            viewporter: None,
+            xdg_decoration_manager: None,
+            activation: None,
+            #[cfg(feature = "wayland-protocols")]
+            xdg_exporter: None,
+            #[cfg(feature = "wayland-protocols")]
+            xdg_importer: None,
+            plasma_shell: None,
+            blur_manager: None,
+            #[cfg(feature = "wayland-protocols")]
+            presentation: None,
+            #[cfg(feature = "misc_protocols")]
+            kde_decoration_manager: None,
+        })
+    }
+
+    /// Create a manager whose frames only manage their shell surface
+    ///
+    /// The frames follow the configure events and states of their shell surface, and
+    /// can set its title, app id, size limits and state, or start an interactive move or
+    /// resize with `Frame::start_move` and `Frame::start_resize`. They never create any
+    /// surface or buffer for decorations, and do not react to any seat: the application
+    /// draws its window entirely, and commits its surface itself.
+    ///
+    /// This is for applications using this crate as an abstraction over the shells. The
+    /// setters of the drawing resources, like `set_shm_formats`, have no effect on such a
+    /// manager.
+    #[allow(clippy::result_unit_err)]
+    pub fn shell_only(compositor: &wl_compositor::WlCompositor, shell: &Shell)
+                      -> Result<DecorationManager, ()> {
+        Ok(DecorationManager {
+            compositor: compositor.clone().ok_or(())?,
+            subcompositor: None,
+            shell: shell.clone().ok_or(())?,
+            initialized: Cell::new(false),
+            seat: None,
+            seat_state: Rc::new(RefCell::new(SeatState::new())),
+            #[cfg(feature = "wayland-protocols")]
+            tablet_manager: None,
+            #[cfg(feature = "wayland-protocols")]
+            tablet_state: Rc::new(RefCell::new(TabletState::new())),
+            keyboard_state: None,
+            pool: None,
+            #[cfg(feature = "cursor_theme")]
+            cursor_theme: None,
+            #[cfg(feature = "wayland-protocols")]
+            viewporter: None,
+            xdg_decoration_manager: None,
+            activation: None,
+            #[cfg(feature = "wayland-protocols")]
+            xdg_exporter: None,
+            #[cfg(feature = "wayland-protocols")]
+            xdg_importer: None,
+            plasma_shell: None,
+            blur_manager: None,
+            #[cfg(feature = "wayland-protocols")]
+            presentation: None,
+            #[cfg(feature = "misc_protocols")]
+            kde_decoration_manager: None,
+        })
+    }
+
+    /// Set the policy used to give memory back once the decorations have become much smaller
+    ///
+    /// `None` disables shrinking, the memory used by the decorations then only ever
+    /// grows.
+    pub fn set_shrink_policy(&self, policy: Option<ShrinkPolicy>) {
+        if let Some(ref pool) = self.pool {
+            pool.borrow_mut().set_shrink_policy(policy);
+        }
+    }
+
+    /// Draw the decorations in a format supported by the compositor
+    ///
+    /// Without this, the compositor is assumed to support Argb8888 buffers. Fails if
+    /// it supports neither Argb8888 nor Xrgb8888, the decorations cannot be drawn then.
+    ///
+    /// This must be set before the first frame is created.
+    pub fn set_shm_formats(&self, formats: &ShmFormats) -> Result<(), ShmError> {
+        let format = formats.decoration_format()?;
+        if let Some(ref pool) = self.pool {
+            pool.borrow_mut().set_format(format);
+        }
+        Ok(())
+    }
+
+    /// Use the single-pixel-buffer protocol for the decorations
+    ///
+    /// When the decorations are hidden, the frames then display single-pixel
+    /// buffers rather than allocating transparent buffers in shared memory.
+    pub fn set_single_pixel_buffer_manager(&self,
+                                           manager: single_pixel_buffer::WpSinglePixelBufferManagerV1) {
+        if let Some(ref pool) = self.pool {
+            pool.borrow_mut().set_single_pixel_buffer_manager(manager);
+        }
+    }
+
+    /// Allocate the buffers of the decorations as dmabufs
+    ///
+    /// The compositor can then scan them out or sample them without copying shared
+    /// memory. The decorations are still drawn on the CPU, in the buffers allocated by
+    /// `implementation`, shared memory is used when it fails to allocate one.
+    ///
+    /// Fails with `FeatureError::Unsupported` if `dmabuf` is older than version 2, which
+    /// creates the buffers without a roundtrip, and with `FeatureError::Undecorated` if the
+    /// manager was created with `shell_only`.
+    #[cfg(feature = "wayland-protocols")]
+    pub fn set_dmabuf_allocator<ID: 'static>(&self, dmabuf: &ZwpLinuxDmabufV1,
+                                             implementation: DmabufImplementation<ID>, idata: ID)
+                                             -> Result<(), FeatureError> {
+        let pool = self.pool.as_ref().ok_or(FeatureError::Undecorated)?;
+        let dmabuf = DmabufPool::new(dmabuf, implementation, idata).map_err(|_| FeatureError::Unsupported)?;
+        pool.borrow_mut().set_dmabuf(dmabuf);
+        Ok(())
+    }
+
+    /// Stretch the decorations during interactive resizes
+    ///
+    /// Frames created afterwards will use the viewporter to cheaply scale their
+    /// previous decorations while the user is resizing the window, and only draw
+    /// them again once the resize is over. This keeps resizes smooth on slow
+    /// machines, at the cost of distorted decorations during the resize.
+    ///
+    /// This requires the compositor to report interactive resizes, which only
+    /// xdg_shell does.
+    #[cfg(feature = "wayland-protocols")]
+    pub fn set_viewporter(&mut self, viewporter: wp_viewporter::WpViewporter) {
+        self.viewporter = Some(viewporter);
+    }
+
+    /// Let the compositor draw the decorations when it supports it
+    ///
+    /// Frames created afterwards with the stable xdg_shell will ask the compositor to
+    /// draw their decorations, and only draw them themselves if it refuses. See
+    /// `Frame::decoration_mode` to know who ends up drawing them.
+    pub fn set_xdg_decoration_manager(&mut self, manager: ZxdgDecorationManagerV1) {
+        self.xdg_decoration_manager = Some(manager);
+    }
+
+    /// Let KWin draw the decorations
+    ///
+    /// Like `set_xdg_decoration_manager`, but using KDE's server decoration protocol
+    /// which works with all shells. The xdg-decoration protocol is preferred when
+    /// both are available.
+    #[cfg(feature = "misc_protocols")]
+    pub fn set_kde_decoration_manager(&mut self, manager: OrgKdeKwinServerDecorationManager) {
+        self.kde_decoration_manager = Some(manager);
+    }
+
+    /// Let the frames request the focus
+    ///
+    /// Frames created afterwards can use `Frame::request_activation` and
+    /// `Frame::request_activation_token`.
+    pub fn set_xdg_activation(&mut self, activation: XdgActivationV1) {
+        self.activation = Some(activation);
+    }
+
+    /// Let the frames export their window to other applications, with xdg-foreign
+    ///
+    /// Frames created afterwards can use `Frame::export`.
+    #[cfg(feature = "wayland-protocols")]
+    pub fn set_xdg_exporter(&mut self, exporter: ZxdgExporterV2) {
+        self.xdg_exporter = Some(exporter);
+    }
+
+    /// Let the frames have windows of other applications as parent, with xdg-foreign
+    ///
+    /// Frames created afterwards can use `Frame::set_foreign_parent`.
+    #[cfg(feature = "wayland-protocols")]
+    pub fn set_xdg_importer(&mut self, importer: ZxdgImporterV2) {
+        self.xdg_importer = Some(importer);
+    }
+
+    /// Let the frames be kept above the other windows, with KDE's Plasma shell extension
+    ///
+    /// Frames created afterwards report it with `WmCapabilities::keep_above`, and show
+    /// the pin button if it is enabled in their `ButtonSet`. See `Frame::set_keep_above`.
+    pub fn set_plasma_shell(&mut self, plasma_shell: OrgKdePlasmaShell) {
+        self.plasma_shell = Some(plasma_shell);
+    }
+
+    /// Let the frames blur what lies behind their decorations, with KDE's blur protocol
+    ///
+    /// Frames created afterwards can use `Frame::set_blur`.
+    pub fn set_blur_manager(&mut self, blur_manager: OrgKdeKwinBlurManager) {
+        self.blur_manager = Some(blur_manager);
+    }
+
+    /// Measure when the decorations are shown on screen
+    ///
+    /// Frames created afterwards ask the presentation-time protocol for feedback on
+    /// each redraw of their decorations, the last one is given by
+    /// `Frame::last_presentation`. Applications can use it to measure the latency of
+    /// the redraws, and to pace their own drawing on the refresh of the output.
+    #[cfg(feature = "wayland-protocols")]
+    pub fn set_presentation(&mut self, presentation: WpPresentation) {
+        self.presentation = Some(presentation);
+    }
+
+    /// Draw the cursors at the scale of the outputs they are shown on
+    ///
+    /// Frames created afterwards will load their cursor theme at the scale of the outputs
+    /// of `outputs` the cursors are on, so that they are not tiny on HiDPI monitors.
+    /// Without it, the cursors are always drawn at scale 1.
+    #[cfg(feature = "cursor_theme")]
+    pub fn set_output_list(&mut self, outputs: &OutputList) {
+        if let Some(ref mut themes) = self.cursor_theme {
+            themes.set_outputs(outputs.scales());
+        }
+    }
+
+    /// React to the tablet tools of the seat
+    ///
+    /// The tools then act on the decorations like the pointer does, the tip of the tool
+    /// taking the role of the left button: the frames can be moved and resized with a pen.
+    ///
+    /// This must be set before the first frame is created, and has no effect if the
+    /// manager was not given a seat.
+    #[cfg(feature = "wayland-protocols")]
+    pub fn set_tablet_manager(&mut self, manager: ZwpTabletManagerV2) {
+        self.tablet_manager = Some(manager);
+    }
+
+    /// Navigate the decorations with the keyboard of the seat
+    ///
+    /// When a frame has the keyboard focus, F10 focuses the buttons of its titlebar,
+    /// highlighted with a focus ring. Tab or the left and right arrows then move the focus
+    /// between the buttons, Enter or Space trigger the focused button, the up and down
+    /// arrows start resizing the window and Escape leaves the navigation.
+    ///
+    /// The keys are still sent to the application, which should ignore them while
+    /// `Frame::is_navigating` is true.
+    ///
+    /// This must be set before the first frame is created, and has no effect if the
+    /// manager was not given a seat.
+    pub fn set_keyboard_navigation(&mut self, enabled: bool) {
+        self.keyboard_state = if enabled {
+            Some(Rc::new(RefCell::new(KeyboardState::new())))
+        } else {
+            None
+        };
+    }
+
+    pub(crate) fn compositor(&self) -> &wl_compositor::WlCompositor {
+        &self.compositor
+    }
+
+    /// Create a decoration frame for a wl_surface
+    ///
+    /// This will create a decoration and declare it as a shell surface to
+    /// the wayland compositor.
+    ///
+    /// See crate documentations for details about how to use it.
+    #[allow(clippy::result_unit_err)]
+    pub fn create_frame<ID: 'static>(&self, evqh: &mut EventQueueHandle,
+                                     implementation: FrameImplementation<ID>, idata: ID,
+                                     surface: &wl_surface::WlSurface, width: i32, height: i32)
+                                     -> Result<Frame, ()> {
+        self.create_frame_with(evqh, implementation, idata, (surface, None), (width, height), |_| {})
+    }
+
+    /// Create a decoration frame sending its events to a channel
+    ///
+    /// This is an alternative to `create_frame`: rather than calling the functions of a
+    /// `FrameImplementation`, the frame sends them as `FrameEvent`s which the application
+    /// drains from the returned receiver in its main loop.
+    #[allow(clippy::result_unit_err)]
+    pub fn create_frame_with_channel(&self, evqh: &mut EventQueueHandle, surface: &wl_surface::WlSurface,
+                                     width: i32, height: i32)
+                                     -> Result<(Frame, Receiver<FrameEvent>), ()> {
+        let (sender, receiver) = ::channel::channel();
+        let frame = self.create_frame(
+            evqh,
+            ::channel::channel_implementation(),
+            sender,
+            surface,
+            width,
+            height,
+        )?;
+        Ok((frame, receiver))
+    }
+
+    /// Create a frame, `setup` being applied to it before its initial commit
+    ///
+    /// The frame adopts the shell objects of the surface if they are given.
+    pub(crate) fn create_frame_with<ID: 'static, F>(&self, evqh: &mut EventQueueHandle,
+                                                    implementation: FrameImplementation<ID>, idata: ID,
+                                                    (surface, adopted): (&wl_surface::WlSurface,
+                                                                         Option<ShellObjects>),
+                                                    (width, height): (i32, i32), setup: F)
+                                                    -> Result<Frame, ()>
+    where
+        F: FnOnce(&mut Frame),
+    {
+        let toplevel = match adopted {
+            Some(ref objects) if !objects.created_from(&self.shell) => return Err(()),
+            Some(objects) => Toplevel::Adopted(shell::Surface::adopt(objects)),
+            None => Toplevel::New(&self.shell),
+        };
+
+        if !self.initialized.replace(true) {
+            self.shell.answer_pings(evqh);
+            if let Some(ref seat) = self.seat {
+                if let Some(ref keyboard_state) = self.keyboard_state {
+                    self.seat_state
+                        .borrow_mut()
+                        .set_keyboard_state(keyboard_state.clone());
+                }
+                evqh.register(seat, ::seat::seat_implementation(), self.seat_state.clone());
+                #[cfg(feature = "wayland-protocols")]
+                {
+                    if let Some(ref manager) = self.tablet_manager {
+                        let tablet_seat = manager
+                            .get_tablet_seat(seat)
+                            .expect("The tablet manager cannot be destroyed.");
+                        evqh.register(
+                            &tablet_seat,
+                            ::tablet::tablet_seat_implementation(),
+                            self.tablet_state.clone(),
+                        );
+                    }
+                }
+            }
+        }
+
+        // create the frame
+        let mut frame = Frame::new(
+            surface,
+            width,
+            height,
+            &self.compositor,
+            self.subcompositor.as_ref(),
+            self.pool.clone(),
+            toplevel,
+        )?;
+
+        // without a subcompositor, the frame surface belongs to the user and there is
+        // nothing to draw or to interact with
+        let drawable = self.subcompositor.is_some();
+        #[cfg(feature = "wayland-protocols")]
+        {
+            if let (Some(viewporter), true) = (self.viewporter.as_ref(), drawable) {
+                let viewport = viewporter
+                    .get_viewport(&frame.surface)
+                    .expect("The viewporter cannot be destroyed.");
+                frame.viewport = Some(viewport);
+            }
+        }
+        if drawable {
+            evqh.register(
+                &frame.surface,
+                ::frame::surface_implementation(),
+                frame.outputs.clone(),
+            );
+        }
+        frame.activation = self.activation.as_ref().and_then(|activation| activation.clone());
+        #[cfg(feature = "wayland-protocols")]
+        {
+            frame.presentation = self.presentation.as_ref().and_then(|presentation| presentation.clone());
+            frame.xdg_exporter = self.xdg_exporter.as_ref().and_then(|exporter| exporter.clone());
+            frame.xdg_importer = self.xdg_importer.as_ref().and_then(|importer| importer.clone());
+        }
+        frame.blur_manager = self.blur_manager.as_ref().and_then(|manager| manager.clone());
+        if let Some(ref plasma_shell) = self.plasma_shell {
+            frame.plasma_surface = Some(plasma_shell.get_surface(frame.toplevel_surface()));
+            let mut meta = frame.meta.lock().unwrap();
+            let mut capabilities = meta.capabilities;
+            capabilities.keep_above = true;
+            meta.set_capabilities(capabilities);
+        }
+
+        let frame_idata = FrameIData {
+            implementation: implementation,
+            meta: frame.meta.clone(),
+            idata: Rc::new(RefCell::new(idata)),
+            pending_configure: Rc::new(RefCell::new(None)),
+            states: Rc::new(Cell::new(Default::default())),
+        };
+
+        // the decoration mode must be negotiated before the initial commit, frames are
+        // not decorated until `set_decorate` is called
+        if let (Some(manager), &mut shell::Surface::XdgStable(ref mut xdg)) =
+            (self.xdg_decoration_manager.as_ref(), &mut frame.shell_surface)
+        {
+            log_debug!("decoration mode negotiated with xdg-decoration");
+            let decoration = manager
+                .get_toplevel_decoration(&xdg.toplevel)
+                .expect("The decoration manager cannot be destroyed.");
+            let decoration_idata = shell::DecorationIData {
+                meta: frame.meta.clone(),
+                shell_surface: shell::Surface::XdgStable(xdg.clone().unwrap()),
+                refresh: refresh_callback(&frame_idata),
+            };
+            evqh.register(
+                &decoration,
+                shell::toplevel_decoration_implementation(),
+                decoration_idata,
+            );
+            xdg.decoration = Some(decoration);
+            xdg.set_server_decorations(false);
+        }
+
+        #[cfg(feature = "misc_protocols")]
+        {
+            let negotiated = match frame.shell_surface {
+                shell::Surface::XdgStable(ref xdg) => xdg.decoration.is_some(),
+                _ => false,
+            };
+            if let (Some(manager), false) = (self.kde_decoration_manager.as_ref(), negotiated) {
+                log_debug!("decoration mode negotiated with the KDE server decoration protocol");
+                let decoration = manager.create(frame.toplevel_surface());
+                let decoration_idata = shell::DecorationIData {
+                    meta: frame.meta.clone(),
+                    shell_surface: frame.shell_surface.clone().unwrap(),
+                    refresh: refresh_callback(&frame_idata),
+                };
+                evqh.register(
+                    &decoration,
+                    shell::kde_decoration_implementation(),
+                    decoration_idata,
+                );
+                frame.kde_decoration = Some(decoration);
+                frame.request_kde_decoration(false);
+            }
+        }
+
+        // each frame gets its own slot for the theme, to replace it with set_cursor_theme
+        #[cfg(feature = "cursor_theme")]
+        {
+            frame.cursor_theme = match (self.cursor_theme.as_ref(), drawable) {
+                (Some(themes), true) => Some(Rc::new(RefCell::new(themes.share()))),
+                _ => None,
+            };
+        }
+
+        // the tools are handled by the tablet seat, focusing the frame they hover
+        #[cfg(feature = "wayland-protocols")]
+        {
+            if let (Some(seat), true) = (self.seat.as_ref(), drawable && self.tablet_manager.is_some()) {
+                let mut pstate = PointerState::new(
+                    frame.meta.clone(),
+                    Pointer::Tool,
+                    frame.surface.clone().unwrap(),
+                    frame.shell_surface.clone().unwrap(),
+                    seat.clone().unwrap(),
+                );
+                if let Some(surface) = frame.plasma_surface.as_ref().and_then(|s| s.clone()) {
+                    pstate.set_plasma_surface(surface);
+                }
+                pstate.set_widgets(frame.widgets.clone());
+                pstate.set_axis_handler(frame.axis_handler.clone());
+                pstate.set_device(::pointer::InputDevice::Tablet);
+                let tool_idata = PointerIData {
+                    implementation,
+                    pstate,
+                    idata: frame_idata.idata.clone(),
+                };
+                let tool = FrameTool::new(
+                    tool_idata,
+                    #[cfg(feature = "cursor_theme")]
+                    frame.cursor_theme
+                        .clone()
+                        .map(|theme| (theme, self.compositor.clone().unwrap())),
+                );
+                let tool: Rc<RefCell<dyn ToolTarget>> = Rc::new(RefCell::new(tool));
+                self.tablet_state.borrow_mut().add_frame(&tool);
+                frame.tool_target = Some(tool);
+            }
+        }
+
+        let keyboard_state = self.keyboard_state.as_ref().filter(|_| drawable);
+        if let (Some(seat), Some(keyboard_state)) = (self.seat.as_ref(), keyboard_state) {
+            let mut pstate = PointerState::new(
+                frame.meta.clone(),
+                Pointer::Keyboard,
+                frame.surface.clone().unwrap(),
+                frame.shell_surface.clone().unwrap(),
+                seat.clone().unwrap(),
+            );
+            if let Some(surface) = frame.plasma_surface.as_ref().and_then(|s| s.clone()) {
+                pstate.set_plasma_surface(surface);
+            }
+            let keyboard_idata = PointerIData {
+                implementation,
+                pstate,
+                idata: frame_idata.idata.clone(),
+            };
+            let keyboard: Rc<RefCell<dyn KeyboardTarget>> =
+                Rc::new(RefCell::new(FrameKeyboard::new(keyboard_idata)));
+            keyboard_state.borrow_mut().add_frame(&keyboard);
+            frame.keyboard_target = Some(keyboard);
+        }
+
+        // the pointer and the touch are created whenever the seat has them
+        if let (Some(seat), true) = (self.seat.as_ref(), drawable) {
+            let seat = seat.clone().unwrap();
+            let compositor = self.compositor.clone().unwrap();
+            #[cfg(feature = "cursor_theme")]
+            let cursor_theme = frame.cursor_theme.clone();
+            let meta = frame.meta.clone();
+            let frame_surface = frame.surface.clone().unwrap();
+            let shell_surface = frame.shell_surface.clone().unwrap();
+            let idata = frame_idata.idata.clone();
+            let plasma_surface = frame.plasma_surface.as_ref().and_then(|s| s.clone());
+            let widgets = frame.widgets.clone();
+            let user_surface = frame.user_surface.clone().unwrap();
+            let drag_region = frame.drag_region.clone();
+            let axis_handler = frame.axis_handler.clone();
+            // popups are only available with the stable xdg_shell
+            let menu_context = match (&self.shell, self.pool.as_ref()) {
+                (Shell::XdgStable(wm_base), Some(pool)) => Some(Rc::new(MenuContext {
+                    wm_base: wm_base.clone().unwrap(),
+                    compositor: compositor.clone().unwrap(),
+                    pool: pool.clone(),
+                })),
+                _ => None,
+            };
+            // the pointer and the touch drive the same state, without a cursor for the touch
+            let pointer_seat = seat.clone().unwrap();
+            let pointer_idata = Rc::new(move |pointer: Pointer| {
+                let mut pstate = PointerState::new(
+                    meta.clone(),
+                    pointer,
+                    frame_surface.clone().unwrap(),
+                    shell_surface.clone().unwrap(),
+                    pointer_seat.clone().unwrap(),
+                );
+                if let Some(ref context) = menu_context {
+                    pstate.set_menu_context(context.clone());
+                }
+                if let Some(surface) = plasma_surface.as_ref().and_then(|s| s.clone()) {
+                    pstate.set_plasma_surface(surface);
+                }
+                pstate.set_widgets(widgets.clone());
+                pstate.set_drag_region(user_surface.clone().unwrap(), drag_region.clone());
+                pstate.set_axis_handler(axis_handler.clone());
+                PointerIData {
+                    implementation: implementation,
+                    pstate: pstate,
+                    idata: idata.clone(),
+                }
+            });
+            let touch_seat = seat.clone().unwrap();
+            let touch_idata = pointer_idata.clone();
+            let create = move |evqh: &mut EventQueueHandle| {
+                let pointer = seat.get_pointer().expect("Received a defunct seat.");
+                let frame_pointer = pointer.clone().unwrap();
+                #[cfg(feature = "cursor_theme")]
+                let pointer = match cursor_theme {
+                    Some(ref theme) => {
+                        Pointer::Themed(ThemedPointer::new(evqh, pointer, theme.clone(), &compositor))
+                    }
+                    None => Pointer::Plain(pointer),
+                };
+                #[cfg(not(feature = "cursor_theme"))]
+                let pointer = Pointer::Plain(pointer);
+                evqh.register(
+                    &frame_pointer,
+                    ::pointer::pointer_implementation(),
+                    pointer_idata(pointer),
+                );
+                frame_pointer
+            };
+            let create_touch = move |evqh: &mut EventQueueHandle| {
+                let touch = touch_seat.get_touch().expect("Received a defunct seat.");
+                let mut idata = touch_idata(Pointer::Touch);
+                idata.pstate.set_device(::pointer::InputDevice::Touch);
+                let frame_touch = FrameTouch::new(idata);
+                evqh.register(&touch, ::touch::touch_implementation(), frame_touch);
+                touch
+            };
+            let pointer = Rc::new(RefCell::new(FramePointer::new(
+                Box::new(create),
+                Box::new(create_touch),
+            )));
+            self.seat_state.borrow_mut().add_frame(evqh, &pointer);
+            frame.pointer = Some(pointer);
+        }
+
+        frame.refresh_callback = Some(Rc::from(refresh_callback(&frame_idata)));
+        if let shell::Surface::Wl(_) = frame.shell_surface {
+            let restore_idata = frame_idata.clone();
+            frame.restore_callback = Some(Rc::new(move |evqh: &mut EventQueueHandle| {
+                shell::restore_size(evqh, &restore_idata)
+            }));
+        }
+        frame.shell_surface.register_to(evqh, frame_idata);
+        setup(&mut frame);
+
+        // the initial commit, xdg_shell answers it with a configure, an adopted toplevel
+        // is committed by the user
+        if !frame.adopted {
+            frame.surface.commit();
+        }
+        frame.redraw(evqh);
+
+        Ok(frame)
+    }
+
+    /// Create a popup anchored to a frame
+    ///
+    /// `surface` becomes the surface of the popup, you need to attach a buffer to it
+    /// once the popup is configured to show it.
+    ///
+    /// Popups are only supported with the stable xdg_shell, this fails with other
+    /// shells, or if the size of the popup is not positive.
+    pub fn create_popup<ID: 'static>(&self, evqh: &mut EventQueueHandle,
+                                     implementation: PopupImplementation<ID>, idata: ID, parent: &Frame,
+                                     surface: &wl_surface::WlSurface, position: &PopupPosition)
+                                     -> Result<Popup, PopupError> {
+        match self.shell {
+            Shell::XdgStable(ref wm_base) => Popup::new(
+                evqh,
+                wm_base,
+                parent,
+                surface,
+                position,
+                implementation,
+                idata,
+            ),
+            _ => Err(PopupError::UnsupportedShell),
+        }
+    }
+}
+
+/// Ask the user to refresh a frame, from handlers that do not know its implementation
+fn refresh_callback<ID: 'static>(frame_idata: &FrameIData<ID>) -> Box<dyn Fn(&mut EventQueueHandle)> {
+    let frame_idata = frame_idata.clone();
+    Box::new(move |evqh| frame_idata.refresh(evqh))
+}
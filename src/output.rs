@@ -0,0 +1,198 @@
+use protocols::xdg_output::v1::client::zxdg_output_manager_v1::ZxdgOutputManagerV1;
+use protocols::xdg_output::v1::client::zxdg_output_v1::{self, ZxdgOutputV1};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wayland_client::{EventQueueHandle, Proxy};
+use wayland_client::protocol::wl_output;
+
+/// Description of an output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputInfo {
+    /// Name of the output given by the compositor, like `HDMI-A-1`
+    ///
+    /// Only known if the compositor supports version 2 of the xdg-output protocol.
+    pub name: Option<String>,
+    /// Human-readable description of the output, if the compositor gives one
+    pub description: Option<String>,
+    /// Manufacturer of the monitor
+    pub make: String,
+    /// Model of the monitor
+    pub model: String,
+    /// Position of the output in the compositor space
+    pub position: (i32, i32),
+    /// Size of the current mode of the output, in pixels
+    pub size: (i32, i32),
+    /// Scale factor of the output, surfaces drawn at this scale are shown without scaling
+    pub scale: i32,
+}
+
+impl Default for OutputInfo {
+    fn default() -> OutputInfo {
+        OutputInfo {
+            name: None,
+            description: None,
+            make: String::new(),
+            model: String::new(),
+            position: (0, 0),
+            size: (0, 0),
+            // outputs without the scale event are not scaled
+            scale: 1,
+        }
+    }
+}
+
+struct Output {
+    output: wl_output::WlOutput,
+    xdg_output: Option<ZxdgOutputV1>,
+    info: Rc<RefCell<OutputInfo>>,
+}
+
+/// A list of the outputs of the compositor
+///
+/// It keeps track of the names and geometry of the outputs given to it, to let
+/// you choose the output a window is made fullscreen on:
+///
+/// ```ignore
+/// if let Some(output) = outputs.find_by_name("HDMI-A-1") {
+///     frame.set_state(State::Fullscreen(Some(&output)));
+/// }
+/// ```
+pub struct OutputList {
+    // shared with the cursors of the frames, see `DecorationManager::set_output_list`
+    outputs: Rc<RefCell<Vec<Output>>>,
+    xdg_output_manager: Option<ZxdgOutputManagerV1>,
+}
+
+impl OutputList {
+    /// Create a new empty list
+    ///
+    /// The names of the outputs are only known with the xdg-output protocol.
+    pub fn new(xdg_output_manager: Option<ZxdgOutputManagerV1>) -> OutputList {
+        OutputList {
+            outputs: Rc::new(RefCell::new(Vec::new())),
+            xdg_output_manager,
+        }
+    }
+
+    /// Track a newly bound output
+    ///
+    /// The list implements the output, it should not be registered elsewhere.
+    pub fn add_output(&mut self, evqh: &mut EventQueueHandle, output: wl_output::WlOutput) {
+        let info = Rc::new(RefCell::new(OutputInfo::default()));
+        evqh.register(&output, output_implementation(), info.clone());
+        let xdg_output = self.xdg_output_manager.as_ref().map(|manager| {
+            let xdg_output = manager
+                .get_xdg_output(&output)
+                .expect("The xdg output manager cannot be destroyed.");
+            evqh.register(&xdg_output, xdg_output_implementation(), info.clone());
+            xdg_output
+        });
+        self.outputs.borrow_mut().push(Output {
+            output,
+            xdg_output,
+            info,
+        });
+    }
+
+    /// Stop tracking an output, when its global is removed
+    pub fn remove_output(&mut self, output: &wl_output::WlOutput) {
+        let position = self.outputs.borrow().iter().position(|o| o.output.equals(output));
+        if let Some(i) = position {
+            let output = self.outputs.borrow_mut().remove(i);
+            if let Some(ref xdg_output) = output.xdg_output {
+                xdg_output.destroy();
+            }
+            if output.output.version() >= 3 {
+                output.output.release();
+            }
+        }
+    }
+
+    /// Find an output from the name the compositor gave it
+    pub fn find_by_name(&self, name: &str) -> Option<wl_output::WlOutput> {
+        self.outputs
+            .borrow()
+            .iter()
+            .find(|o| o.info.borrow().name.as_ref().map(|n| &n[..]) == Some(name))
+            .and_then(|o| o.output.clone())
+    }
+
+    /// Description of a tracked output
+    pub fn info(&self, output: &wl_output::WlOutput) -> Option<OutputInfo> {
+        self.outputs
+            .borrow()
+            .iter()
+            .find(|o| o.output.equals(output))
+            .map(|o| o.info.borrow().clone())
+    }
+
+    #[cfg(feature = "cursor_theme")]
+    pub(crate) fn scales(&self) -> OutputScales {
+        OutputScales(self.outputs.clone())
+    }
+}
+
+/// The scales of the outputs of a list, shared with the cursors of the frames
+#[cfg(feature = "cursor_theme")]
+#[derive(Clone)]
+pub(crate) struct OutputScales(Rc<RefCell<Vec<Output>>>);
+
+#[cfg(feature = "cursor_theme")]
+impl OutputScales {
+    /// The largest scale of given outputs, 1 for the outputs not in the list
+    pub(crate) fn max_scale(&self, outputs: &[wl_output::WlOutput]) -> i32 {
+        self.0
+            .borrow()
+            .iter()
+            .filter(|o| outputs.iter().any(|output| o.output.equals(output)))
+            .map(|o| o.info.borrow().scale)
+            .fold(1, i32::max)
+    }
+}
+
+impl Drop for OutputList {
+    fn drop(&mut self) {
+        for output in self.outputs.borrow().iter() {
+            if let Some(ref xdg_output) = output.xdg_output {
+                xdg_output.destroy();
+            }
+        }
+    }
+}
+
+fn output_implementation() -> wl_output::Implementation<Rc<RefCell<OutputInfo>>> {
+    wl_output::Implementation {
+        geometry: |_, info, _, x, y, _, _, _, make, model, _| {
+            let mut info = info.borrow_mut();
+            info.position = (x, y);
+            info.make = make;
+            info.model = model;
+        },
+        mode: |_, info, _, flags, width, height, _| {
+            if flags.contains(wl_output::Mode::Current) {
+                info.borrow_mut().size = (width, height);
+            }
+        },
+        done: |_, _, _| {},
+        scale: |_, info, _, factor| {
+            info.borrow_mut().scale = factor;
+        },
+    }
+}
+
+fn xdg_output_implementation() -> zxdg_output_v1::Implementation<Rc<RefCell<OutputInfo>>> {
+    zxdg_output_v1::Implementation {
+        // the logical position accounts for the scale and transform of the outputs
+        logical_position: |_, info, _, x, y| {
+            info.borrow_mut().position = (x, y);
+        },
+        logical_size: |_, _, _, _, _| {},
+        done: |_, _, _| {},
+        name: |_, info, _, name| {
+            info.borrow_mut().name = Some(name);
+        },
+        description: |_, info, _, description| {
+            info.borrow_mut().description = Some(description);
+        },
+    }
+}
@@ -0,0 +1,16 @@
+use protocols::plasma_shell::client::org_kde_plasma_surface::{OrgKdePlasmaSurface, PanelBehavior, Role};
+
+/// Keep a window above the others, or put it back among them
+///
+/// The Plasma shell has no request for this, but KWin keeps the windows with the panel
+/// role above the others when they are set to let windows go below them. Such windows
+/// are treated like panels while they are kept above: they are not listed in the
+/// taskbar.
+pub(crate) fn set_keep_above(surface: &OrgKdePlasmaSurface, keep_above: bool) {
+    if keep_above {
+        surface.set_role(Role::Panel.to_raw());
+        surface.set_panel_behavior(PanelBehavior::WindowsGoBelow.to_raw());
+    } else {
+        surface.set_role(Role::Normal.to_raw());
+    }
+}
@@ -1,14 +1,46 @@
-use {shell, Location, PointerIData, UIButton};
+use {shell, FrameIData, Location, PointerIData, TitlebarAction, UIButton};
+use axis::{self, AxisEvent, AxisHandlerSlot};
 use frame::FrameMetadata;
+use protocols::plasma_shell::client::org_kde_plasma_surface::OrgKdePlasmaSurface;
+use region::Region;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
-use theme::compute_location;
+use theme::compute_location_with_widgets;
+#[cfg(feature = "cursor_theme")]
 use themed_pointer::ThemedPointer;
-use wayland_client::Proxy;
+use widget::{WidgetHandlers, WidgetId};
+use wayland_client::{EventQueueHandle, Proxy};
 use wayland_client::protocol::{wl_pointer, wl_seat, wl_shell_surface, wl_surface};
+use window_menu::{MenuContext, MenuItem, WindowMenu};
+
+// linux input event codes
+pub(crate) const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+
+/// The kind of device moving a pointer over the decorations
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum InputDevice {
+    Mouse,
+    // a tablet tool, less precise than a mouse
+    #[cfg_attr(not(feature = "wayland-protocols"), allow(dead_code))]
+    Tablet,
+    // a finger, the least precise
+    Touch,
+}
 
 pub(crate) enum Pointer {
     Plain(wl_pointer::WlPointer),
+    #[cfg(feature = "cursor_theme")]
     Themed(ThemedPointer),
+    // a tablet tool, without a cursor theme to set its cursor
+    #[cfg(feature = "wayland-protocols")]
+    Tool,
+    // the keyboard, navigating the decorations without a cursor
+    Keyboard,
+    // a touch point, without a cursor
+    Touch,
 }
 
 impl Drop for Pointer {
@@ -22,11 +54,34 @@ impl Drop for Pointer {
 pub(crate) struct PointerState {
     location: Location,
     coordinates: (f64, f64),
-    pointer: Pointer,
+    // only released when dropped if its cursor is not themed
+    #[cfg_attr(not(feature = "cursor_theme"), allow(dead_code))]
+    pub(crate) pointer: Pointer,
     shell_surface: shell::Surface,
-    frame_surface: wl_surface::WlSurface,
+    pub(crate) frame_surface: wl_surface::WlSurface,
     seat: wl_seat::WlSeat,
-    meta: Arc<Mutex<FrameMetadata>>,
+    pub(crate) meta: Arc<Mutex<FrameMetadata>>,
+    // time of the last click on the titlebar, to detect double-clicks
+    last_click: Option<u32>,
+    // serial and position of a press on the titlebar that may start a move
+    pending_move: Option<(u32, (f64, f64))>,
+    // to show a window menu when the compositor does not provide one
+    menu_context: Option<Rc<MenuContext>>,
+    menu: Option<WindowMenu>,
+    // whether the pointer is over the window menu
+    in_menu: bool,
+    // to keep the window above the others with the pin button
+    plasma_surface: Option<OrgKdePlasmaSurface>,
+    // the widgets of the titlebar, clicked by the pointer
+    widgets: Option<WidgetHandlers>,
+    // the surface of the user and the area of it moving the window when dragged
+    drag_region: Option<(wl_surface::WlSurface, Rc<RefCell<Region>>)>,
+    // whether the pointer is over the surface of the user
+    in_contents: bool,
+    // the handler of the scrolls over the decorations
+    axis_handler: Option<AxisHandlerSlot>,
+    // the device moving the pointer, giving the size of the resize zones
+    device: InputDevice,
 }
 
 impl PointerState {
@@ -42,48 +97,203 @@ impl PointerState {
             frame_surface: frame_surface,
             shell_surface: shell_surface,
             seat: seat,
+            last_click: None,
+            pending_move: None,
+            menu_context: None,
+            menu: None,
+            in_menu: false,
+            plasma_surface: None,
+            widgets: None,
+            drag_region: None,
+            in_contents: false,
+            axis_handler: None,
+            device: InputDevice::Mouse,
+        }
+    }
+
+    pub(crate) fn set_menu_context(&mut self, context: Rc<MenuContext>) {
+        self.menu_context = Some(context);
+    }
+
+    pub(crate) fn set_plasma_surface(&mut self, surface: OrgKdePlasmaSurface) {
+        self.plasma_surface = Some(surface);
+    }
+
+    pub(crate) fn set_widgets(&mut self, widgets: WidgetHandlers) {
+        self.widgets = Some(widgets);
+    }
+
+    pub(crate) fn set_drag_region(&mut self, user_surface: wl_surface::WlSurface,
+                                  region: Rc<RefCell<Region>>) {
+        self.drag_region = Some((user_surface, region));
+    }
+
+    pub(crate) fn set_axis_handler(&mut self, handler: AxisHandlerSlot) {
+        self.axis_handler = Some(handler);
+    }
+
+    pub(crate) fn set_device(&mut self, device: InputDevice) {
+        self.device = device;
+    }
+
+    /// Whether given surface is the one of the decorations, of the user or of the window menu
+    pub(crate) fn owns(&self, surface: &wl_surface::WlSurface) -> bool {
+        let in_contents = match self.drag_region {
+            Some((ref user_surface, _)) => user_surface.equals(surface),
+            None => false,
+        };
+        let in_menu = self.menu.as_ref().map(|menu| menu.owns(surface)).unwrap_or(false);
+        self.frame_surface.equals(surface) || in_contents || in_menu
+    }
+
+    /// Give a scroll over the decorations to the user, returns whether it consumed it
+    fn forward_axis(&self, evqh: &mut EventQueueHandle, event: AxisEvent) -> bool {
+        if self.in_menu || self.location == Location::None || self.location == Location::Inside {
+            return false;
+        }
+        match self.axis_handler {
+            Some(ref handler) => axis::forward(handler, evqh, self.location, event),
+            None => false,
+        }
+    }
+
+    fn toggle_keep_above(&self) {
+        if let Some(ref surface) = self.plasma_surface {
+            let mut meta = self.meta.lock().unwrap();
+            meta.keep_above = !meta.keep_above;
+            meta.need_redraw = true;
+            ::plasma::set_keep_above(surface, meta.keep_above);
+        }
+    }
+
+    /// Show the window menu drawn by the frame at the pointer
+    fn open_menu(&mut self, evqh: &mut EventQueueHandle, serial: u32) {
+        let parent = match self.shell_surface {
+            shell::Surface::XdgStable(ref xdg) => &xdg.surface,
+            _ => return,
+        };
+        if let Some(ref context) = self.menu_context {
+            let (x, y) = self.coordinates;
+            let meta = self.meta.lock().unwrap();
+            self.menu = WindowMenu::new(
+                evqh,
+                context,
+                parent,
+                &meta,
+                (x as i32, y as i32),
+                &self.seat,
+                serial,
+            );
+        }
+    }
+
+    /// Drop the window menu if the compositor dismissed it
+    fn close_dismissed_menu(&mut self) {
+        if self.menu.as_ref().map(|menu| menu.is_done()).unwrap_or(false) {
+            self.menu = None;
+            self.in_menu = false;
+        }
+    }
+
+    /// Start moving the window once the pointer has been dragged far enough
+    fn drag(&mut self) {
+        if let Some((serial, (x, y))) = self.pending_move {
+            let (dx, dy) = (self.coordinates.0 - x, self.coordinates.1 - y);
+            let threshold = f64::from(self.meta.lock().unwrap().config.pointer.drag_threshold);
+            if dx * dx + dy * dy >= threshold * threshold {
+                self.pending_move = None;
+                log_trace!("pointer dragged, moving the window");
+                self.shell_surface._move(&self.seat, serial);
+            }
+        }
+    }
+
+    /// Whether given point of the surface of the user is in its drag region
+    fn in_drag_region(&self, (x, y): (f64, f64)) -> bool {
+        match self.drag_region {
+            Some((_, ref region)) => region.borrow().contains(x, y),
+            None => false,
+        }
+    }
+
+    /// Whether a click at `time` on the titlebar completes a double-click
+    fn double_click(&mut self, time: u32) -> bool {
+        let interval = self.meta.lock().unwrap().config.pointer.double_click_interval;
+        match (self.last_click.take(), interval) {
+            (Some(last), Some(interval)) if time.wrapping_sub(last) <= interval => true,
+            _ => {
+                self.last_click = Some(time);
+                false
+            }
+        }
+    }
+
+    fn toggle_maximized(&self) {
+        let maximize = {
+            let mut meta = self.meta.lock().unwrap();
+            if meta.max_size.is_some() || !meta.capabilities.maximize {
+                // the window cannot be maximized, the button is greyed or hidden
+                return;
+            }
+            let maximize = !meta.maximized;
+            if let shell::Surface::Wl(_) = self.shell_surface {
+                // wl_shell applies the state without telling us
+                meta.wl_set_maximized(maximize);
+            }
+            maximize
+        };
+        if maximize {
+            self.shell_surface.set_maximized();
+        } else {
+            self.shell_surface.unset_maximized();
         }
     }
 
     fn pointer_entered(&mut self, surface: &wl_surface::WlSurface, serial: u32) {
+        self.in_contents = match self.drag_region {
+            Some((ref user_surface, _)) => user_surface.equals(surface),
+            None => false,
+        };
         if self.frame_surface.equals(surface) {
             self.update(Some(serial), true);
         } else {
             // A surface that we don't manage
-            self.meta.lock().unwrap().ptr_location = Location::None;
+            self.meta.lock().unwrap().set_ptr_location(Location::None);
             self.location = Location::None;
         }
     }
 
     fn pointer_left(&mut self, serial: u32) {
-        self.meta.lock().unwrap().ptr_location = Location::None;
+        {
+            let mut meta = self.meta.lock().unwrap();
+            if meta.ptr_location.is_hoverable() {
+                // a button was hovered, we need a redraw
+                meta.need_redraw = true;
+            }
+            meta.set_ptr_location(Location::None);
+        }
         self.location = Location::None;
         self.change_pointer(Location::None, Some(serial))
     }
 
-    fn update(&mut self, serial: Option<u32>, force: bool) -> bool {
+    fn update(&mut self, serial: Option<u32>, force: bool) {
         let mut meta = self.meta.lock().unwrap();
-        let new_location = if meta.decorate && !meta.fullscreen {
-            compute_location(self.coordinates, meta.dimensions)
-        } else {
-            Location::Inside
-        };
+        let new_location = pointer_location(&meta, self.coordinates, self.device);
 
         if new_location != self.location || force {
             // a button is hovered, we need a redraw
-            if let Location::Button(_) = self.location {
-                meta.need_redraw = true;
-            }
-            if let Location::Button(_) = new_location {
+            if self.location.is_hoverable() || new_location.is_hoverable() {
                 meta.need_redraw = true;
             }
             self.location = new_location;
             self.change_pointer(new_location, serial);
-            meta.ptr_location = new_location;
+            meta.set_ptr_location(new_location);
+        } else {
+            self.refresh_cursor_theme();
         }
-        return meta.need_redraw;
     }
 
+    #[cfg(feature = "cursor_theme")]
     fn change_pointer(&self, location: Location, serial: Option<u32>) {
         let name = match location {
             Location::Top => "top_side",
@@ -100,71 +310,311 @@ impl PointerState {
             themed.set_cursor(name, serial);
         }
     }
+
+    /// Without cursor themes, the cursor is left as is
+    #[cfg(not(feature = "cursor_theme"))]
+    fn change_pointer(&self, _location: Location, _serial: Option<u32>) {}
+
+    /// Take the cursor from the new theme, once `Frame::set_cursor_theme` replaced it
+    #[cfg(feature = "cursor_theme")]
+    fn refresh_cursor_theme(&self) {
+        if let Pointer::Themed(ref themed) = self.pointer {
+            themed.refresh_theme();
+        }
+    }
+
+    #[cfg(not(feature = "cursor_theme"))]
+    fn refresh_cursor_theme(&self) {}
+}
+
+impl<ID: 'static> PointerIData<ID> {
+    /// Ask for a refresh of the frame if it needs a redraw, scheduling
+    /// the hover animations of the buttons if needed
+    pub(crate) fn refresh_if_needed(&mut self, evqh: &mut EventQueueHandle) {
+        let need_redraw = self.pstate.meta.lock().unwrap().need_redraw;
+        if need_redraw {
+            let frame_idata = FrameIData {
+                implementation: self.implementation,
+                meta: self.pstate.meta.clone(),
+                idata: self.idata.clone(),
+                // the animations do not deal with configures
+                pending_configure: Rc::new(RefCell::new(None)),
+                states: Rc::new(Cell::new(Default::default())),
+            };
+            ::animation::schedule(evqh, &self.pstate.frame_surface, frame_idata);
+            let mut user_idata = self.idata.borrow_mut();
+            (self.implementation.refresh)(evqh, &mut *user_idata);
+        }
+    }
+
+    pub(crate) fn enter(&mut self, evqh: &mut EventQueueHandle, serial: u32, surface: &wl_surface::WlSurface,
+                        x: f64, y: f64) {
+        self.pstate.close_dismissed_menu();
+        if let Some(ref menu) = self.pstate.menu {
+            if menu.owns(surface) {
+                self.pstate.in_menu = true;
+                menu.hover(evqh, Some(y));
+                return;
+            }
+        }
+        self.pstate.in_menu = false;
+        self.pstate.coordinates = (x, y);
+        self.pstate.pointer_entered(surface, serial);
+        self.refresh_if_needed(evqh);
+    }
+
+    pub(crate) fn leave(&mut self, evqh: &mut EventQueueHandle, serial: u32) {
+        if self.pstate.in_menu {
+            self.pstate.in_menu = false;
+            if let Some(ref menu) = self.pstate.menu {
+                menu.hover(evqh, None);
+            }
+            return;
+        }
+        self.pstate.pending_move = None;
+        self.pstate.in_contents = false;
+        self.pstate.pointer_left(serial);
+        self.refresh_if_needed(evqh);
+    }
+
+    pub(crate) fn motion(&mut self, evqh: &mut EventQueueHandle, x: f64, y: f64) {
+        if self.pstate.in_menu {
+            if let Some(ref menu) = self.pstate.menu {
+                menu.hover(evqh, Some(y));
+            }
+            return;
+        }
+        if self.pstate.in_contents {
+            self.pstate.coordinates = (x, y);
+            self.pstate.drag();
+            return;
+        }
+        if self.pstate.location != Location::None {
+            self.pstate.coordinates = (x, y);
+            self.pstate.drag();
+            self.pstate.update(None, false);
+            self.refresh_if_needed(evqh);
+        }
+    }
+
+    pub(crate) fn button(&mut self, evqh: &mut EventQueueHandle, serial: u32, time: u32, button: u32,
+                         pressed: bool) {
+        self.pstate.close_dismissed_menu();
+        if self.pstate.in_menu {
+            if pressed && button == BTN_LEFT {
+                self.choose_menu_item(evqh, serial);
+            }
+            return;
+        }
+        if !pressed {
+            if button == BTN_LEFT {
+                // a simple click, the window is not moved
+                self.pstate.pending_move = None;
+            }
+            return;
+        }
+        if self.pstate.in_contents {
+            // the application handles the other clicks on its surface
+            if button == BTN_LEFT && self.pstate.in_drag_region(self.pstate.coordinates) {
+                self.pstate.pending_move = Some((serial, self.pstate.coordinates));
+            }
+            return;
+        }
+        if self.pstate.location == Location::TopBar && (button == BTN_RIGHT || button == BTN_MIDDLE) {
+            let action = {
+                let config = self.pstate.meta.lock().unwrap().config.pointer;
+                if button == BTN_RIGHT {
+                    config.titlebar_right_action
+                } else {
+                    config.titlebar_middle_action
+                }
+            };
+            self.titlebar_action(evqh, serial, action);
+            return;
+        }
+        if button != BTN_LEFT {
+            return;
+        }
+        if self.pstate.location != Location::TopBar {
+            self.pstate.last_click = None;
+        } else if self.pstate.double_click(time) {
+            self.pstate.toggle_maximized();
+            self.refresh_if_needed(evqh);
+            return;
+        }
+        log_trace!("pointer pressed on {:?}", self.pstate.location);
+        match compute_pointer_action(self.pstate.location) {
+            PointerAction::Resize(direction) => self.resize(serial, direction),
+            PointerAction::Move => {
+                // only move once the pointer is dragged, so that clicks are not swallowed
+                self.pstate.pending_move = Some((serial, self.pstate.coordinates));
+            }
+            PointerAction::Button(b) => self.activate(evqh, b),
+            PointerAction::Widget(id) => {
+                if let Some(ref widgets) = self.pstate.widgets {
+                    ::widget::click(widgets, evqh, id);
+                }
+            }
+            PointerAction::None => {}
+        }
+    }
+
+    /// Trigger the action of a click on the titlebar
+    fn titlebar_action(&mut self, evqh: &mut EventQueueHandle, serial: u32, action: TitlebarAction) {
+        log_trace!("titlebar clicked for {:?}", action);
+        match action {
+            TitlebarAction::None => {}
+            TitlebarAction::Menu => {
+                let window_menu = self.pstate.meta.lock().unwrap().capabilities.window_menu;
+                if window_menu {
+                    // let the compositor show its window menu
+                    let (x, y) = self.pstate.coordinates;
+                    self.pstate
+                        .shell_surface
+                        .show_window_menu(&self.pstate.seat, serial, x as i32, y as i32);
+                } else {
+                    self.pstate.open_menu(evqh, serial);
+                }
+            }
+            TitlebarAction::ToggleMaximize => self.activate(evqh, UIButton::Maximize),
+            TitlebarAction::Minimize => self.activate(evqh, UIButton::Minimize),
+            TitlebarAction::ToggleShade => {
+                {
+                    let mut meta = self.pstate.meta.lock().unwrap();
+                    if !meta.maximized {
+                        let shaded = !meta.shaded;
+                        meta.set_shaded(shaded);
+                    }
+                }
+                self.refresh_if_needed(evqh);
+            }
+        }
+    }
+
+    /// Trigger the item of the window menu under the pointer, closing the menu
+    fn choose_menu_item(&mut self, evqh: &mut EventQueueHandle, serial: u32) {
+        let item = match self.pstate.menu.as_ref().and_then(|menu| menu.hovered_item()) {
+            Some(item) => item,
+            None => return,
+        };
+        self.pstate.menu = None;
+        self.pstate.in_menu = false;
+        match item {
+            MenuItem::Move => self.pstate.shell_surface._move(&self.pstate.seat, serial),
+            MenuItem::Resize => self.resize(serial, wl_shell_surface::Resize::BottomRight),
+            MenuItem::Minimize => self.activate(evqh, UIButton::Minimize),
+            MenuItem::Maximize => self.activate(evqh, UIButton::Maximize),
+            MenuItem::Close => self.activate(evqh, UIButton::Close),
+        }
+    }
+
+    /// Trigger the action of a titlebar button
+    pub(crate) fn activate(&mut self, evqh: &mut EventQueueHandle, button: UIButton) {
+        log_trace!("{:?} button activated", button);
+        match button {
+            UIButton::Minimize => {
+                self.pstate.shell_surface.set_minimized();
+            }
+            UIButton::Maximize => {
+                self.pstate.toggle_maximized();
+                self.refresh_if_needed(evqh);
+            }
+            UIButton::Close => {
+                ::request_close(evqh, &self.implementation, &self.idata, &self.pstate.meta);
+            }
+            UIButton::Pin => {
+                self.pstate.toggle_keep_above();
+                self.refresh_if_needed(evqh);
+            }
+        }
+    }
+
+    /// Start an interactive resize of the window
+    pub(crate) fn resize(&self, serial: u32, direction: wl_shell_surface::Resize) {
+        log_trace!("interactive resize started from {:?}", direction);
+        self.pstate
+            .shell_surface
+            .resize(&self.pstate.seat, serial, direction);
+    }
 }
 
-pub(crate) fn pointer_implementation<ID>() -> wl_pointer::Implementation<PointerIData<ID>> {
+pub(crate) fn pointer_implementation<ID: 'static>() -> wl_pointer::Implementation<PointerIData<ID>> {
     wl_pointer::Implementation {
-        enter: |_, idata, _, serial, surface, x, y| {
-            idata.pstate.coordinates = (x, y);
-            idata.pstate.pointer_entered(surface, serial);
+        enter: |evqh, idata, _, serial, surface, x, y| {
+            idata.enter(evqh, serial, surface, x, y);
         },
-        leave: |_, idata, _, serial, _| {
-            idata.pstate.pointer_left(serial);
+        leave: |evqh, idata, _, serial, _| {
+            idata.leave(evqh, serial);
         },
-        motion: |evqh, idata, _, _, x, y| if idata.pstate.location != Location::None {
-            idata.pstate.coordinates = (x, y);
-            let need_redraw = idata.pstate.update(None, false);
-            if need_redraw {
-                let mut user_idata = idata.idata.borrow_mut();
-                (idata.implementation.refresh)(evqh, &mut *user_idata);
-            }
+        motion: |evqh, idata, _, _, x, y| {
+            idata.motion(evqh, x, y);
         },
-        button: |evqh, idata, _, serial, _, button, state| {
-            if button != 0x110 {
+        button: |evqh, idata, _, serial, time, button, state| {
+            let pressed = state == wl_pointer::ButtonState::Pressed;
+            idata.button(evqh, serial, time, button, pressed);
+        },
+        axis: |evqh, idata, _, time, axis, value| {
+            if idata.pstate.forward_axis(evqh, AxisEvent::Axis { time, axis, value }) {
                 return;
             }
-            if let wl_pointer::ButtonState::Released = state {
+            if idata.pstate.location != Location::TopBar || axis != wl_pointer::Axis::VerticalScroll {
                 return;
             }
-            match compute_pointer_action(idata.pstate.location) {
-                PointerAction::Resize(direction) => idata.pstate.shell_surface.resize(
-                    &idata.pstate.seat,
-                    serial,
-                    direction,
-                ),
-                PointerAction::Move => idata.pstate.shell_surface._move(&idata.pstate.seat, serial),
-                PointerAction::Button(b) => match b {
-                    UIButton::Minimize => {
-                        idata.pstate.shell_surface.set_minimized();
-                    }
-                    UIButton::Maximize => {
-                        let maximize = {
-                            let meta = idata.pstate.meta.lock().unwrap();
-                            if meta.max_size.is_some() {
-                                // there is a max size, the button is greyed
-                                return;
-                            }
-                            !meta.maximized
-                        };
-                        if maximize {
-                            idata.pstate.shell_surface.set_maximized();
-                        } else {
-                            idata.pstate.shell_surface.unset_maximized();
-                        }
-                    }
-                    UIButton::Close => {
-                        let mut user_idata = idata.idata.borrow_mut();
-                        (idata.implementation.close)(evqh, &mut *user_idata);
-                    }
-                },
-                PointerAction::None => {}
+            {
+                // scrolling up shades the window, scrolling down unshades it
+                let mut meta = idata.pstate.meta.lock().unwrap();
+                let shaded = value < 0.;
+                if meta.shaded == shaded || meta.maximized {
+                    return;
+                }
+                meta.set_shaded(shaded);
             }
+            idata.refresh_if_needed(evqh);
+        },
+        axis_discrete: |evqh, idata, _, axis, steps| {
+            idata.pstate.forward_axis(evqh, AxisEvent::Discrete { axis, steps });
         },
-        axis: |_, _, _, _, _, _| {},
-        axis_discrete: |_, _, _, _, _| {},
         axis_source: |_, _, _, _| {},
         axis_stop: |_, _, _, _, _| {},
-        frame: |_, _, _| {},
+        frame: |evqh, idata, _| {
+            idata.pstate.forward_axis(evqh, AxisEvent::Frame);
+        },
+    }
+}
+
+/// The part of the frame under the pointer, at given coordinates of the frame surface
+pub(crate) fn pointer_location(meta: &FrameMetadata, coordinates: (f64, f64), device: InputDevice)
+                               -> Location {
+    let grab_margin = meta.config.pointer.grab_margin(device);
+    if meta.is_shaded() {
+        let drawn = meta.drawn();
+        match compute_location_with_widgets(
+            coordinates,
+            drawn.dimensions,
+            &drawn.config,
+            &drawn.widgets,
+            grab_margin,
+        ) {
+            // shaded windows are not resizable
+            location if location.is_hoverable() || location == Location::None => location,
+            _ => Location::TopBar,
+        }
+    } else if meta.draws_borders() {
+        match compute_location_with_widgets(
+            coordinates,
+            meta.dimensions,
+            &meta.config,
+            &meta.widgets,
+            grab_margin,
+        ) {
+            // the titlebar is still drawn on a tiled top edge, but not resizable
+            Location::Top if meta.tiled.top => Location::TopBar,
+            Location::TopLeft if meta.tiled.top => Location::Left,
+            Location::TopRight if meta.tiled.top => Location::Right,
+            location => location,
+        }
+    } else {
+        Location::Inside
     }
 }
 
@@ -173,21 +623,119 @@ enum PointerAction {
     Move,
     None,
     Button(UIButton),
+    Widget(WidgetId),
 }
 
 fn compute_pointer_action(location: Location) -> PointerAction {
-    use self::wl_shell_surface::Resize;
+    if let Some(direction) = location.resize_direction() {
+        return PointerAction::Resize(direction);
+    }
     match location {
-        Location::Top => PointerAction::Resize(Resize::Top),
-        Location::TopLeft => PointerAction::Resize(Resize::TopLeft),
-        Location::Left => PointerAction::Resize(Resize::Left),
-        Location::BottomLeft => PointerAction::Resize(Resize::BottomLeft),
-        Location::Bottom => PointerAction::Resize(Resize::Bottom),
-        Location::BottomRight => PointerAction::Resize(Resize::BottomRight),
-        Location::Right => PointerAction::Resize(Resize::Right),
-        Location::TopRight => PointerAction::Resize(Resize::TopRight),
         Location::TopBar => PointerAction::Move,
         Location::Button(b) => PointerAction::Button(b),
-        Location::None | Location::Inside => PointerAction::None,
+        Location::Widget(id) => PointerAction::Widget(id),
+        _ => PointerAction::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::{decorated, hover};
+
+    #[test]
+    fn borders_and_corners() {
+        let mut meta = decorated((100, 100));
+        assert_eq!(hover(&mut meta, (2., 2.)), Location::TopLeft);
+        assert_eq!(hover(&mut meta, (50., 2.)), Location::Top);
+        assert_eq!(hover(&mut meta, (50., 20.)), Location::TopBar);
+        assert_eq!(hover(&mut meta, (2., 60.)), Location::Left);
+        assert_eq!(hover(&mut meta, (50., 60.)), Location::Inside);
+        assert_eq!(hover(&mut meta, (114., 136.)), Location::BottomRight);
+    }
+
+    #[test]
+    fn close_button() {
+        let mut meta = decorated((100, 100));
+        assert_eq!(hover(&mut meta, (96., 16.)), Location::Button(UIButton::Close));
+        let action = compute_pointer_action(meta.ptr_location);
+        assert!(matches!(action, PointerAction::Button(UIButton::Close)));
+    }
+
+    #[test]
+    fn touch_down_actions() {
+        let meta = decorated((100, 100));
+        let action = |coordinates| {
+            compute_pointer_action(pointer_location(&meta, coordinates, InputDevice::Touch))
+        };
+        assert!(matches!(action((2., 2.)), PointerAction::Resize(wl_shell_surface::Resize::TopLeft)));
+        assert!(matches!(action((50., 16.)), PointerAction::Resize(wl_shell_surface::Resize::Top)));
+        assert!(matches!(action((18., 24.)), PointerAction::Resize(wl_shell_surface::Resize::TopLeft)));
+        assert!(matches!(action((100., 136.)), PointerAction::Resize(wl_shell_surface::Resize::BottomRight)));
+        assert!(matches!(action((50., 24.)), PointerAction::Move));
+        assert!(matches!(action((96., 16.)), PointerAction::Button(UIButton::Close)));
+        assert!(matches!(action((50., 60.)), PointerAction::None));
+    }
+
+    #[test]
+    fn enlarged_button_hit_zones() {
+        let mut meta = decorated((100, 100));
+        assert_eq!(hover(&mut meta, (96., 28.)), Location::TopBar);
+        assert_eq!(hover(&mut meta, (82., 16.)), Location::TopBar);
+        let mut config = meta.user_config;
+        config.accessibility.button_hit_margin = 6;
+        meta.set_config(config);
+        assert_eq!(hover(&mut meta, (96., 28.)), Location::Button(UIButton::Close));
+        assert_eq!(hover(&mut meta, (82., 16.)), Location::Button(UIButton::Close));
+        assert_eq!(hover(&mut meta, (78., 16.)), Location::Button(UIButton::Maximize));
+        assert_eq!(hover(&mut meta, (96., 2.)), Location::Top);
+    }
+
+    #[test]
+    fn touch_grab_margins() {
+        let meta = decorated((100, 100));
+        let location = |coordinates, device| pointer_location(&meta, coordinates, device);
+        assert_eq!(location((18., 2.), InputDevice::Tablet), Location::Top);
+        assert_eq!(location((18., 2.), InputDevice::Touch), Location::TopLeft);
+        assert_eq!(location((48., 18.), InputDevice::Tablet), Location::TopBar);
+        assert_eq!(location((48., 18.), InputDevice::Touch), Location::Top);
+        assert_eq!(location((48., 22.), InputDevice::Touch), Location::TopBar);
+        assert_eq!(location((104., 16.), InputDevice::Touch), Location::Button(UIButton::Close));
+    }
+
+    #[test]
+    fn tablet_grab_margins() {
+        let meta = decorated((100, 100));
+        let location = |coordinates, device| pointer_location(&meta, coordinates, device);
+        assert_eq!(location((14., 2.), InputDevice::Mouse), Location::Top);
+        assert_eq!(location((14., 2.), InputDevice::Tablet), Location::TopLeft);
+        assert_eq!(location((48., 14.), InputDevice::Mouse), Location::TopBar);
+        assert_eq!(location((48., 14.), InputDevice::Tablet), Location::Top);
+        assert_eq!(location((104., 16.), InputDevice::Tablet), Location::Button(UIButton::Close));
+        assert_eq!(location((104., 28.), InputDevice::Tablet), Location::TopRight);
+        assert_eq!(location((104., 136.), InputDevice::Mouse), Location::Bottom);
+        assert_eq!(location((104., 136.), InputDevice::Tablet), Location::BottomRight);
+    }
+
+    #[test]
+    fn tiled_top_edge_is_not_resizable() {
+        let mut meta = decorated((100, 100));
+        meta.toplevel_configure((0, 0), [::WindowState::TiledTop].iter().cloned().collect());
+        assert_eq!(hover(&mut meta, (50., 2.)), Location::TopBar);
+        assert_eq!(hover(&mut meta, (2., 2.)), Location::Left);
+    }
+
+    #[test]
+    fn shaded_window_is_not_resizable() {
+        let mut meta = decorated((100, 100));
+        meta.set_shaded(true);
+        assert_eq!(hover(&mut meta, (50., 2.)), Location::TopBar);
+        assert_eq!(hover(&mut meta, (2., 20.)), Location::TopBar);
+    }
+
+    #[test]
+    fn undecorated_window_is_inside() {
+        let mut meta = FrameMetadata::new((100, 100), false, true);
+        assert_eq!(hover(&mut meta, (2., 2.)), Location::Inside);
     }
 }
@@ -0,0 +1,453 @@
+#[cfg(feature = "wayland-protocols")]
+use dmabuf::DmabufPool;
+use memmap::MmapMut;
+use protocols::single_pixel_buffer::v1::client::wp_single_pixel_buffer_manager_v1 as single_pixel_buffer;
+use std::cell::Cell;
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::rc::Rc;
+use tempfile::tempfile;
+use wayland_client::{EventQueueHandle, Proxy};
+use wayland_client::protocol::{wl_buffer, wl_shm, wl_shm_pool};
+
+/// A buffer of the pool, kept until the compositor releases it
+struct Buffer {
+    buffer: wl_buffer::WlBuffer,
+    offset: usize,
+    len: usize,
+    dimensions: (i32, i32),
+    owner: usize,
+    // whether this is the last buffer drawn for its owner
+    latest: bool,
+    released: Rc<Cell<bool>>,
+}
+
+/// A shm pool from which the buffers of the decorations are allocated
+///
+/// The memory of a buffer is never reused before the compositor has released
+/// it, so that we never draw over contents it may still be reading.
+///
+/// A pool can be shared by several frames, each of them being an owner of
+/// buffers. The last buffer drawn for an owner is kept around, so that its
+/// next one can be drawn incrementally on top of its contents.
+///
+/// The whole file stays mapped in memory, and is only mapped again when the
+/// pool grows.
+///
+/// A wl_shm_pool can only grow, so shrinking the pool means replacing it by a
+/// new one, which is only done once the compositor has released all buffers.
+pub(crate) struct Pool {
+    shm: wl_shm::WlShm,
+    file: File,
+    mmap: MmapMut,
+    pool: wl_shm_pool::WlShmPool,
+    capacity: usize,
+    buffers: Vec<Buffer>,
+    next_owner: usize,
+    shrink_policy: Option<ShrinkPolicy>,
+    // number of consecutive redraws for which the pool was oversized
+    oversized_redraws: u32,
+    single_pixel_buffers: Option<single_pixel_buffer::WpSinglePixelBufferManagerV1>,
+    // transparent single-pixel buffer, shared by all owners
+    transparent: Option<wl_buffer::WlBuffer>,
+    format: wl_shm::Format,
+    // allocates the buffers as dmabufs instead, when possible
+    #[cfg(feature = "wayland-protocols")]
+    dmabuf: Option<DmabufPool>,
+}
+
+/// Policy to give memory back once the decorations have become much smaller
+///
+/// The memory used to draw the decorations grows with the windows, for example
+/// when they are maximized on a large screen. It is given back once the pool has
+/// been oversized for a number of redraws.
+///
+/// The default policy shrinks the pool once it has been 4 times too large for
+/// 60 redraws.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ShrinkPolicy {
+    /// The pool is oversized when it is more than `factor` times larger than the
+    /// memory in use
+    pub factor: usize,
+    /// Number of consecutive redraws the pool must have been oversized before
+    /// being shrunk
+    pub redraws: u32,
+}
+
+impl Default for ShrinkPolicy {
+    fn default() -> ShrinkPolicy {
+        ShrinkPolicy {
+            factor: 4,
+            redraws: 60,
+        }
+    }
+}
+
+/// Initial size of the pools, in bytes
+const INITIAL_POOL_SIZE: usize = 100;
+
+impl Pool {
+    pub(crate) fn new(shm: &wl_shm::WlShm) -> Result<Pool, ()> {
+        let (file, mmap, pool) = open_pool(shm, INITIAL_POOL_SIZE)?;
+        Ok(Pool {
+            shm: shm.clone().ok_or(())?,
+            file,
+            mmap,
+            pool,
+            capacity: INITIAL_POOL_SIZE,
+            buffers: Vec::new(),
+            next_owner: 0,
+            shrink_policy: Some(ShrinkPolicy::default()),
+            oversized_redraws: 0,
+            single_pixel_buffers: None,
+            transparent: None,
+            format: wl_shm::Format::Argb8888,
+            #[cfg(feature = "wayland-protocols")]
+            dmabuf: None,
+        })
+    }
+
+    pub(crate) fn set_single_pixel_buffer_manager(&mut self,
+                                                  manager: single_pixel_buffer::WpSinglePixelBufferManagerV1) {
+        self.single_pixel_buffers = Some(manager);
+    }
+
+    /// Get a fully transparent 1x1 buffer for `owner`
+    ///
+    /// When the single-pixel-buffer protocol is available, no shared memory is
+    /// used and the same buffer is given to all owners, as its contents never change.
+    pub(crate) fn transparent_buffer(&mut self, evqh: &mut EventQueueHandle, owner: usize)
+                                     -> &wl_buffer::WlBuffer {
+        if let Some(ref manager) = self.single_pixel_buffers {
+            // the previous buffer of this owner is not displayed anymore
+            for b in self.buffers.iter_mut().filter(|b| b.owner == owner) {
+                b.latest = false;
+            }
+            if self.transparent.is_none() {
+                let buffer = manager
+                    .create_u32_rgba_buffer(0, 0, 0, 0)
+                    .expect("The single pixel buffer manager cannot be destroyed.");
+                self.transparent = Some(buffer);
+            }
+            return self.transparent.as_ref().unwrap();
+        }
+        self.buffer(evqh, owner, 1, 1, |canvas, _| {
+            for b in canvas {
+                *b = 0;
+            }
+        })
+    }
+
+    /// Format of the buffers created afterwards, the pixels are drawn the same way
+    pub(crate) fn set_format(&mut self, format: wl_shm::Format) {
+        self.format = format;
+    }
+
+    #[cfg(feature = "wayland-protocols")]
+    pub(crate) fn set_dmabuf(&mut self, dmabuf: DmabufPool) {
+        self.dmabuf = Some(dmabuf);
+    }
+
+    pub(crate) fn set_shrink_policy(&mut self, policy: Option<ShrinkPolicy>) {
+        self.shrink_policy = policy;
+        self.oversized_redraws = 0;
+    }
+
+    /// Replace the pool by a new one of initial size if it has been oversized
+    /// for long enough and all its buffers have been released
+    fn shrink_if_needed(&mut self) {
+        let policy = match self.shrink_policy {
+            Some(policy) => policy,
+            None => return,
+        };
+        if self.oversized_redraws < policy.redraws || !self.buffers.iter().all(|b| b.released.get()) {
+            return;
+        }
+        self.oversized_redraws = 0;
+        if let Ok((file, mmap, pool)) = open_pool(&self.shm, INITIAL_POOL_SIZE) {
+            log_debug!("shm pool shrunk from {} to {} bytes", self.capacity, INITIAL_POOL_SIZE);
+            for b in self.buffers.drain(..) {
+                b.buffer.destroy();
+            }
+            self.pool.destroy();
+            self.file = file;
+            self.mmap = mmap;
+            self.pool = pool;
+            self.capacity = INITIAL_POOL_SIZE;
+        }
+    }
+
+    /// Count the consecutive redraws for which the pool was oversized
+    fn track_usage(&mut self) {
+        let used = self.buffers.iter().map(|b| b.offset + b.len).max().unwrap_or(0);
+        let count = self.oversized_redraws;
+        self.oversized_redraws = count_oversized(count, self.capacity, used, self.shrink_policy);
+    }
+
+    /// Get an identifier for a new owner of buffers
+    pub(crate) fn new_owner(&mut self) -> usize {
+        self.next_owner += 1;
+        self.next_owner
+    }
+
+    /// Destroy all the buffers of an owner
+    pub(crate) fn release_owner(&mut self, owner: usize) {
+        #[cfg(feature = "wayland-protocols")]
+        {
+            if let Some(ref mut dmabuf) = self.dmabuf {
+                dmabuf.release_owner(owner);
+            }
+        }
+        self.buffers.retain(|b| {
+            if b.owner == owner {
+                b.buffer.destroy();
+            }
+            b.owner != owner
+        });
+    }
+
+    /// Get an ARGB8888 buffer of given dimensions for `owner`, its contents drawn by `draw`
+    ///
+    /// If the previous buffer of this owner has the same dimensions, the new one
+    /// starts with its contents and `draw` is given `true`, so that it may only
+    /// redraw what changed. The previous buffer itself is reused when the compositor
+    /// has already released it. Otherwise, `draw` is given `false` and must draw
+    /// everything.
+    ///
+    /// With a dmabuf allocator, the buffer is a dmabuf unless it fails to allocate one.
+    pub(crate) fn buffer<F>(&mut self, evqh: &mut EventQueueHandle, owner: usize, width: i32, height: i32,
+                            draw: F)
+                            -> &wl_buffer::WlBuffer
+    where
+        F: FnOnce(&mut [u8], bool),
+    {
+        #[cfg(feature = "wayland-protocols")]
+        let draw = match self.dmabuf {
+            Some(ref mut dmabuf) => match dmabuf.buffer(evqh, owner, width, height, self.format, draw) {
+                Ok(idx) => {
+                    // the previous shm buffer of the owner is now out of date
+                    for b in self.buffers.iter_mut().filter(|b| b.owner == owner) {
+                        b.latest = false;
+                    }
+                    return self.dmabuf.as_ref().unwrap().wl_buffer(idx);
+                }
+                Err(draw) => {
+                    log_debug!("no dmabuf allocated for {:?}, drawing in shared memory", (width, height));
+                    draw
+                }
+            },
+            None => draw,
+        };
+        self.shm_buffer(evqh, owner, width, height, draw)
+    }
+
+    /// Get a buffer from the shm pool, see `buffer`
+    fn shm_buffer<F>(&mut self, evqh: &mut EventQueueHandle, owner: usize, width: i32, height: i32, draw: F)
+                     -> &wl_buffer::WlBuffer
+    where
+        F: FnOnce(&mut [u8], bool),
+    {
+        self.shrink_if_needed();
+        let len = (width * height * 4) as usize;
+        let previous = self.buffers.iter().position(|b| b.owner == owner && b.latest);
+        let mut source = None;
+        if let Some(idx) = previous {
+            let (offset, released) = {
+                let b = &self.buffers[idx];
+                (b.offset, b.released.get())
+            };
+            if self.buffers[idx].dimensions == (width, height) {
+                if released {
+                    // the compositor is done with the previous buffer, draw in place
+                    self.buffers[idx].released.set(false);
+                    draw(&mut self.mmap[offset..offset + len], true);
+                    self.track_usage();
+                    return &self.buffers[idx].buffer;
+                }
+                // the previous buffer is still busy, and kept while we copy from it
+                source = Some(offset);
+            }
+            self.buffers[idx].latest = false;
+        }
+
+        // a buffer of the owner the compositor is done with is drawn again rather than
+        // creating a new one, so that redraws alternate between two buffers
+        let spare = self.buffers.iter().position(|b| {
+            b.owner == owner && !b.latest && b.released.get() && b.dimensions == (width, height)
+        });
+        if let Some(idx) = spare {
+            let offset = self.buffers[idx].offset;
+            if let Some(source) = source {
+                self.mmap.copy_within(source..source + len, offset);
+            }
+            self.buffers[idx].released.set(false);
+            self.buffers[idx].latest = true;
+            draw(&mut self.mmap[offset..offset + len], source.is_some());
+            self.track_usage();
+            return &self.buffers[idx].buffer;
+        }
+        self.forget_released();
+
+        let offset = self.free_offset(len);
+        if offset + len > self.capacity {
+            // realloc needed!
+            log_debug!("shm pool grown from {} to {} bytes", self.capacity, offset + len);
+            self.file.set_len((offset + len) as u64).unwrap();
+            self.pool.resize((offset + len) as i32);
+            self.capacity = offset + len;
+            self.mmap = unsafe { MmapMut::map_mut(&self.file).unwrap() };
+        }
+
+        // write the contents, the mapping is shared with the compositor
+        // so no flushing is needed
+        if let Some(source) = source {
+            self.mmap.copy_within(source..source + len, offset);
+        }
+        draw(&mut self.mmap[offset..offset + len], source.is_some());
+
+        let buffer = self.pool
+            .create_buffer(offset as i32, width, height, width * 4, self.format)
+            .expect("The pool cannot be defunct!");
+        let released = Rc::new(Cell::new(false));
+        evqh.register(&buffer, buffer_implementation(), released.clone());
+        self.buffers.push(Buffer {
+            buffer,
+            offset,
+            len,
+            dimensions: (width, height),
+            owner,
+            latest: true,
+            released,
+        });
+        self.track_usage();
+        &self.buffers[self.buffers.len() - 1].buffer
+    }
+
+    /// Forget about the buffers the compositor is done with, except the last
+    /// one of each owner
+    fn forget_released(&mut self) {
+        self.buffers.retain(|b| {
+            let forget = b.released.get() && !b.latest;
+            if forget {
+                b.buffer.destroy();
+            }
+            !forget
+        });
+    }
+
+    /// Find the first offset at which `len` bytes do not overlap any buffer
+    /// kept by the pool
+    fn free_offset(&self, len: usize) -> usize {
+        let buffers = &self.buffers;
+        ::std::iter::once(0)
+            .chain(buffers.iter().map(|b| b.offset + b.len))
+            .filter(|&offset| {
+                buffers
+                    .iter()
+                    .all(|b| offset + len <= b.offset || offset >= b.offset + b.len)
+            })
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        for b in self.buffers.drain(..) {
+            b.buffer.destroy();
+        }
+        if let Some(ref buffer) = self.transparent {
+            buffer.destroy();
+        }
+        self.pool.destroy();
+    }
+}
+
+/// Consecutive oversized redraws after one more redraw, using `used` bytes of a pool of
+/// `capacity` bytes
+///
+/// The count saturates, it keeps growing while buffers are held by the compositor.
+fn count_oversized(count: u32, capacity: usize, used: usize, policy: Option<ShrinkPolicy>) -> u32 {
+    match policy {
+        Some(policy) if capacity > used.saturating_mul(policy.factor) => count.saturating_add(1),
+        _ => 0,
+    }
+}
+
+/// Create a shm pool of given size, and map its file in memory
+fn open_pool(shm: &wl_shm::WlShm, size: usize) -> Result<(File, MmapMut, wl_shm_pool::WlShmPool), ()> {
+    let file = create_shm_file().map_err(|_| ())?;
+    file.set_len(size as u64).map_err(|_| ())?;
+    let mmap = unsafe { MmapMut::map_mut(&file).map_err(|_| ())? };
+    let pool = shm.create_pool(file.as_raw_fd(), size as i32);
+    Ok((file, mmap, pool))
+}
+
+/// Create the file backing a pool
+///
+/// A sealed memfd is used when available, so that the compositor can trust the
+/// file not to shrink under its feet. Otherwise, we fall back to a tempfile.
+fn create_shm_file() -> io::Result<File> {
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(file) = create_memfd() {
+            return Ok(file);
+        }
+    }
+    tempfile()
+}
+
+#[cfg(target_os = "linux")]
+fn create_memfd() -> Option<File> {
+    use std::os::unix::io::FromRawFd;
+    let name = b"wayland-window\0";
+    let fd = unsafe {
+        ::libc::syscall(
+            ::libc::SYS_memfd_create,
+            name.as_ptr(),
+            ::libc::MFD_CLOEXEC | ::libc::MFD_ALLOW_SEALING,
+        )
+    };
+    if fd < 0 {
+        // memfd_create is not supported by kernels older than 3.17
+        return None;
+    }
+    let file = unsafe { File::from_raw_fd(fd as ::libc::c_int) };
+    // the pool can only grow, forbid shrinking the file or changing the seals
+    unsafe {
+        ::libc::fcntl(
+            file.as_raw_fd(),
+            ::libc::F_ADD_SEALS,
+            ::libc::F_SEAL_SHRINK | ::libc::F_SEAL_SEAL,
+        );
+    }
+    Some(file)
+}
+
+pub(crate) fn buffer_implementation() -> wl_buffer::Implementation<Rc<Cell<bool>>> {
+    wl_buffer::Implementation {
+        release: |_, released, _| {
+            released.set(true);
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_redraws_counting() {
+        let policy = Some(ShrinkPolicy::default());
+        assert_eq!(count_oversized(0, 4096, 1000, policy), 1);
+        assert_eq!(count_oversized(1, 4096, 1000, policy), 2);
+        // in use again, or no longer shrinking
+        assert_eq!(count_oversized(2, 4096, 1024, policy), 0);
+        assert_eq!(count_oversized(2, 4096, 1000, None), 0);
+        // never overflows
+        assert_eq!(count_oversized(u32::MAX, 4096, 1000, policy), u32::MAX);
+        let policy = Some(ShrinkPolicy { factor: usize::MAX, redraws: 1 });
+        assert_eq!(count_oversized(0, usize::MAX, 2, policy), 0);
+    }
+}
@@ -0,0 +1,237 @@
+use frame::Frame;
+use protocols::xdg_shell::client::xdg_popup::{self, XdgPopup};
+use protocols::xdg_shell::client::xdg_positioner::{Anchor, ConstraintAdjustment, Gravity};
+use protocols::xdg_shell::client::xdg_surface::{self, XdgSurface};
+use protocols::xdg_shell::client::xdg_wm_base::XdgWmBase;
+use shell;
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+use wayland_client::EventQueueHandle;
+use wayland_client::protocol::{wl_seat, wl_surface};
+
+/// Placement of a popup relative to the window it belongs to
+///
+/// The coordinates are relative to the contents of the parent window, the
+/// decorations are accounted for. The anchor, gravity and constraint adjustment
+/// types are found in `protocols::xdg_shell::client::xdg_positioner`.
+#[derive(Copy, Clone, Debug)]
+pub struct PopupPosition {
+    /// Size of the popup
+    pub size: (i32, i32),
+    /// Rectangle the popup is placed relative to, as `(x, y, width, height)`
+    pub anchor_rect: (i32, i32, i32, i32),
+    /// Point of the anchor rectangle the popup is placed at
+    pub anchor: Anchor,
+    /// Direction in which the popup extends from this point
+    pub gravity: Gravity,
+    /// How the compositor may move the popup if it does not fit on the screen
+    pub constraint_adjustment: ConstraintAdjustment,
+    /// Offset of the popup from the anchor point
+    pub offset: (i32, i32),
+}
+
+impl PopupPosition {
+    /// A popup dropping down from the bottom-left corner of the anchor rectangle
+    ///
+    /// The compositor can slide it or flip it above the rectangle to keep it on
+    /// the screen, like a menu.
+    pub fn new(size: (i32, i32), anchor_rect: (i32, i32, i32, i32)) -> PopupPosition {
+        PopupPosition {
+            size,
+            anchor_rect,
+            anchor: Anchor::BottomLeft,
+            gravity: Gravity::BottomRight,
+            constraint_adjustment: ConstraintAdjustment::SlideX | ConstraintAdjustment::SlideY
+                | ConstraintAdjustment::FlipY,
+            offset: (0, 0),
+        }
+    }
+}
+
+/// Error creating a popup, see `DecorationManager::create_popup`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PopupError {
+    /// The manager or the parent frame does not use the stable xdg_shell
+    UnsupportedShell,
+    /// The size of the popup is not positive
+    EmptySize,
+}
+
+impl fmt::Display for PopupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PopupError::UnsupportedShell => {
+                f.write_str("popups are only supported with the stable xdg_shell")
+            }
+            PopupError::EmptySize => f.write_str("the size of the popup is not positive"),
+        }
+    }
+}
+
+impl Error for PopupError {}
+
+/// For handling events that occur to a Popup.
+pub struct PopupImplementation<ID> {
+    /// Called when the compositor has placed the popup
+    ///
+    /// The position is relative to the contents of the parent window. The popup
+    /// should be drawn with the given size.
+    pub configure: fn(evqh: &mut EventQueueHandle, idata: &mut ID, x: i32, y: i32, width: i32, height: i32),
+    /// Called when the popup has been dismissed, it should then be dropped.
+    pub done: fn(evqh: &mut EventQueueHandle, idata: &mut ID),
+}
+
+impl<ID> Copy for PopupImplementation<ID> {}
+impl<ID> Clone for PopupImplementation<ID> {
+    fn clone(&self) -> PopupImplementation<ID> {
+        *self
+    }
+}
+
+// x, y, width and height
+type Rect = (i32, i32, i32, i32);
+
+struct PopupIData<ID> {
+    implementation: PopupImplementation<ID>,
+    // offset of the contents of the parent in its window geometry
+    offset: (i32, i32),
+    // placement received, applied with the next xdg_surface configure
+    pending: Rc<Cell<Option<Rect>>>,
+    idata: Rc<RefCell<ID>>,
+}
+
+impl<ID> Clone for PopupIData<ID> {
+    fn clone(&self) -> PopupIData<ID> {
+        PopupIData {
+            implementation: self.implementation,
+            offset: self.offset,
+            pending: self.pending.clone(),
+            idata: self.idata.clone(),
+        }
+    }
+}
+
+/// A popup surface anchored to a frame
+///
+/// Popups are short-lived surfaces like menus or tooltips, placed by the
+/// compositor relative to their parent window.
+///
+/// Dropping it will dismiss the popup and unmap its wl_surface.
+pub struct Popup {
+    popup: XdgPopup,
+    surface: XdgSurface,
+}
+
+impl Popup {
+    pub(crate) fn new<ID: 'static>(evqh: &mut EventQueueHandle, wm_base: &XdgWmBase, parent: &Frame,
+                                   surface: &wl_surface::WlSurface, position: &PopupPosition,
+                                   implementation: PopupImplementation<ID>, idata: ID)
+                                   -> Result<Popup, PopupError> {
+        let parent_surface = match parent.shell_surface {
+            shell::Surface::XdgStable(ref xdg) => &xdg.surface,
+            _ => return Err(PopupError::UnsupportedShell),
+        };
+        Popup::create(
+            evqh,
+            wm_base,
+            (parent_surface, parent.contents_offset),
+            surface,
+            position,
+            implementation,
+            idata,
+        )
+    }
+
+    /// Create a popup from the xdg surface of its parent
+    ///
+    /// `offset` is the position of the origin of the coordinates of `position`
+    /// in the window geometry of the parent.
+    pub(crate) fn create<ID: 'static>(evqh: &mut EventQueueHandle, wm_base: &XdgWmBase,
+                                      (parent_surface, offset): (&XdgSurface, (i32, i32)),
+                                      surface: &wl_surface::WlSurface, position: &PopupPosition,
+                                      implementation: PopupImplementation<ID>, idata: ID)
+                                      -> Result<Popup, PopupError> {
+        if position.size.0 <= 0 || position.size.1 <= 0 {
+            return Err(PopupError::EmptySize);
+        }
+
+        let positioner = wm_base
+            .create_positioner()
+            .expect("xdg_wm_base cannot be destroyed");
+        let (x, y, w, h) = position.anchor_rect;
+        positioner.set_size(position.size.0, position.size.1);
+        positioner.set_anchor_rect(x + offset.0, y + offset.1, w, h);
+        positioner.set_anchor(position.anchor);
+        positioner.set_gravity(position.gravity);
+        positioner.set_constraint_adjustment(position.constraint_adjustment);
+        positioner.set_offset(position.offset.0, position.offset.1);
+
+        let xdg_surface = wm_base
+            .get_xdg_surface(surface)
+            .expect("xdg_wm_base cannot be destroyed");
+        let popup = xdg_surface
+            .get_popup(Some(parent_surface), &positioner)
+            .expect("xdg_surface cannot be destroyed");
+        positioner.destroy();
+
+        let popup_idata = PopupIData {
+            implementation,
+            offset,
+            pending: Rc::new(Cell::new(None)),
+            idata: Rc::new(RefCell::new(idata)),
+        };
+        evqh.register(&popup, popup_implementation(), popup_idata.clone());
+        evqh.register(&xdg_surface, popup_surface_implementation(), popup_idata);
+        surface.commit();
+
+        Ok(Popup {
+            popup,
+            surface: xdg_surface,
+        })
+    }
+
+    /// Make the popup take an explicit grab
+    ///
+    /// The popup then receives all input and is dismissed when the user clicks
+    /// outside of it, which is expected from menus. This must be done in response
+    /// to a user event, before the popup is mapped.
+    pub fn grab(&self, seat: &wl_seat::WlSeat, serial: u32) {
+        self.popup.grab(seat, serial);
+    }
+}
+
+impl Drop for Popup {
+    fn drop(&mut self) {
+        self.popup.destroy();
+        self.surface.destroy();
+    }
+}
+
+fn popup_implementation<ID>() -> xdg_popup::Implementation<PopupIData<ID>> {
+    xdg_popup::Implementation {
+        configure: |_, idata, _, x, y, width, height| {
+            idata
+                .pending
+                .set(Some((x - idata.offset.0, y - idata.offset.1, width, height)));
+        },
+        popup_done: |evqh, idata, _| {
+            let mut user_idata = idata.idata.borrow_mut();
+            (idata.implementation.done)(evqh, &mut *user_idata);
+        },
+        repositioned: |_, _, _, _| {},
+    }
+}
+
+fn popup_surface_implementation<ID>() -> xdg_surface::Implementation<PopupIData<ID>> {
+    xdg_surface::Implementation {
+        configure: |evqh, idata, xdg_surface, serial| {
+            xdg_surface.ack_configure(serial);
+            if let Some((x, y, width, height)) = idata.pending.take() {
+                let mut user_idata = idata.idata.borrow_mut();
+                (idata.implementation.configure)(evqh, &mut *user_idata, x, y, width, height);
+            }
+        },
+    }
+}
@@ -0,0 +1,93 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+use wayland_client::EventQueueHandle;
+use wayland_client::protocol::wl_surface;
+use wayland_protocols::presentation_time::client::wp_presentation::WpPresentation;
+use wayland_protocols::presentation_time::client::wp_presentation_feedback::{self, Kind};
+
+/// When a redraw of the decorations was shown on screen
+///
+/// This is given by the presentation-time protocol, see
+/// `DecorationManager::set_presentation` and `Frame::last_presentation`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PresentationFeedback {
+    /// Time at which the decorations were committed, on the `CLOCK_MONOTONIC` clock
+    pub committed: Duration,
+    /// Time at which the decorations were shown, on the clock advertised by the
+    /// `clock_id` event of `wp_presentation`
+    ///
+    /// This is usually `CLOCK_MONOTONIC`, the latency of the redraw is then
+    /// `presented - committed`.
+    pub presented: Duration,
+    /// Duration of an output frame in nanoseconds, 0 if unknown
+    ///
+    /// The next frame is expected to be shown at `presented + refresh`.
+    pub refresh: u32,
+    /// Count of the vertical retraces of the output, 0 if unknown
+    pub sequence: u64,
+    /// Whether the presentation was synchronized with the vertical retrace
+    pub vsync: bool,
+    /// Whether the timestamp comes from the hardware clock of the display
+    pub hw_clock: bool,
+    /// Whether the hardware signaled the completion of the presentation
+    pub hw_completion: bool,
+    /// Whether the buffer was scanned out directly, without copy
+    pub zero_copy: bool,
+}
+
+pub(crate) struct FeedbackIData {
+    committed: Duration,
+    last: Rc<Cell<Option<PresentationFeedback>>>,
+}
+
+/// Ask for the presentation feedback of the next commit of `surface`
+///
+/// Discarded commits do not replace the previous feedback in `last`.
+pub(crate) fn request_feedback(evqh: &mut EventQueueHandle, presentation: &WpPresentation,
+                               surface: &wl_surface::WlSurface,
+                               last: &Rc<Cell<Option<PresentationFeedback>>>) {
+    let feedback = presentation
+        .feedback(surface)
+        .expect("The presentation global cannot be destroyed.");
+    evqh.register(
+        &feedback,
+        feedback_implementation(),
+        FeedbackIData {
+            committed: monotonic_time(),
+            last: last.clone(),
+        },
+    );
+}
+
+fn feedback_implementation() -> wp_presentation_feedback::Implementation<FeedbackIData> {
+    wp_presentation_feedback::Implementation {
+        sync_output: |_, _, _, _| {},
+        presented: |_, idata, _, tv_sec_hi, tv_sec_lo, tv_nsec, refresh, seq_hi, seq_lo, flags| {
+            let seconds = (u64::from(tv_sec_hi) << 32) | u64::from(tv_sec_lo);
+            let has = |kind: Kind| flags & kind.to_raw() != 0;
+            idata.last.set(Some(PresentationFeedback {
+                committed: idata.committed,
+                presented: Duration::new(seconds, tv_nsec),
+                refresh,
+                sequence: (u64::from(seq_hi) << 32) | u64::from(seq_lo),
+                vsync: has(Kind::Vsync),
+                hw_clock: has(Kind::HwClock),
+                hw_completion: has(Kind::HwCompletion),
+                zero_copy: has(Kind::ZeroCopy),
+            }));
+        },
+        discarded: |_, _, _| {},
+    }
+}
+
+fn monotonic_time() -> Duration {
+    let mut time = ::libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        ::libc::clock_gettime(::libc::CLOCK_MONOTONIC, &mut time);
+    }
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
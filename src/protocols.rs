@@ -0,0 +1,126 @@
+//! Bindings to the protocols used by this crate that wayland-protocols does not provide
+//!
+//! They are generated from the XML files in the `protocols` directory, in the same
+//! way wayland-protocols generates its own.
+
+macro_rules! wayland_protocol(
+    ($name: expr, [$(($import: ident, $interface: ident)),*]) => {
+        wayland_protocol!($name, [$(($import, $interface)),*], []);
+    };
+    // protocols extending other protocols of this module list their interfaces
+    // as (module, interface, interface_symbol)
+    ($name: expr, [$(($import: ident, $interface: ident)),*],
+     [$(($protocol: ident, $pimport: ident, $pinterface: ident)),*]) => {
+        pub use self::generated::client::api as client;
+
+        pub(crate) mod generated {
+            // the generated code is not ours to lint
+            #![allow(warnings)]
+
+            pub mod client {
+                pub mod interfaces {
+                    pub use wayland_client::protocol_interfaces::{$($interface),*};
+                    $(pub use protocols::$protocol::generated::client::interfaces::$pinterface;)*
+                    include!(concat!(env!("OUT_DIR"), "/", $name, "_interfaces.rs"));
+                }
+
+                /// Client-side API of this protocol
+                pub mod api {
+                    pub(crate) use wayland_client::{Proxy, Implementable, RequestResult, EventQueueHandle, Liveness};
+                    pub(crate) use super::interfaces;
+                    pub(crate) use wayland_client::protocol::{$($import),*};
+                    $(pub(crate) use protocols::$protocol::client::$pimport;)*
+                    include!(concat!(env!("OUT_DIR"), "/", $name, "_client_api.rs"));
+                }
+            }
+        }
+    };
+);
+
+/// KDE's blur effect
+///
+/// This protocol asks KWin to blur what lies behind the translucent parts of
+/// a surface.
+pub mod blur {
+    wayland_protocol!(
+        "blur",
+        [(wl_region, wl_region_interface), (wl_surface, wl_surface_interface)]
+    );
+}
+
+/// KDE's Plasma shell extension
+///
+/// This protocol gives surfaces roles handled by KWin, which places some of them
+/// above the other windows.
+pub mod plasma_shell {
+    wayland_protocol!(
+        "plasma-shell",
+        [(wl_output, wl_output_interface), (wl_surface, wl_surface_interface)]
+    );
+}
+
+/// Single-pixel buffers
+///
+/// This protocol allows creating 1x1 buffers of a single color without
+/// going through shared memory.
+pub mod single_pixel_buffer {
+    /// Version 1
+    pub mod v1 {
+        wayland_protocol!("single-pixel-buffer-v1", [(wl_buffer, wl_buffer_interface)]);
+    }
+}
+
+/// Activation of windows
+///
+/// This protocol allows windows to request the focus, with a token
+/// obtained from an user action.
+pub mod xdg_activation {
+    /// Version 1
+    pub mod v1 {
+        wayland_protocol!(
+            "xdg-activation-v1",
+            [(wl_seat, wl_seat_interface), (wl_surface, wl_surface_interface)]
+        );
+    }
+}
+
+/// Stable xdg_shell
+///
+/// wayland-protocols only provides the first version of this protocol, these bindings
+/// support up to version 6.
+pub mod xdg_shell {
+    wayland_protocol!(
+        "xdg-shell",
+        [
+            (wl_output, wl_output_interface),
+            (wl_seat, wl_seat_interface),
+            (wl_surface, wl_surface_interface)
+        ]
+    );
+}
+
+/// Window decorations negotiation
+///
+/// This protocol allows the compositor to draw the decorations of stable
+/// xdg_shell toplevels itself.
+pub mod xdg_decoration {
+    /// Unstable version 1
+    pub mod v1 {
+        wayland_protocol!(
+            "xdg-decoration-unstable-v1",
+            [],
+            [(xdg_shell, xdg_toplevel, xdg_toplevel_interface)]
+        );
+    }
+}
+
+/// Description of the outputs
+///
+/// wayland-protocols only provides the first version of this protocol, these bindings
+/// support up to version 3, which gives the names of the outputs.
+pub mod xdg_output {
+    /// Unstable version 1
+    pub mod v1 {
+        wayland_protocol!("xdg-output-unstable-v1", [(wl_output, wl_output_interface)]);
+    }
+}
@@ -0,0 +1,39 @@
+/// An area of a surface, made of rectangles
+///
+/// Like a `wl_region`, rectangles are added to or subtracted from the area in order,
+/// but the region is kept by this crate to test which points it contains.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Region {
+    // the rectangles as (added, x, y, width, height), the last one containing a
+    // point deciding whether the region contains it
+    rects: Vec<(bool, i32, i32, i32, i32)>,
+}
+
+impl Region {
+    /// Create an empty region
+    pub fn new() -> Region {
+        Region::default()
+    }
+
+    /// Add a rectangle to the region
+    pub fn add(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.rects.push((true, x, y, width, height));
+    }
+
+    /// Subtract a rectangle from the region
+    pub fn subtract(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.rects.push((false, x, y, width, height));
+    }
+
+    /// Whether the region contains given point
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        self.rects
+            .iter()
+            .rev()
+            .find(|&&(_, rx, ry, rw, rh)| {
+                x >= rx as f64 && x < (rx + rw) as f64 && y >= ry as f64 && y < (ry + rh) as f64
+            })
+            .map(|&(added, _, _, _, _)| added)
+            .unwrap_or(false)
+    }
+}
@@ -0,0 +1,141 @@
+use frame::FrameMetadata;
+use pool::Pool;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::str;
+use wayland_client::EventQueueHandle;
+use wayland_client::protocol::{wl_compositor, wl_subcompositor, wl_subsurface, wl_surface};
+
+/// The size of the contents shown during an interactive resize, in a subsurface of the
+/// decorations, see `FrameConfig::resize_overlay`
+pub(crate) struct ResizeOverlay {
+    pub(crate) label: ResizeLabel,
+    surface: wl_surface::WlSurface,
+    subsurface: wl_subsurface::WlSubsurface,
+    pool: Rc<RefCell<Pool>>,
+    pool_owner: usize,
+}
+
+impl ResizeOverlay {
+    /// Show the overlay with given text, with the next commit of the decorations
+    pub(crate) fn new(evqh: &mut EventQueueHandle, label: ResizeLabel, meta: &FrameMetadata,
+                      parent: &wl_surface::WlSurface, compositor: &wl_compositor::WlCompositor,
+                      subcompositor: &wl_subcompositor::WlSubcompositor, pool: &Rc<RefCell<Pool>>)
+                      -> ResizeOverlay {
+        let surface = compositor.create_surface();
+        let subsurface = subcompositor
+            .get_subsurface(&surface, parent)
+            .expect("Provided Subcompositor was defunct");
+        // the pointer goes through the overlay
+        let region = compositor.create_region();
+        surface.set_input_region(Some(&region));
+        region.destroy();
+
+        let mut overlay = ResizeOverlay {
+            label,
+            surface,
+            subsurface,
+            pool: pool.clone(),
+            pool_owner: pool.borrow_mut().new_owner(),
+        };
+        overlay.draw(evqh, meta);
+        overlay
+    }
+
+    /// Show another text, the contents having been resized
+    pub(crate) fn set_label(&mut self, evqh: &mut EventQueueHandle, label: ResizeLabel,
+                            meta: &FrameMetadata) {
+        self.label = label;
+        self.draw(evqh, meta);
+    }
+
+    fn draw(&mut self, evqh: &mut EventQueueHandle, meta: &FrameMetadata) {
+        let (w, h) = ::theme::tooltip_size(self.label.as_str());
+        let (x, y) = ::theme::resize_overlay_position(meta, (w, h));
+        self.subsurface.set_position(x, y);
+        {
+            let label = self.label.as_str();
+            let mut pool = self.pool.borrow_mut();
+            let buffer = pool.buffer(evqh, self.pool_owner, w, h, |canvas, _| {
+                ::theme::draw_tooltip(canvas, label);
+            });
+            self.surface.attach(Some(buffer), 0, 0);
+        }
+        self.surface.damage(0, 0, w, h);
+        self.surface.commit();
+    }
+}
+
+impl Drop for ResizeOverlay {
+    fn drop(&mut self) {
+        self.subsurface.destroy();
+        self.surface.destroy();
+        self.pool.borrow_mut().release_owner(self.pool_owner);
+    }
+}
+
+/// The text of an overlay, like `640x480`
+///
+/// It is computed on each redraw of an interactive resize, and formatted in place
+/// rather than in a `String`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct ResizeLabel {
+    // two numbers of up to 10 digits around the `x`
+    text: [u8; 21],
+    len: usize,
+}
+
+impl ResizeLabel {
+    fn new(width: i32, height: i32) -> ResizeLabel {
+        let mut label = ResizeLabel { text: [0; 21], len: 0 };
+        label.push_number(width);
+        label.text[label.len] = b'x';
+        label.len += 1;
+        label.push_number(height);
+        label
+    }
+
+    fn push_number(&mut self, value: i32) {
+        let mut value = value.max(0) as u32;
+        let start = self.len;
+        loop {
+            self.text[self.len] = b'0' + (value % 10) as u8;
+            self.len += 1;
+            value /= 10;
+            if value == 0 {
+                break;
+            }
+        }
+        // the digits were written from the last one
+        self.text[start..self.len].reverse();
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        str::from_utf8(&self.text[..self.len]).expect("The label only has ASCII digits.")
+    }
+}
+
+/// Text of the overlay of a window, `None` if it shows none
+///
+/// The overlay is only shown during interactive resizes, and gives the size of the
+/// contents in pixels, or in increments if the window has resize increments.
+pub(crate) fn resize_label(meta: &FrameMetadata) -> Option<ResizeLabel> {
+    if !meta.resizing || !meta.config.resize_overlay || !meta.draws_borders() {
+        return None;
+    }
+    let (w, h) = meta.dimensions;
+    let (w, h) = match meta.resize_increments {
+        Some(((inc_w, inc_h), (base_w, base_h))) => {
+            let steps = |size: i32, inc: i32, base: i32| {
+                if inc > 1 {
+                    (size - base).max(0) / inc
+                } else {
+                    size
+                }
+            };
+            (steps(w, inc_w, base_w), steps(h, inc_h, base_h))
+        }
+        None => (w, h),
+    };
+    Some(ResizeLabel::new(w, h))
+}
@@ -0,0 +1,161 @@
+use keyboard::KeyboardState;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use wayland_client::{EventQueueHandle, Proxy};
+use wayland_client::protocol::{wl_keyboard, wl_pointer, wl_seat, wl_touch};
+
+/// The pointer and touch of a frame, following the capabilities of the seat
+pub(crate) struct FramePointer {
+    pub(crate) pointer: Option<wl_pointer::WlPointer>,
+    create: Box<dyn Fn(&mut EventQueueHandle) -> wl_pointer::WlPointer>,
+    touch: Option<wl_touch::WlTouch>,
+    create_touch: Box<dyn Fn(&mut EventQueueHandle) -> wl_touch::WlTouch>,
+}
+
+impl FramePointer {
+    pub(crate) fn new(create: Box<dyn Fn(&mut EventQueueHandle) -> wl_pointer::WlPointer>,
+                      create_touch: Box<dyn Fn(&mut EventQueueHandle) -> wl_touch::WlTouch>)
+                      -> FramePointer {
+        FramePointer {
+            pointer: None,
+            create,
+            touch: None,
+            create_touch,
+        }
+    }
+
+    fn set_available(&mut self, evqh: &mut EventQueueHandle, pointer: bool, touch: bool) {
+        if pointer && self.pointer.is_none() {
+            self.pointer = Some((self.create)(evqh));
+        } else if !pointer {
+            self.release_pointer();
+        }
+        if touch && self.touch.is_none() {
+            self.touch = Some((self.create_touch)(evqh));
+        } else if !touch {
+            self.release_touch();
+        }
+    }
+
+    fn release_pointer(&mut self) {
+        if let Some(pointer) = self.pointer.take() {
+            if pointer.version() >= 3 {
+                pointer.release();
+            }
+        }
+    }
+
+    fn release_touch(&mut self) {
+        if let Some(touch) = self.touch.take() {
+            if touch.version() >= 3 {
+                touch.release();
+            }
+        }
+    }
+}
+
+impl Drop for FramePointer {
+    fn drop(&mut self) {
+        self.release_pointer();
+        self.release_touch();
+    }
+}
+
+/// Capabilities of the seat and the pointers and touch of the frames using it
+pub(crate) struct SeatState {
+    // unknown until the compositor sends them, a pointer is assumed meanwhile
+    capabilities: Option<wl_seat::Capability>,
+    frames: Vec<Weak<RefCell<FramePointer>>>,
+    // the keyboard is shared by the frames, and only taken for the keyboard navigation
+    keyboard: Option<wl_keyboard::WlKeyboard>,
+    keyboard_state: Option<Rc<RefCell<KeyboardState>>>,
+}
+
+impl SeatState {
+    pub(crate) fn new() -> SeatState {
+        SeatState {
+            capabilities: None,
+            frames: Vec::new(),
+            keyboard: None,
+            keyboard_state: None,
+        }
+    }
+
+    /// Navigate the decorations of the frames with the keyboard of the seat
+    pub(crate) fn set_keyboard_state(&mut self, keyboard_state: Rc<RefCell<KeyboardState>>) {
+        self.keyboard_state = Some(keyboard_state);
+    }
+
+    fn update_keyboard(&mut self, evqh: &mut EventQueueHandle, seat: &wl_seat::WlSeat) {
+        let available = self.capabilities
+            .map(|capabilities| capabilities.contains(wl_seat::Capability::Keyboard))
+            .unwrap_or(false);
+        match (self.keyboard_state.as_ref(), available) {
+            (Some(keyboard_state), true) if self.keyboard.is_none() => {
+                let keyboard = seat.get_keyboard().expect("Received a defunct seat.");
+                evqh.register(
+                    &keyboard,
+                    ::keyboard::keyboard_implementation(),
+                    keyboard_state.clone(),
+                );
+                self.keyboard = Some(keyboard);
+            }
+            (_, false) => self.release_keyboard(),
+            _ => {}
+        }
+    }
+
+    fn release_keyboard(&mut self) {
+        if let Some(keyboard) = self.keyboard.take() {
+            if keyboard.version() >= 3 {
+                keyboard.release();
+            }
+        }
+    }
+
+    /// Track the pointer and touch of a new frame, creating them if the seat has them
+    pub(crate) fn add_frame(&mut self, evqh: &mut EventQueueHandle, pointer: &Rc<RefCell<FramePointer>>) {
+        pointer
+            .borrow_mut()
+            .set_available(evqh, self.has_pointer(), self.has_touch());
+        self.frames.push(Rc::downgrade(pointer));
+    }
+
+    fn has_pointer(&self) -> bool {
+        self.capabilities
+            .map(|capabilities| capabilities.contains(wl_seat::Capability::Pointer))
+            .unwrap_or(true)
+    }
+
+    fn has_touch(&self) -> bool {
+        self.capabilities
+            .map(|capabilities| capabilities.contains(wl_seat::Capability::Touch))
+            .unwrap_or(false)
+    }
+}
+
+pub(crate) fn seat_implementation() -> wl_seat::Implementation<Rc<RefCell<SeatState>>> {
+    wl_seat::Implementation {
+        capabilities: |evqh, state, seat, capabilities| {
+            let mut state = state.borrow_mut();
+            state.capabilities = Some(capabilities);
+            state.update_keyboard(evqh, seat);
+            let (pointer, touch) = (state.has_pointer(), state.has_touch());
+            // forget the frames that were dropped
+            state.frames.retain(|frame| match frame.upgrade() {
+                Some(frame) => {
+                    frame.borrow_mut().set_available(evqh, pointer, touch);
+                    true
+                }
+                None => false,
+            });
+        },
+        name: |_, _, _, _| {},
+    }
+}
+
+impl Drop for SeatState {
+    fn drop(&mut self) {
+        self.release_keyboard();
+    }
+}
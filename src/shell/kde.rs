@@ -0,0 +1,15 @@
+use super::DecorationIData;
+use frame::DecorationMode;
+use wayland_protocols::misc::server_decoration::client::org_kde_kwin_server_decoration::{self, Mode};
+
+pub(crate) fn kde_decoration_implementation() -> org_kde_kwin_server_decoration::Implementation<DecorationIData> {
+    org_kde_kwin_server_decoration::Implementation {
+        mode: |evqh, idata, _, mode| {
+            // the compositor can take the decorations back at any time
+            idata.set_mode(evqh, match Mode::from_raw(mode) {
+                Some(Mode::Server) => DecorationMode::Server,
+                _ => DecorationMode::Client,
+            });
+        },
+    }
+}
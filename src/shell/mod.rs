@@ -1,22 +1,107 @@
 use FrameIData;
+use frame::{DecorationMode, FrameMetadata, WmCapabilities};
+use protocols::xdg_shell as xdg_shell_stable;
+use std::sync::{Arc, Mutex};
 use wayland_client::{EventQueueHandle, Proxy};
 use wayland_client::protocol::*;
+#[cfg(feature = "xdg_shell_v6")]
 use wayland_protocols::unstable::xdg_shell::v6 as xdg_shell;
 
+#[cfg(feature = "xdg_shell_v6")]
 mod xdg;
+mod xdg_stable;
 mod wl;
+#[cfg(feature = "misc_protocols")]
+mod kde;
+
+pub(crate) use self::wl::restore_size;
+pub(crate) use self::xdg_stable::toplevel_decoration_implementation;
+#[cfg(feature = "misc_protocols")]
+pub(crate) use self::kde::kde_decoration_implementation;
 
 /// Enum over the supported shells
 pub enum Shell {
+    /// A stable xdg_shell
+    XdgStable(xdg_shell_stable::client::xdg_wm_base::XdgWmBase),
     /// A xdg_shell from unstable v6
+    #[cfg(feature = "xdg_shell_v6")]
     Xdg(xdg_shell::client::zxdg_shell_v6::ZxdgShellV6),
     /// A wl_shell
     Wl(wl_shell::WlShell),
 }
 
 impl Shell {
+    /// Bind the best shell advertised by the compositor
+    ///
+    /// `globals` are the globals advertised on `registry`, as listed by
+    /// `EnvHandler::globals()`. The stable xdg_shell is preferred, then the
+    /// unstable xdg_shell v6, then wl_shell. Returns `None` if the compositor
+    /// advertises none of them.
+    pub fn detect(registry: &wl_registry::WlRegistry, globals: &[(u32, String, u32)]) -> Option<Shell> {
+        use self::xdg_shell_stable::client::xdg_wm_base::XdgWmBase;
+        let find = |interface: &str| {
+            globals
+                .iter()
+                .find(|global| global.1 == interface)
+                .map(|&(id, _, version)| (id, version))
+        };
+        if let Some((id, version)) = find(XdgWmBase::interface_name()) {
+            let version = ::std::cmp::min(version, XdgWmBase::supported_version());
+            return Some(Shell::XdgStable(registry.bind(version, id)));
+        }
+        #[cfg(feature = "xdg_shell_v6")]
+        {
+            if let Some((id, _)) = find(xdg_shell::client::zxdg_shell_v6::ZxdgShellV6::interface_name()) {
+                return Some(Shell::Xdg(registry.bind(1, id)));
+            }
+        }
+        if let Some((id, _)) = find(wl_shell::WlShell::interface_name()) {
+            return Some(Shell::Wl(registry.bind(1, id)));
+        }
+        None
+    }
+
+    pub(crate) fn clone(&self) -> Option<Shell> {
+        match *self {
+            Shell::XdgStable(ref s) => s.clone().map(Shell::XdgStable),
+            #[cfg(feature = "xdg_shell_v6")]
+            Shell::Xdg(ref s) => s.clone().map(Shell::Xdg),
+            Shell::Wl(ref s) => s.clone().map(Shell::Wl),
+        }
+    }
+
+    /// Answer the pings of the compositor
+    ///
+    /// xdg_shell compositors regularly check that applications are still responsive,
+    /// and may kill those that do not answer. Frames do this for you when they are
+    /// created, this replaces any implementation you registered for the shell global.
+    pub fn answer_pings(&self, evqh: &mut EventQueueHandle) {
+        match *self {
+            Shell::XdgStable(ref shell) => {
+                let implementation = xdg_shell_stable::client::xdg_wm_base::Implementation {
+                    ping: |_, _, shell, serial| {
+                        shell.pong(serial).expect("xdg_wm_base cannot be destroyed");
+                    },
+                };
+                evqh.register(shell, implementation, ());
+            }
+            #[cfg(feature = "xdg_shell_v6")]
+            Shell::Xdg(ref shell) => {
+                let implementation = xdg_shell::client::zxdg_shell_v6::Implementation {
+                    ping: |_, _, shell, serial| {
+                        shell.pong(serial);
+                    },
+                };
+                evqh.register(shell, implementation, ());
+            }
+            Shell::Wl(_) => { /* each wl_shell_surface answers its own pings */ }
+        }
+    }
+
     pub(crate) fn needs_readiness(&self) -> bool {
         match *self {
+            Shell::XdgStable(_) => true,
+            #[cfg(feature = "xdg_shell_v6")]
             Shell::Xdg(_) => true,
             Shell::Wl(_) => false,
         }
@@ -24,23 +109,308 @@ impl Shell {
 }
 
 pub(crate) enum Surface {
+    XdgStable(self::xdg_stable::Surface),
+    #[cfg(feature = "xdg_shell_v6")]
     Xdg(self::xdg::Surface),
     Wl(wl_shell_surface::WlShellSurface),
 }
 
+impl Surface {
+    pub(crate) fn objects(&self) -> ShellSurface<'_> {
+        match *self {
+            Surface::XdgStable(ref xdg) => ShellSurface::XdgStable(&xdg.surface, &xdg.toplevel),
+            #[cfg(feature = "xdg_shell_v6")]
+            Surface::Xdg(ref xdg) => ShellSurface::Xdg(&xdg.surface, &xdg.toplevel),
+            Surface::Wl(ref shell_surface) => ShellSurface::Wl(shell_surface),
+        }
+    }
+}
+
+/// The shell objects of a frame, see `Frame::shell_surface`
+pub enum ShellSurface<'a> {
+    /// The xdg_surface and xdg_toplevel of the stable xdg_shell
+    XdgStable(
+        &'a xdg_shell_stable::client::xdg_surface::XdgSurface,
+        &'a xdg_shell_stable::client::xdg_toplevel::XdgToplevel,
+    ),
+    /// The xdg_surface and xdg_toplevel of the unstable xdg_shell v6
+    #[cfg(feature = "xdg_shell_v6")]
+    Xdg(
+        &'a xdg_shell::client::zxdg_surface_v6::ZxdgSurfaceV6,
+        &'a xdg_shell::client::zxdg_toplevel_v6::ZxdgToplevelV6,
+    ),
+    /// The wl_shell_surface of wl_shell
+    Wl(&'a wl_shell_surface::WlShellSurface),
+}
+
+/// The shell objects of a window, owned by the application
+///
+/// They are given back by `Frame::into_undecorated`, and must be destroyed by the
+/// application before the surface of the window. A frame can also take them over, see
+/// `FrameBuilder::adopt`.
+pub enum ShellObjects {
+    /// The xdg_surface and xdg_toplevel of the stable xdg_shell
+    XdgStable(
+        xdg_shell_stable::client::xdg_surface::XdgSurface,
+        xdg_shell_stable::client::xdg_toplevel::XdgToplevel,
+    ),
+    /// The xdg_surface and xdg_toplevel of the unstable xdg_shell v6
+    #[cfg(feature = "xdg_shell_v6")]
+    Xdg(
+        xdg_shell::client::zxdg_surface_v6::ZxdgSurfaceV6,
+        xdg_shell::client::zxdg_toplevel_v6::ZxdgToplevelV6,
+    ),
+    /// The wl_shell_surface of wl_shell
+    Wl(wl_shell_surface::WlShellSurface),
+}
+
+impl ShellObjects {
+    /// Whether these objects were created from this shell
+    pub(crate) fn created_from(&self, shell: &Shell) -> bool {
+        match (self, shell) {
+            (&ShellObjects::XdgStable(..), &Shell::XdgStable(_)) => true,
+            #[cfg(feature = "xdg_shell_v6")]
+            (&ShellObjects::Xdg(..), &Shell::Xdg(_)) => true,
+            (&ShellObjects::Wl(_), &Shell::Wl(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Data of the handlers of the decoration negotiation protocols
+pub(crate) struct DecorationIData {
+    pub(crate) meta: Arc<Mutex<FrameMetadata>>,
+    pub(crate) shell_surface: Surface,
+    // asks the user to refresh the frame, so that the decorations are rebuilt
+    pub(crate) refresh: Box<dyn Fn(&mut EventQueueHandle)>,
+}
+
+impl DecorationIData {
+    /// Apply the decoration mode chosen by the compositor
+    pub(crate) fn set_mode(&self, evqh: &mut EventQueueHandle, mode: DecorationMode) {
+        {
+            let mut meta = self.meta.lock().unwrap();
+            if meta.decoration_mode == mode {
+                return;
+            }
+            log_debug!("decoration mode changed to {:?}", mode);
+            let decorated = meta.decorated();
+            meta.decoration_mode = mode;
+            if meta.decorated() == decorated {
+                return;
+            }
+            meta.decorations_toggled();
+            // the size limits given to the shell include the borders we draw
+            self.shell_surface.set_size_limits(&meta);
+        }
+        (self.refresh)(evqh);
+    }
+}
+
 /// Configure data for a decorated surface handler.
+///
+/// The sizes given by the compositor are the ones of the whole window,
+/// decorations included, before any clamping to the size limits.
 #[derive(Debug, Clone)]
 pub enum Configure {
+    /// Configure data from stable xdg_shell
+    XdgStable {
+        /// Serial of the configure, to acknowledge it
+        serial: u32,
+        /// Size suggested by the compositor, `(0, 0)` if we get to choose it
+        size: (i32, i32),
+        /// States of the window
+        states: StateSet,
+        /// Recommended maximum size of the contents, if known
+        bounds: Option<(i32, i32)>,
+    },
     /// Configure data from xdg_shell
-    Xdg(Vec<xdg_shell::client::zxdg_toplevel_v6::State>),
+    #[cfg(feature = "xdg_shell_v6")]
+    Xdg {
+        /// Serial of the configure, to acknowledge it
+        serial: u32,
+        /// Size suggested by the compositor, `(0, 0)` if we get to choose it
+        size: (i32, i32),
+        /// States of the window
+        states: StateSet,
+    },
     /// Configure data from wl_shell
-    Wl(wl_shell_surface::Resize),
+    Wl {
+        /// Edges being dragged by the user
+        edges: wl_shell_surface::Resize,
+        /// Size suggested by the compositor
+        size: (i32, i32),
+        /// States of the window, as requested with `Frame::set_state`
+        ///
+        /// wl_shell does not tell when they are applied.
+        states: StateSet,
+    },
+}
+
+impl Configure {
+    /// Serial of the configure, `None` with wl_shell which does not use them
+    pub fn serial(&self) -> Option<u32> {
+        match *self {
+            Configure::XdgStable { serial, .. } => Some(serial),
+            #[cfg(feature = "xdg_shell_v6")]
+            Configure::Xdg { serial, .. } => Some(serial),
+            Configure::Wl { .. } => None,
+        }
+    }
+
+    /// Size of the whole window suggested by the compositor
+    ///
+    /// `(0, 0)` means we get to choose the size.
+    pub fn size(&self) -> (i32, i32) {
+        match *self {
+            Configure::XdgStable { size, .. } => size,
+            #[cfg(feature = "xdg_shell_v6")]
+            Configure::Xdg { size, .. } => size,
+            Configure::Wl { size, .. } => size,
+        }
+    }
+
+    /// States of the window, whatever the shell
+    pub fn states(&self) -> StateSet {
+        match *self {
+            Configure::XdgStable { states, .. } => states,
+            #[cfg(feature = "xdg_shell_v6")]
+            Configure::Xdg { states, .. } => states,
+            Configure::Wl { states, .. } => states,
+        }
+    }
+
+    pub(crate) fn set_serial(&mut self, new_serial: u32) {
+        match *self {
+            Configure::XdgStable { ref mut serial, .. } => *serial = new_serial,
+            #[cfg(feature = "xdg_shell_v6")]
+            Configure::Xdg { ref mut serial, .. } => *serial = new_serial,
+            Configure::Wl { .. } => {}
+        }
+    }
+}
+
+/// State of a window, as reported in a configure
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WindowState {
+    /// The window is maximized
+    Maximized,
+    /// The window is fullscreen
+    Fullscreen,
+    /// The window is being resized by the user
+    Resizing,
+    /// The window has the focus
+    Activated,
+    /// The left edge of the window is tiled against another window or the screen
+    TiledLeft,
+    /// The right edge of the window is tiled
+    TiledRight,
+    /// The top edge of the window is tiled
+    TiledTop,
+    /// The bottom edge of the window is tiled
+    TiledBottom,
+    /// The window is not visible, it should not be drawn
+    Suspended,
+}
+
+impl WindowState {
+    pub(crate) const ALL: [WindowState; 9] = [
+        WindowState::Maximized,
+        WindowState::Fullscreen,
+        WindowState::Resizing,
+        WindowState::Activated,
+        WindowState::TiledLeft,
+        WindowState::TiledRight,
+        WindowState::TiledTop,
+        WindowState::TiledBottom,
+        WindowState::Suspended,
+    ];
+
+    pub(crate) fn from_stable(state: xdg_shell_stable::client::xdg_toplevel::State) -> WindowState {
+        use self::xdg_shell_stable::client::xdg_toplevel::State;
+        match state {
+            State::Maximized => WindowState::Maximized,
+            State::Fullscreen => WindowState::Fullscreen,
+            State::Resizing => WindowState::Resizing,
+            State::Activated => WindowState::Activated,
+            State::TiledLeft => WindowState::TiledLeft,
+            State::TiledRight => WindowState::TiledRight,
+            State::TiledTop => WindowState::TiledTop,
+            State::TiledBottom => WindowState::TiledBottom,
+            State::Suspended => WindowState::Suspended,
+        }
+    }
+
+    #[cfg(feature = "xdg_shell_v6")]
+    pub(crate) fn from_v6(state: xdg_shell::client::zxdg_toplevel_v6::State) -> WindowState {
+        use self::xdg_shell::client::zxdg_toplevel_v6::State;
+        match state {
+            State::Maximized => WindowState::Maximized,
+            State::Fullscreen => WindowState::Fullscreen,
+            State::Resizing => WindowState::Resizing,
+            State::Activated => WindowState::Activated,
+        }
+    }
+}
+
+/// A set of window states, as given in a `Configure`
+///
+/// Unlike a `Vec`, it does not need allocations.
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub struct StateSet(u16);
+
+impl ::std::fmt::Debug for StateSet {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl StateSet {
+    /// Whether the window is in given state
+    pub fn contains(self, state: WindowState) -> bool {
+        self.0 & (1 << state as u16) != 0
+    }
+
+    pub(crate) fn set(&mut self, state: WindowState, active: bool) {
+        if active {
+            self.0 |= 1 << state as u16;
+        } else {
+            self.0 &= !(1 << state as u16);
+        }
+    }
+
+    /// The states of the set, in the order of their declaration
+    pub fn iter(self) -> impl Iterator<Item = WindowState> {
+        WindowState::ALL.iter().cloned().filter(move |&state| self.contains(state))
+    }
+}
+
+impl ::std::iter::FromIterator<WindowState> for StateSet {
+    fn from_iter<I: IntoIterator<Item = WindowState>>(states: I) -> StateSet {
+        StateSet(states.into_iter().fold(0, |set, state| set | 1 << state as u16))
+    }
 }
 
 impl Surface {
     pub fn from_shell(surface: &wl_surface::WlSurface, shell: &Shell) -> Self {
         match *shell {
             // Create the `xdg_surface` and assign the `toplevel` role.
+            Shell::XdgStable(ref shell) => {
+                let xdg_surface = shell
+                    .get_xdg_surface(surface)
+                    .expect("shell cannot be destroyed");
+                let toplevel = xdg_surface
+                    .get_toplevel()
+                    .expect("xdg_surface cannot be destroyed");
+                Surface::XdgStable(self::xdg_stable::Surface {
+                    surface: xdg_surface,
+                    toplevel,
+                    decoration: None,
+                })
+            }
+
+            // Same with unstable xdg_shell v6.
+            #[cfg(feature = "xdg_shell_v6")]
             Shell::Xdg(ref shell) => {
                 let xdg_surface = shell
                     .get_xdg_surface(surface)
@@ -48,7 +418,6 @@ impl Surface {
                 let toplevel = xdg_surface
                     .get_toplevel()
                     .expect("xdg_surface cannot be destroyed");
-                surface.commit();
                 Surface::Xdg(self::xdg::Surface {
                     surface: xdg_surface,
                     toplevel: toplevel,
@@ -64,8 +433,24 @@ impl Surface {
         }
     }
 
+    /// Take over shell objects created by the user
+    pub(crate) fn adopt(objects: ShellObjects) -> Surface {
+        match objects {
+            ShellObjects::XdgStable(surface, toplevel) => Surface::XdgStable(self::xdg_stable::Surface {
+                surface,
+                toplevel,
+                decoration: None,
+            }),
+            #[cfg(feature = "xdg_shell_v6")]
+            ShellObjects::Xdg(surface, toplevel) => Surface::Xdg(self::xdg::Surface { surface, toplevel }),
+            ShellObjects::Wl(shell_surface) => Surface::Wl(shell_surface),
+        }
+    }
+
     pub(crate) fn clone(&self) -> Option<Surface> {
         match *self {
+            Surface::XdgStable(ref s) => s.clone().map(Surface::XdgStable),
+            #[cfg(feature = "xdg_shell_v6")]
             Surface::Xdg(ref s) => s.clone().map(Surface::Xdg),
             Surface::Wl(ref s) => s.clone().map(Surface::Wl),
         }
@@ -73,6 +458,19 @@ impl Surface {
 
     pub(crate) fn register_to<ID: 'static>(&self, evqh: &mut EventQueueHandle, idata: FrameIData<ID>) {
         match *self {
+            Surface::XdgStable(ref xdg) => {
+                evqh.register(
+                    &xdg.toplevel,
+                    self::xdg_stable::xdg_toplevel_implementation(),
+                    idata.clone(),
+                );
+                evqh.register(
+                    &xdg.surface,
+                    self::xdg_stable::xdg_surface_implementation(),
+                    idata,
+                );
+            }
+            #[cfg(feature = "xdg_shell_v6")]
             Surface::Xdg(ref xdg) => {
                 evqh.register(
                     &xdg.toplevel,
@@ -91,15 +489,44 @@ impl Surface {
         }
     }
 
+    /// Give the shell objects to the application, dropping the decoration negotiation
+    pub(crate) fn detach(&self) -> Option<ShellObjects> {
+        Some(match *self {
+            Surface::XdgStable(ref xdg) => {
+                if let Some(ref decoration) = xdg.decoration {
+                    decoration.destroy();
+                }
+                ShellObjects::XdgStable(xdg.surface.clone()?, xdg.toplevel.clone()?)
+            }
+            #[cfg(feature = "xdg_shell_v6")]
+            Surface::Xdg(ref xdg) => ShellObjects::Xdg(xdg.surface.clone()?, xdg.toplevel.clone()?),
+            Surface::Wl(ref shell_surface) => ShellObjects::Wl(shell_surface.clone()?),
+        })
+    }
+
     pub(crate) fn destroy(&self) {
         match *self {
+            Surface::XdgStable(ref xdg) => xdg.destroy(),
+            #[cfg(feature = "xdg_shell_v6")]
             Surface::Xdg(ref xdg) => xdg.destroy(),
             Surface::Wl(ref _shell_surface) => { /* we can't destroy it :'( */ }
         }
     }
 
+    pub(crate) fn set_server_decorations(&self, server_side: bool) {
+        if let Surface::XdgStable(ref xdg) = *self {
+            xdg.set_server_decorations(server_side);
+        }
+    }
+
     pub(crate) fn resize(&self, seat: &wl_seat::WlSeat, serial: u32, direction: wl_shell_surface::Resize) {
         match *self {
+            Surface::XdgStable(ref xdg) => {
+                let edges = xdg_shell_stable::client::xdg_toplevel::ResizeEdge::from_raw(direction.to_raw())
+                    .unwrap_or(xdg_shell_stable::client::xdg_toplevel::ResizeEdge::None);
+                xdg.toplevel.resize(seat, serial, edges);
+            }
+            #[cfg(feature = "xdg_shell_v6")]
             Surface::Xdg(ref xdg) => {
                 xdg.toplevel.resize(seat, serial, direction.to_raw());
             }
@@ -107,8 +534,97 @@ impl Surface {
         }
     }
 
+    /// Set the parent of this surface, given with its wl_surface
+    ///
+    /// Nothing is done if the parent does not use the same shell.
+    pub(crate) fn set_parent(&self, parent: Option<(&Surface, &wl_surface::WlSurface)>) {
+        match *self {
+            Surface::XdgStable(ref xdg) => {
+                let parent = match parent.map(|(parent, _)| parent) {
+                    Some(Surface::XdgStable(parent)) => Some(&parent.toplevel),
+                    Some(_) => return,
+                    None => None,
+                };
+                xdg.toplevel.set_parent(parent);
+            }
+            #[cfg(feature = "xdg_shell_v6")]
+            Surface::Xdg(ref xdg) => {
+                let parent = match parent.map(|(parent, _)| parent) {
+                    Some(Surface::Xdg(parent)) => Some(&parent.toplevel),
+                    Some(_) => return,
+                    None => None,
+                };
+                xdg.toplevel.set_parent(parent);
+            }
+            Surface::Wl(ref wl) => match parent {
+                Some((&Surface::Wl(_), parent_surface)) => {
+                    wl.set_transient(parent_surface, 0, 0, wl_shell_surface::Transient::empty());
+                }
+                Some(_) => {}
+                None => wl.set_toplevel(),
+            },
+        }
+    }
+
+    pub(crate) fn ack_configure(&self, serial: u32) {
+        match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.surface.ack_configure(serial);
+            }
+            #[cfg(feature = "xdg_shell_v6")]
+            Surface::Xdg(ref xdg) => {
+                xdg.surface.ack_configure(serial);
+            }
+            Surface::Wl(_) => { /* not needed */ }
+        }
+    }
+
+    pub(crate) fn set_window_geometry(&self, (x, y, w, h): (i32, i32, i32, i32)) {
+        match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.surface.set_window_geometry(x, y, w, h);
+            }
+            #[cfg(feature = "xdg_shell_v6")]
+            Surface::Xdg(ref xdg) => {
+                xdg.surface.set_window_geometry(x, y, w, h);
+            }
+            Surface::Wl(_) => { /* not available */ }
+        }
+    }
+
+    /// The actions the shell supports, before the compositor reports its own
+    pub(crate) fn capabilities(&self) -> WmCapabilities {
+        match *self {
+            Surface::XdgStable(_) => WmCapabilities::default(),
+            #[cfg(feature = "xdg_shell_v6")]
+            Surface::Xdg(_) => WmCapabilities::default(),
+            Surface::Wl(_) => WmCapabilities {
+                window_menu: false,
+                minimize: false,
+                ..WmCapabilities::default()
+            },
+        }
+    }
+
+    pub(crate) fn show_window_menu(&self, seat: &wl_seat::WlSeat, serial: u32, x: i32, y: i32) {
+        match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.toplevel.show_window_menu(seat, serial, x, y);
+            }
+            #[cfg(feature = "xdg_shell_v6")]
+            Surface::Xdg(ref xdg) => {
+                xdg.toplevel.show_window_menu(seat, serial, x, y);
+            }
+            Surface::Wl(_) => { /* not available */ }
+        }
+    }
+
     pub(crate) fn _move(&self, seat: &wl_seat::WlSeat, serial: u32) {
         match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.toplevel._move(seat, serial);
+            }
+            #[cfg(feature = "xdg_shell_v6")]
             Surface::Xdg(ref xdg) => {
                 xdg.toplevel._move(seat, serial);
             }
@@ -118,6 +634,10 @@ impl Surface {
 
     pub(crate) fn set_title(&self, title: String) {
         match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.toplevel.set_title(title);
+            }
+            #[cfg(feature = "xdg_shell_v6")]
             Surface::Xdg(ref xdg) => {
                 xdg.toplevel.set_title(title);
             }
@@ -129,6 +649,10 @@ impl Surface {
 
     pub(crate) fn set_app_id(&self, title: String) {
         match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.toplevel.set_app_id(title);
+            }
+            #[cfg(feature = "xdg_shell_v6")]
             Surface::Xdg(ref xdg) => {
                 xdg.toplevel.set_app_id(title);
             }
@@ -140,6 +664,10 @@ impl Surface {
 
     pub(crate) fn set_fullscreen(&self, output: Option<&wl_output::WlOutput>) {
         match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.toplevel.set_fullscreen(output);
+            }
+            #[cfg(feature = "xdg_shell_v6")]
             Surface::Xdg(ref xdg) => {
                 xdg.toplevel.set_fullscreen(output);
             }
@@ -153,6 +681,10 @@ impl Surface {
 
     pub(crate) fn unset_fullscreen(&self) {
         match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.toplevel.unset_fullscreen();
+            }
+            #[cfg(feature = "xdg_shell_v6")]
             Surface::Xdg(ref xdg) => {
                 xdg.toplevel.unset_fullscreen();
             }
@@ -164,6 +696,10 @@ impl Surface {
 
     pub(crate) fn set_maximized(&self) {
         match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.toplevel.set_maximized();
+            }
+            #[cfg(feature = "xdg_shell_v6")]
             Surface::Xdg(ref xdg) => {
                 xdg.toplevel.set_maximized();
             }
@@ -175,6 +711,10 @@ impl Surface {
 
     pub(crate) fn unset_maximized(&self) {
         match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.toplevel.unset_maximized();
+            }
+            #[cfg(feature = "xdg_shell_v6")]
             Surface::Xdg(ref xdg) => {
                 xdg.toplevel.unset_maximized();
             }
@@ -186,6 +726,10 @@ impl Surface {
 
     pub(crate) fn set_minimized(&self) {
         match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.toplevel.set_minimized();
+            }
+            #[cfg(feature = "xdg_shell_v6")]
             Surface::Xdg(ref xdg) => {
                 xdg.toplevel.set_minimized();
             }
@@ -194,16 +738,42 @@ impl Surface {
     }
 
     pub(crate) fn set_min_size(&self, size: Option<(i32, i32)>) {
-        if let Surface::Xdg(ref xdg) = *self {
-            let (w, h) = size.unwrap_or((0, 0));
-            xdg.toplevel.set_min_size(w, h);
+        let (w, h) = size.unwrap_or((0, 0));
+        match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.toplevel.set_min_size(w, h);
+            }
+            #[cfg(feature = "xdg_shell_v6")]
+            Surface::Xdg(ref xdg) => {
+                xdg.toplevel.set_min_size(w, h);
+            }
+            Surface::Wl(_) => { /* not available */ }
         }
     }
 
     pub(crate) fn set_max_size(&self, size: Option<(i32, i32)>) {
-        if let Surface::Xdg(ref xdg) = *self {
-            let (w, h) = size.unwrap_or((0, 0));
-            xdg.toplevel.set_max_size(w, h);
+        let (w, h) = size.unwrap_or((0, 0));
+        match *self {
+            Surface::XdgStable(ref xdg) => {
+                xdg.toplevel.set_max_size(w, h);
+            }
+            #[cfg(feature = "xdg_shell_v6")]
+            Surface::Xdg(ref xdg) => {
+                xdg.toplevel.set_max_size(w, h);
+            }
+            Surface::Wl(_) => { /* not available */ }
+        }
+    }
+
+    /// Give the size limits of the contents to the shell, with the drawn borders
+    pub(crate) fn set_size_limits(&self, meta: &FrameMetadata) {
+        if meta.is_shaded() {
+            // shaded windows are smaller than their size limits
+            self.set_min_size(None);
+            self.set_max_size(None);
+        } else {
+            self.set_min_size(meta.min_size.map(|size| meta.outer_size(size)));
+            self.set_max_size(meta.max_size.map(|size| meta.outer_size(size)));
         }
     }
 }
@@ -1,4 +1,7 @@
 use FrameIData;
+use frame::FrameMetadata;
+use super::{StateSet, WindowState};
+use wayland_client::EventQueueHandle;
 use wayland_client::protocol::wl_shell_surface;
 
 pub(crate) fn wl_shell_surface_implementation<ID>() -> wl_shell_surface::Implementation<FrameIData<ID>> {
@@ -7,17 +10,62 @@ pub(crate) fn wl_shell_surface_implementation<ID>() -> wl_shell_surface::Impleme
             shell_surface.pong(serial);
         },
         configure: |evqh, idata, _, edges, width, height| {
-            let newsize = {
+            let (newsize, states) = {
                 let mut meta = idata.meta.lock().unwrap();
-                meta.need_redraw = true;
-                meta.clamp_to_limits((width, height))
+                let states = states(&meta, edges);
+                // the size is the one of the whole surface, borders included
+                (meta.wl_configure((width, height)), states)
             };
-            let configure = super::Configure::Wl(edges);
-            let mut user_idata = idata.idata.borrow_mut();
-            (idata.implementation.configure)(evqh, &mut *user_idata, configure, Some(newsize))
+            log_debug!(
+                "wl_shell_surface configure: size {:?}, edges {:?}, contents {:?}",
+                (width, height),
+                edges,
+                newsize
+            );
+            let configure = super::Configure::Wl {
+                edges,
+                size: (width, height),
+                states,
+            };
+            idata.configure(evqh, configure, newsize);
         },
         popup_done: |_, _, _| {
             // We are not doing popups
         },
     }
 }
+
+/// Give the size of the contents from before maximizing back to the user
+///
+/// wl_shell does not configure the windows it unmaximizes, this sends the configure
+/// the user would have received from xdg_shell.
+pub(crate) fn restore_size<ID>(evqh: &mut EventQueueHandle, idata: &FrameIData<ID>) {
+    let (configure, size) = {
+        let mut meta = idata.meta.lock().unwrap();
+        let size = match meta.restore_size.take() {
+            Some(size) => size,
+            None => return,
+        };
+        log_debug!("wl_shell surface unmaximized, restoring contents {:?}", size);
+        meta.need_redraw = true;
+        let configure = super::Configure::Wl {
+            edges: wl_shell_surface::Resize::empty(),
+            size: meta.outer_size(size),
+            states: states(&meta, wl_shell_surface::Resize::empty()),
+        };
+        (configure, size)
+    };
+    idata.configure(evqh, configure, Some(size));
+}
+
+/// States of the window for a configure
+///
+/// wl_shell does not report the states, the requested ones are used.
+fn states(meta: &FrameMetadata, edges: wl_shell_surface::Resize) -> StateSet {
+    let mut states = StateSet::default();
+    states.set(WindowState::Maximized, meta.maximized);
+    states.set(WindowState::Fullscreen, meta.fullscreen);
+    states.set(WindowState::Resizing, !edges.is_empty());
+    states.set(WindowState::Activated, meta.activated);
+    states
+}
@@ -1,4 +1,5 @@
 use FrameIData;
+use super::WindowState;
 use wayland_client::Proxy;
 use wayland_protocols::unstable::xdg_shell::v6::client::zxdg_surface_v6::{self, ZxdgSurfaceV6};
 use wayland_protocols::unstable::xdg_shell::v6::client::zxdg_toplevel_v6::{self, ZxdgToplevelV6};
@@ -28,58 +29,58 @@ impl Surface {
 
 pub(crate) fn xdg_toplevel_implementation<ID>() -> zxdg_toplevel_v6::Implementation<FrameIData<ID>> {
     zxdg_toplevel_v6::Implementation {
-        configure: |evqh, idata, _, width, height, states| {
-            let mut newsize = if width == 0 || height == 0 {
-                // if either w or h is zero, then we get to choose our size
-                None
-            } else {
-                Some(idata.meta.lock().unwrap().clamp_to_limits((width, height)))
-            };
+        configure: |_, idata, _, width, height, states| {
             let view: &[u32] =
                 unsafe { ::std::slice::from_raw_parts(states.as_ptr() as *const _, states.len() / 4) };
             let states = view.iter()
                 .cloned()
                 .flat_map(zxdg_toplevel_v6::State::from_raw)
-                .collect::<Vec<_>>();
-            let activated = states.contains(&zxdg_toplevel_v6::State::Activated);
-            let new_maximized = states.contains(&zxdg_toplevel_v6::State::Maximized);
-            let configure = super::Configure::Xdg(states);
-            {
-                let mut meta = idata.meta.lock().unwrap();
-                meta.need_redraw = true;
-                meta.activated = activated;
-                match (new_maximized, meta.maximized) {
-                    (false, true) => {
-                        // we got de-maximized
-                        meta.maximized = false;
-                        if newsize.is_none() {
-                            newsize = meta.old_size;
-                        }
-                        meta.old_size = None;
-                    }
-                    (true, false) => {
-                        // we are being maximized
-                        meta.maximized = true;
-                        meta.old_size = Some(meta.dimensions);
-                    }
-                    _ => { /* nothing changed */ }
-                }
-            }
-            let mut user_idata = idata.idata.borrow_mut();
-            (idata.implementation.configure)(evqh, &mut *user_idata, configure, newsize);
+                .map(WindowState::from_v6)
+                .collect();
+            let newsize = idata
+                .meta
+                .lock()
+                .unwrap()
+                .toplevel_configure((width, height), states);
+            log_debug!(
+                "zxdg_toplevel_v6 configure: size {:?}, states {:?}, contents {:?}",
+                (width, height),
+                states,
+                newsize
+            );
+            let configure = super::Configure::Xdg {
+                // known with the following configure of the xdg surface
+                serial: 0,
+                size: (width, height),
+                states,
+            };
+            *idata.pending_configure.borrow_mut() = Some((configure, newsize));
         },
         close: |evqh, idata, _| {
-            let mut user_idata = idata.idata.borrow_mut();
-            (idata.implementation.close)(evqh, &mut *user_idata);
+            idata.close(evqh);
         },
     }
 }
 
 pub(crate) fn xdg_surface_implementation<ID>() -> zxdg_surface_v6::Implementation<FrameIData<ID>> {
     zxdg_surface_v6::Implementation {
-        configure: |_, idata, xdg_surface, serial| {
-            idata.meta.lock().unwrap().ready = true;
-            xdg_surface.ack_configure(serial);
+        configure: |evqh, idata, xdg_surface, serial| {
+            {
+                let mut meta = idata.meta.lock().unwrap();
+                meta.ready = true;
+                if meta.manual_ack {
+                    // a configure needs a commit anyway, acked with the next redraw at the latest
+                    log_trace!("configure {} acked with the next redraw", serial);
+                    meta.pending_serial = Some(serial);
+                    meta.need_redraw = true;
+                } else {
+                    xdg_surface.ack_configure(serial);
+                }
+            }
+            if let Some((mut configure, newsize)) = idata.pending_configure.borrow_mut().take() {
+                configure.set_serial(serial);
+                idata.configure(evqh, configure, newsize);
+            }
         },
     }
 }
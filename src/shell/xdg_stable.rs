@@ -0,0 +1,152 @@
+use FrameIData;
+use frame::{DecorationMode, WmCapabilities};
+use protocols::xdg_decoration::v1::client::zxdg_toplevel_decoration_v1::{self, ZxdgToplevelDecorationV1};
+use protocols::xdg_shell::client::xdg_surface::{self, XdgSurface};
+use protocols::xdg_shell::client::xdg_toplevel::{self, XdgToplevel};
+use super::{DecorationIData, WindowState};
+use wayland_client::Proxy;
+
+pub(crate) struct Surface {
+    pub toplevel: XdgToplevel,
+    pub surface: XdgSurface,
+    pub decoration: Option<ZxdgToplevelDecorationV1>,
+}
+
+impl Surface {
+    pub(crate) fn clone(&self) -> Option<Surface> {
+        let decoration = match self.decoration {
+            Some(ref decoration) => Some(decoration.clone()?),
+            None => None,
+        };
+        match (self.toplevel.clone(), self.surface.clone()) {
+            (Some(t), Some(s)) => Some(Surface {
+                toplevel: t,
+                surface: s,
+                decoration,
+            }),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn set_server_decorations(&self, server_side: bool) {
+        if let Some(ref decoration) = self.decoration {
+            let mode = if server_side {
+                zxdg_toplevel_decoration_v1::Mode::ServerSide
+            } else {
+                zxdg_toplevel_decoration_v1::Mode::ClientSide
+            };
+            decoration
+                .set_mode(mode)
+                .expect("The decoration is destroyed with the frame.");
+        }
+    }
+
+    pub(crate) fn destroy(&self) {
+        // destroy surfaces in the right order
+        if let Some(ref decoration) = self.decoration {
+            decoration.destroy();
+        }
+        self.toplevel.destroy();
+        self.surface.destroy();
+    }
+}
+
+pub(crate) fn xdg_toplevel_implementation<ID>() -> xdg_toplevel::Implementation<FrameIData<ID>> {
+    xdg_toplevel::Implementation {
+        configure: |_, idata, _, width, height, states| {
+            let view: &[u32] =
+                unsafe { ::std::slice::from_raw_parts(states.as_ptr() as *const _, states.len() / 4) };
+            let states = view.iter()
+                .cloned()
+                .flat_map(xdg_toplevel::State::from_raw)
+                .map(WindowState::from_stable)
+                .collect();
+            let newsize;
+            let configure;
+            {
+                let mut meta = idata.meta.lock().unwrap();
+                newsize = meta.toplevel_configure((width, height), states);
+                log_debug!(
+                    "xdg_toplevel configure: size {:?}, states {:?}, contents {:?}",
+                    (width, height),
+                    states,
+                    newsize
+                );
+                configure = super::Configure::XdgStable {
+                    // known with the following configure of the xdg surface
+                    serial: 0,
+                    size: (width, height),
+                    states,
+                    bounds: meta.inner_bounds(),
+                };
+            }
+            *idata.pending_configure.borrow_mut() = Some((configure, newsize));
+        },
+        close: |evqh, idata, _| {
+            idata.close(evqh);
+        },
+        configure_bounds: |_, idata, _, width, height| {
+            // applied with the following configure
+            log_debug!("xdg_toplevel bounds: {:?}", (width, height));
+            idata.meta.lock().unwrap().bounds = if width == 0 || height == 0 {
+                None
+            } else {
+                Some((width, height))
+            };
+        },
+        wm_capabilities: |_, idata, _, capabilities| {
+            let view: &[u32] = unsafe {
+                ::std::slice::from_raw_parts(capabilities.as_ptr() as *const _, capabilities.len() / 4)
+            };
+            let capabilities = view.iter()
+                .cloned()
+                .flat_map(xdg_toplevel::WmCapabilities::from_raw)
+                .collect::<Vec<_>>();
+            let mut meta = idata.meta.lock().unwrap();
+            let keep_above = meta.capabilities.keep_above;
+            meta.set_capabilities(WmCapabilities {
+                window_menu: capabilities.contains(&xdg_toplevel::WmCapabilities::WindowMenu),
+                maximize: capabilities.contains(&xdg_toplevel::WmCapabilities::Maximize),
+                fullscreen: capabilities.contains(&xdg_toplevel::WmCapabilities::Fullscreen),
+                minimize: capabilities.contains(&xdg_toplevel::WmCapabilities::Minimize),
+                // provided by another protocol
+                keep_above,
+            });
+            meta.need_redraw = true;
+        },
+    }
+}
+
+pub(crate) fn xdg_surface_implementation<ID>() -> xdg_surface::Implementation<FrameIData<ID>> {
+    xdg_surface::Implementation {
+        configure: |evqh, idata, xdg_surface, serial| {
+            {
+                let mut meta = idata.meta.lock().unwrap();
+                meta.ready = true;
+                if meta.manual_ack {
+                    // a configure needs a commit anyway, acked with the next redraw at the latest
+                    log_trace!("configure {} acked with the next redraw", serial);
+                    meta.pending_serial = Some(serial);
+                    meta.need_redraw = true;
+                } else {
+                    xdg_surface.ack_configure(serial);
+                }
+            }
+            if let Some((mut configure, newsize)) = idata.pending_configure.borrow_mut().take() {
+                configure.set_serial(serial);
+                idata.configure(evqh, configure, newsize);
+            }
+        },
+    }
+}
+
+pub(crate) fn toplevel_decoration_implementation() -> zxdg_toplevel_decoration_v1::Implementation<DecorationIData> {
+    zxdg_toplevel_decoration_v1::Implementation {
+        configure: |evqh, idata, _, mode| {
+            idata.set_mode(evqh, match mode {
+                zxdg_toplevel_decoration_v1::Mode::ServerSide => DecorationMode::Server,
+                zxdg_toplevel_decoration_v1::Mode::ClientSide => DecorationMode::Client,
+            });
+        },
+    }
+}
@@ -0,0 +1,76 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+use wayland_client::EventQueueHandle;
+use wayland_client::protocol::wl_shm;
+
+/// The pixel formats supported by the compositor for shared memory buffers
+///
+/// The compositor advertises them right after the wl_shm global is bound, so the
+/// list must be created before the next roundtrip. It implements the wl_shm, which
+/// should not be registered elsewhere.
+///
+/// Give it to `DecorationManager::set_shm_formats` to draw the decorations in a
+/// supported format.
+pub struct ShmFormats {
+    formats: Rc<RefCell<Vec<wl_shm::Format>>>,
+}
+
+impl ShmFormats {
+    /// Start listening to the formats advertised for this wl_shm
+    pub fn new(evqh: &mut EventQueueHandle, shm: &wl_shm::WlShm) -> ShmFormats {
+        let formats = Rc::new(RefCell::new(Vec::new()));
+        evqh.register(shm, shm_implementation(), formats.clone());
+        ShmFormats { formats }
+    }
+
+    /// The formats advertised so far
+    pub fn formats(&self) -> Vec<wl_shm::Format> {
+        self.formats.borrow().clone()
+    }
+
+    /// Whether the compositor advertised this format
+    pub fn supports(&self, format: wl_shm::Format) -> bool {
+        self.formats.borrow().contains(&format)
+    }
+
+    /// The format used to draw the decorations
+    ///
+    /// Argb8888 is preferred. Xrgb8888 is used otherwise, the decorations are fully
+    /// opaque but what lies below translucent contents is then black.
+    pub(crate) fn decoration_format(&self) -> Result<wl_shm::Format, ShmError> {
+        if self.supports(wl_shm::Format::Argb8888) {
+            Ok(wl_shm::Format::Argb8888)
+        } else if self.supports(wl_shm::Format::Xrgb8888) {
+            Ok(wl_shm::Format::Xrgb8888)
+        } else {
+            Err(ShmError::NoUsableFormat)
+        }
+    }
+}
+
+/// Error using the shared memory of the compositor
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShmError {
+    /// The compositor supports neither Argb8888 nor Xrgb8888 buffers
+    NoUsableFormat,
+}
+
+impl fmt::Display for ShmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShmError::NoUsableFormat => f.write_str("no usable shm format is supported by the compositor"),
+        }
+    }
+}
+
+impl Error for ShmError {}
+
+fn shm_implementation() -> wl_shm::Implementation<Rc<RefCell<Vec<wl_shm::Format>>>> {
+    wl_shm::Implementation {
+        format: |_, formats, _, format| {
+            formats.borrow_mut().push(format);
+        },
+    }
+}
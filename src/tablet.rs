@@ -0,0 +1,214 @@
+use PointerIData;
+use pointer::{Pointer, BTN_LEFT};
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+#[cfg(feature = "cursor_theme")]
+use themed_pointer::{ThemeSlot, ThemedPointer};
+use wayland_client::{EventQueueHandle, Proxy};
+#[cfg(feature = "cursor_theme")]
+use wayland_client::protocol::wl_compositor;
+use wayland_client::protocol::wl_surface;
+use wayland_protocols::unstable::tablet::v2::client::{zwp_tablet_seat_v2, zwp_tablet_tool_v2, zwp_tablet_v2};
+use wayland_protocols::unstable::tablet::v2::client::zwp_tablet_tool_v2::ZwpTabletToolV2;
+
+/// A frame reacting to the tablet tools
+///
+/// The tools behave like a pointer whose left button is the tip of the tool.
+pub(crate) trait ToolTarget {
+    /// Whether the tool entered this frame, which then takes the focus of the tool
+    fn proximity_in(&mut self, evqh: &mut EventQueueHandle, tool: &ZwpTabletToolV2, serial: u32,
+                    surface: &wl_surface::WlSurface)
+                    -> bool;
+    fn proximity_out(&mut self, evqh: &mut EventQueueHandle, serial: u32);
+    fn motion(&mut self, evqh: &mut EventQueueHandle, x: f64, y: f64);
+    fn tip(&mut self, evqh: &mut EventQueueHandle, serial: u32, time: u32, down: bool);
+}
+
+pub(crate) struct FrameTool<ID> {
+    idata: PointerIData<ID>,
+    // the theme of the cursor of the tools, with the compositor to create its surface
+    #[cfg(feature = "cursor_theme")]
+    cursor: Option<(ThemeSlot, wl_compositor::WlCompositor)>,
+    // serial of the proximity_in event, the frame is entered once the position is known
+    pending_enter: Option<u32>,
+}
+
+impl<ID> FrameTool<ID> {
+    pub(crate) fn new(idata: PointerIData<ID>,
+                      #[cfg(feature = "cursor_theme")] cursor: Option<(ThemeSlot,
+                                                                      wl_compositor::WlCompositor)>)
+                      -> FrameTool<ID> {
+        FrameTool {
+            idata,
+            #[cfg(feature = "cursor_theme")]
+            cursor,
+            pending_enter: None,
+        }
+    }
+
+    /// The pointer of a tool entering the frame
+    #[cfg(feature = "cursor_theme")]
+    fn tool_pointer(&self, evqh: &mut EventQueueHandle, tool: &ZwpTabletToolV2) -> Pointer {
+        match self.cursor {
+            Some((ref theme, ref compositor)) => {
+                let tool = tool.clone().expect("The tool is destroyed once removed.");
+                Pointer::Themed(ThemedPointer::for_tool(evqh, tool, theme.clone(), compositor))
+            }
+            None => Pointer::Tool,
+        }
+    }
+
+    /// The pointer of a tool entering the frame, whose cursor is left as is
+    #[cfg(not(feature = "cursor_theme"))]
+    fn tool_pointer(&self, _evqh: &mut EventQueueHandle, _tool: &ZwpTabletToolV2) -> Pointer {
+        Pointer::Tool
+    }
+}
+
+impl<ID: 'static> ToolTarget for FrameTool<ID> {
+    fn proximity_in(&mut self, evqh: &mut EventQueueHandle, tool: &ZwpTabletToolV2, serial: u32,
+                    surface: &wl_surface::WlSurface)
+                    -> bool {
+        if !self.idata.pstate.frame_surface.equals(surface) {
+            return false;
+        }
+        self.idata.pstate.pointer = self.tool_pointer(evqh, tool);
+        // the position of the tool is given by the following motion
+        self.pending_enter = Some(serial);
+        true
+    }
+
+    fn proximity_out(&mut self, evqh: &mut EventQueueHandle, serial: u32) {
+        if self.pending_enter.take().is_none() {
+            self.idata.leave(evqh, serial);
+        }
+        self.idata.pstate.pointer = Pointer::Tool;
+    }
+
+    fn motion(&mut self, evqh: &mut EventQueueHandle, x: f64, y: f64) {
+        match self.pending_enter.take() {
+            Some(serial) => {
+                let surface = self.idata.pstate.frame_surface.clone().unwrap();
+                self.idata.enter(evqh, serial, &surface, x, y);
+            }
+            None => self.idata.motion(evqh, x, y),
+        }
+    }
+
+    fn tip(&mut self, evqh: &mut EventQueueHandle, serial: u32, time: u32, down: bool) {
+        self.idata.button(evqh, serial, time, BTN_LEFT, down);
+    }
+}
+
+/// The frames reacting to the tablet tools of a seat
+pub(crate) struct TabletState {
+    frames: Vec<Weak<RefCell<dyn ToolTarget>>>,
+}
+
+impl TabletState {
+    pub(crate) fn new() -> TabletState {
+        TabletState { frames: Vec::new() }
+    }
+
+    pub(crate) fn add_frame(&mut self, frame: &Rc<RefCell<dyn ToolTarget>>) {
+        self.frames.push(Rc::downgrade(frame));
+    }
+}
+
+pub(crate) fn tablet_seat_implementation() -> zwp_tablet_seat_v2::Implementation<Rc<RefCell<TabletState>>> {
+    zwp_tablet_seat_v2::Implementation {
+        tablet_added: |evqh, _, _, tablet| {
+            evqh.register(&tablet, tablet_implementation(), ());
+        },
+        tool_added: |evqh, state, _, tool| {
+            let tool_idata = ToolIData {
+                state: state.clone(),
+                focus: None,
+                serial: 0,
+                time: 0,
+            };
+            evqh.register(&tool, tool_implementation(), tool_idata);
+        },
+        pad_added: |_, _, _, pad| {
+            // the pads do not interact with the decorations
+            pad.destroy();
+        },
+    }
+}
+
+fn tablet_implementation() -> zwp_tablet_v2::Implementation<()> {
+    zwp_tablet_v2::Implementation {
+        name: |_, _, _, _| {},
+        id: |_, _, _, _, _| {},
+        path: |_, _, _, _| {},
+        done: |_, _, _| {},
+        removed: |_, _, tablet| {
+            tablet.destroy();
+        },
+    }
+}
+
+struct ToolIData {
+    state: Rc<RefCell<TabletState>>,
+    // the frame the tool is over
+    focus: Option<Rc<RefCell<dyn ToolTarget>>>,
+    // serial of the proximity_in event
+    serial: u32,
+    // time of the last frame of events, the tip events come before it
+    time: u32,
+}
+
+fn tool_implementation() -> zwp_tablet_tool_v2::Implementation<ToolIData> {
+    zwp_tablet_tool_v2::Implementation {
+        type_: |_, _, _, _| {},
+        hardware_serial: |_, _, _, _, _| {},
+        hardware_id_wacom: |_, _, _, _, _| {},
+        capability: |_, _, _, _| {},
+        done: |_, _, _| {},
+        removed: |_, idata, tool| {
+            idata.focus = None;
+            tool.destroy();
+        },
+        proximity_in: |evqh, idata, tool, serial, _, surface| {
+            idata.serial = serial;
+            // forget the frames that were dropped
+            let mut state = idata.state.borrow_mut();
+            state.frames.retain(|frame| frame.upgrade().is_some());
+            idata.focus = state
+                .frames
+                .iter()
+                .filter_map(|frame| frame.upgrade())
+                .find(|frame| frame.borrow_mut().proximity_in(evqh, tool, serial, surface));
+        },
+        proximity_out: |evqh, idata, _| {
+            if let Some(frame) = idata.focus.take() {
+                frame.borrow_mut().proximity_out(evqh, idata.serial);
+            }
+        },
+        down: |evqh, idata, _, serial| {
+            if let Some(ref frame) = idata.focus {
+                frame.borrow_mut().tip(evqh, serial, idata.time, true);
+            }
+        },
+        up: |evqh, idata, _| {
+            if let Some(ref frame) = idata.focus {
+                frame.borrow_mut().tip(evqh, idata.serial, idata.time, false);
+            }
+        },
+        motion: |evqh, idata, _, x, y| {
+            if let Some(ref frame) = idata.focus {
+                frame.borrow_mut().motion(evqh, x, y);
+            }
+        },
+        pressure: |_, _, _, _| {},
+        distance: |_, _, _, _| {},
+        tilt: |_, _, _, _, _| {},
+        rotation: |_, _, _, _| {},
+        slider: |_, _, _, _| {},
+        wheel: |_, _, _, _, _| {},
+        button: |_, _, _, _, _, _| {},
+        frame: |_, idata, _, time| {
+            idata.time = time;
+        },
+    }
+}
@@ -0,0 +1,90 @@
+//! Fake compositor endpoints, to test the frames without a compositor
+//!
+//! The frames react to the compositor by updating their `FrameMetadata` from the
+//! implementations of the shells and seat, and draw it in shm buffers. These fakes
+//! drive the metadata and draw it the same way, without any wayland object.
+
+use {Location, WindowState};
+use frame::FrameMetadata;
+
+/// Metadata of a ready frame drawing its decorations, with contents of given size
+pub(crate) fn decorated(size: (i32, i32)) -> FrameMetadata {
+    let mut meta = FrameMetadata::new(size, false, true);
+    meta.decorate = true;
+    meta
+}
+
+/// A shell configuring a frame
+pub(crate) struct FakeShell {
+    pub(crate) meta: FrameMetadata,
+    // whether configures are sent like wl_shell does, rather than xdg_shell
+    wl: bool,
+}
+
+impl FakeShell {
+    /// A stable or v6 xdg_shell configuring the toplevel of a decorated frame
+    pub(crate) fn xdg(size: (i32, i32)) -> FakeShell {
+        FakeShell {
+            meta: decorated(size),
+            wl: false,
+        }
+    }
+
+    /// A wl_shell configuring the surface of a decorated frame
+    ///
+    /// wl_shell does not report the states, the ones given to `configure` are ignored.
+    pub(crate) fn wl(size: (i32, i32)) -> FakeShell {
+        FakeShell {
+            meta: decorated(size),
+            wl: true,
+        }
+    }
+
+    /// Send a configure suggesting the size of the whole window
+    ///
+    /// Returns the size of the contents given to the user, which is then applied like
+    /// `Frame::resize` does.
+    pub(crate) fn configure(&mut self, size: (i32, i32), states: &[WindowState]) -> Option<(i32, i32)> {
+        let newsize = if self.wl {
+            self.meta.wl_configure(size)
+        } else {
+            self.meta.toplevel_configure(size, states.iter().cloned().collect())
+        };
+        if let Some((w, h)) = newsize {
+            self.meta.dimensions = (w.max(1), h.max(1));
+        }
+        newsize
+    }
+
+    /// Size of the whole window, as seen by the compositor
+    pub(crate) fn window_size(&self) -> (i32, i32) {
+        let drawn = self.meta.drawn();
+        if self.meta.draws_borders() {
+            drawn.config.add_borders(drawn.dimensions.0, drawn.dimensions.1)
+        } else {
+            drawn.dimensions
+        }
+    }
+}
+
+/// Move a pointer over the frame surface, returns the part of the frame it hovers
+pub(crate) fn hover(meta: &mut FrameMetadata, coordinates: (f64, f64)) -> Location {
+    let location = ::pointer::pointer_location(meta, coordinates, ::pointer::InputDevice::Mouse);
+    meta.set_ptr_location(location);
+    location
+}
+
+/// The decorations drawn in a shm buffer, with their width
+pub(crate) fn draw(meta: &FrameMetadata) -> (Vec<u8>, i32) {
+    let drawn = meta.drawn();
+    let (width, height) = drawn.config.add_borders(drawn.dimensions.0, drawn.dimensions.1);
+    let mut canvas = vec![0; (width * height * 4) as usize];
+    ::theme::draw_contents(&mut canvas, &drawn);
+    (canvas, width)
+}
+
+/// A pixel of drawn decorations
+pub(crate) fn pixel(&(ref canvas, width): &(Vec<u8>, i32), (x, y): (i32, i32)) -> [u8; 4] {
+    let idx = ((y * width + x) * 4) as usize;
+    [canvas[idx], canvas[idx + 1], canvas[idx + 2], canvas[idx + 3]]
+}
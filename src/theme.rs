@@ -1,7 +1,16 @@
 use {Location, UIButton};
+use frame::{BorderSizes, FrameConfig, FrameMetadata};
+use geometry::METRICS;
+use widget::{WidgetId, WidgetSide, WidgetSlots, WidgetState, MAX_WIDGETS};
 
-const DECORATION_SIZE: i32 = 8;
-const DECORATION_TOP_SIZE: i32 = 32;
+const DECORATION_SIZE: i32 = METRICS.border;
+const BUTTON_WIDTH: i32 = METRICS.button_width;
+const BUTTON_HEIGHT: i32 = METRICS.button_height;
+const BUTTON_SPACING: i32 = METRICS.button_spacing;
+const PROGRESS_HEIGHT: i32 = 2;
+
+/// Whether the title of the window is drawn in the titlebar, it is not yet
+pub(crate) const DRAWS_TITLE: bool = false;
 
 #[cfg(target_endian = "little")]
 macro_rules! auto_endian(
@@ -27,102 +36,271 @@ const GREEN_BUTTON_HOVER: [u8; 4] = auto_endian!(0xFF, 0x40, 0xFF, 0x40);
 const YELLOW_BUTTON_REGULAR: [u8; 4] = auto_endian!(0xFF, 0xB0, 0xB0, 0x40);
 const YELLOW_BUTTON_HOVER: [u8; 4] = auto_endian!(0xFF, 0xFF, 0xFF, 0x40);
 const YELLOW_BUTTON_DISABLED: [u8; 4] = auto_endian!(0xFF, 0x80, 0x80, 0x20);
+const BLUE_BUTTON_REGULAR: [u8; 4] = auto_endian!(0xFF, 0x40, 0x70, 0xB0);
+const BLUE_BUTTON_HOVER: [u8; 4] = auto_endian!(0xFF, 0x40, 0x90, 0xFF);
+const BUTTON_GLYPH: [u8; 4] = auto_endian!(0x90, 0x00, 0x00, 0x00);
+const INACTIVE_BUTTON_GLYPH: [u8; 4] = auto_endian!(0x48, 0x00, 0x00, 0x00);
+const FOCUS_RING: [u8; 4] = auto_endian!(0xFF, 0x30, 0x60, 0xD0);
+const PROGRESS_BAR: [u8; 4] = auto_endian!(0xFF, 0x40, 0x90, 0xFF);
+
+const TOOLTIP_BACKGROUND: [u8; 4] = auto_endian!(0xFF, 0x30, 0x30, 0x30);
+const TOOLTIP_TEXT: [u8; 4] = auto_endian!(0xFF, 0xF0, 0xF0, 0xF0);
+const MENU_HOVER: [u8; 4] = auto_endian!(0xFF, 0x50, 0x50, 0x50);
+const MENU_DISABLED_TEXT: [u8; 4] = auto_endian!(0xFF, 0x80, 0x80, 0x80);
+
+// the tooltips are written with a 5x7 bitmap font, scaled up
+const GLYPH_WIDTH: i32 = 5;
+const GLYPH_HEIGHT: i32 = 7;
+const GLYPH_SCALE: i32 = 2;
+const TOOLTIP_PADDING: i32 = 6;
+const MENU_ITEM_HEIGHT: i32 = GLYPH_HEIGHT * GLYPH_SCALE + 2 * TOOLTIP_PADDING;
+
+// how much the buttons of inactive windows blend into the border, between 0 and 1
+const INACTIVE_DIMMING: f32 = 0.6;
+
+// index of the alpha channel in a pixel
+#[cfg(target_endian = "little")]
+const ALPHA: usize = 3;
+#[cfg(target_endian = "big")]
+const ALPHA: usize = 0;
+
+// a button or widget of the titlebar, with its horizontal position and width
+type TitlebarItem = (Location, i32, i32);
+
+type TitlebarPositions = [Option<TitlebarItem>; 4 + MAX_WIDGETS];
+
+/// Places the items of the titlebar on either side of its free part
+struct Packer {
+    positions: TitlebarPositions,
+    count: usize,
+    // horizontal extent of the part of the titlebar not used yet
+    free: (i32, i32),
+}
+
+impl Packer {
+    /// Place an item on a side of the free part, returns whether it fits
+    fn pack(&mut self, (item, width): (Location, i32), side: WidgetSide) -> bool {
+        let (x0, x1) = self.free;
+        if x1 - x0 < width {
+            return false;
+        }
+        let x = match side {
+            WidgetSide::Left => {
+                self.free.0 += width + BUTTON_SPACING;
+                x0
+            }
+            WidgetSide::Right => {
+                self.free.1 -= width + BUTTON_SPACING;
+                x1 - width
+            }
+        };
+        self.positions[self.count] = Some((item, x, width));
+        self.count += 1;
+        true
+    }
+}
+
+/// Horizontal positions of the visible buttons and widgets of the titlebar
+///
+/// Buttons on the right side of the layout are packed from the right edge of the
+/// titlebar, then buttons on the left side are packed from its left edge. The widgets
+/// of each side are then packed next to its buttons, so that they never push the
+/// buttons out. Items that do not fit in the titlebar are not listed.
+fn titlebar_positions(w: i32, config: &FrameConfig, widgets: &WidgetSlots) -> TitlebarPositions {
+    let left = METRICS.border_sizes(config).left;
+    let layout = &config.button_layout;
+    let visible = |button: &&UIButton| config.buttons.contains(**button);
+    let as_item = |&button: &UIButton| (Location::Button(button), BUTTON_WIDTH);
+    let mut packer = Packer {
+        positions: [None; 4 + MAX_WIDGETS],
+        count: 0,
+        free: (left, left + w),
+    };
+    for item in layout.right.iter().rev().flatten().filter(&visible).map(&as_item) {
+        if !packer.pack(item, WidgetSide::Right) {
+            break;
+        }
+    }
+    for item in layout.left.iter().flatten().filter(&visible).map(&as_item) {
+        if !packer.pack(item, WidgetSide::Left) {
+            break;
+        }
+    }
+    for &side in &[WidgetSide::Right, WidgetSide::Left] {
+        let side_widgets = widgets.iter().enumerate().filter_map(|(id, widget)| match *widget {
+            Some((s, width)) if s == side => Some((Location::Widget(WidgetId(id)), width)),
+            _ => None,
+        });
+        for item in side_widgets {
+            if !packer.pack(item, side) {
+                break;
+            }
+        }
+    }
+    packer.positions
+}
 
-/// Compute on which part of the window given point falls
-pub(crate) fn compute_location((x, y): (f64, f64), (w, h): (i32, i32)) -> Location {
-    if y <= DECORATION_TOP_SIZE as f64 {
+/// The buttons among the items of the titlebar, with their horizontal position
+fn buttons(positions: &TitlebarPositions) -> impl Iterator<Item = (UIButton, i32)> + '_ {
+    positions.iter().filter_map(|item| match *item {
+        Some((Location::Button(button), x, _)) => Some((button, x)),
+        _ => None,
+    })
+}
+
+// a button of the titlebar with its rectangle
+#[cfg(feature = "egl")]
+type ButtonRect = (UIButton, (i32, i32, i32, i32));
+
+/// The visible buttons of the titlebar, with their rectangle
+#[cfg(feature = "egl")]
+pub(crate) fn button_rects(meta: &FrameMetadata) -> [Option<ButtonRect>; 4] {
+    let positions = titlebar_positions(meta.dimensions.0, &meta.config, &meta.widgets);
+    let mut rects = [None; 4];
+    for (rect, (button, x)) in rects.iter_mut().zip(buttons(&positions)) {
+        *rect = Some((button, (x, DECORATION_SIZE, BUTTON_WIDTH, BUTTON_HEIGHT)));
+    }
+    rects
+}
+
+/// The visible buttons of the titlebar, from left to right
+pub(crate) fn focus_order(meta: &FrameMetadata) -> Vec<UIButton> {
+    let positions = titlebar_positions(meta.dimensions.0, &meta.config, &meta.widgets);
+    let mut buttons = buttons(&positions).collect::<Vec<_>>();
+    buttons.sort_by_key(|&(_, x)| x);
+    buttons.into_iter().map(|(button, _)| button).collect()
+}
+
+/// Compute on which part of a decorated window given point falls
+///
+/// `point` is relative to the top-left corner of the decorations, and `size` is the
+/// size of the contents, as given to `Frame::resize`. This is the hit-testing used by
+/// the frames themselves, for applications drawing their own header bar and wanting
+/// the same edges and corners, including the hit zones of the buttons enlarged by
+/// `FrameAccessibility`. It assumes there are no titlebar widgets.
+pub fn compute_location(point: (f64, f64), size: (i32, i32), config: &FrameConfig) -> Location {
+    compute_location_with_widgets(point, size, config, &[None; MAX_WIDGETS], 0)
+}
+
+/// Compute on which part of a decorated window given point falls, see `compute_location`
+///
+/// Over the decorations, the resize zones extend by `grab_margin` pixels: the corners
+/// along the top and bottom borders, and the top edge down the titlebar. The items of
+/// the titlebar keep their hit zones.
+pub(crate) fn compute_location_with_widgets((x, y): (f64, f64), (w, h): (i32, i32), config: &FrameConfig,
+                                            widgets: &WidgetSlots, grab_margin: i32)
+                                            -> Location {
+    let BorderSizes { left, top, right, bottom } = METRICS.border_sizes(config);
+    let in_left_corner = left > 0 && x <= (left + grab_margin) as f64;
+    let in_right_corner = right > 0 && x > (w + left - grab_margin) as f64;
+    if y <= top as f64 {
         // we are in the top part
-        if x <= DECORATION_SIZE as f64 {
+        if left > 0 && x <= left as f64 {
             Location::TopLeft
-        } else if x <= (w + DECORATION_SIZE) as f64 {
-            if y <= DECORATION_SIZE as f64 {
-                Location::Top
+        } else if x <= (w + left) as f64 {
+            let item = if y <= DECORATION_SIZE as f64 {
+                None
             } else {
-                // check for buttons
-                if (w >= 24) && (x > (w + DECORATION_SIZE - 24) as f64) && (x <= (w + DECORATION_SIZE) as f64)
-                    && (y > DECORATION_SIZE as f64) && (y <= (DECORATION_SIZE + 16) as f64)
-                {
-                    Location::Button(UIButton::Close)
-                } else if (w >= 56) && (x > (w + DECORATION_SIZE - 56) as f64)
-                    && (x <= (w + DECORATION_SIZE - 32) as f64)
-                    && (y > DECORATION_SIZE as f64)
-                    && (y <= (DECORATION_SIZE + 16) as f64)
-                {
-                    Location::Button(UIButton::Maximize)
-                } else if (w >= 88) && (x > (w + DECORATION_SIZE - 88) as f64)
-                    && (x <= (w + DECORATION_SIZE - 64) as f64)
-                    && (y > DECORATION_SIZE as f64)
-                    && (y <= (DECORATION_SIZE + 16) as f64)
-                {
-                    Location::Button(UIButton::Minimize)
-                } else {
-                    Location::TopBar
-                }
+                titlebar_item_at((x, y), w, config, widgets)
+            };
+            match item {
+                Some(item) => item,
+                None if in_left_corner => Location::TopLeft,
+                None if in_right_corner => Location::TopRight,
+                None if y <= (DECORATION_SIZE + grab_margin) as f64 => Location::Top,
+                None => Location::TopBar,
             }
-        } else {
+        } else if right > 0 {
             Location::TopRight
+        } else {
+            Location::None
         }
-    } else if y <= (DECORATION_TOP_SIZE + h) as f64 {
-        if x <= DECORATION_SIZE as f64 {
+    } else if y <= (top + h) as f64 || bottom == 0 {
+        if left > 0 && x <= left as f64 {
             Location::Left
-        } else if x <= (w + DECORATION_SIZE) as f64 {
+        } else if x <= (w + left) as f64 {
             Location::Inside
-        } else {
+        } else if right > 0 {
             Location::Right
+        } else {
+            Location::None
         }
     } else {
-        if x <= DECORATION_SIZE as f64 {
+        if in_left_corner {
             Location::BottomLeft
-        } else if x <= (w + DECORATION_SIZE) as f64 {
+        } else if in_right_corner {
+            Location::BottomRight
+        } else if x <= (w + left) as f64 {
             Location::Bottom
         } else {
-            Location::BottomRight
+            Location::None
         }
     }
 }
 
-/// Offset at which the contents should be drawn relative to the top-left
-/// corner of the decorations
-pub(crate) fn subsurface_offset() -> (i32, i32) {
-    (DECORATION_SIZE, DECORATION_TOP_SIZE)
+/// The button or widget of the titlebar under given point, below the top border
+///
+/// Points near the buttons are on them when their hit zones are enlarged, see
+/// `FrameAccessibility::button_hit_margin`.
+fn titlebar_item_at((x, y): (f64, f64), w: i32, config: &FrameConfig, widgets: &WidgetSlots)
+                    -> Option<Location> {
+    let positions = titlebar_positions(w, config, widgets);
+    let items = positions.iter().filter_map(|&position| position);
+    let within = |(x0, x1): (i32, i32), margin: i32| {
+        y <= (DECORATION_SIZE + BUTTON_HEIGHT + margin) as f64 && x > (x0 - margin) as f64
+            && x <= (x1 + margin) as f64
+    };
+    if let Some((item, _, _)) = items.clone().find(|&(_, bx, bw)| within((bx, bx + bw), 0)) {
+        return Some(item);
+    }
+    let margin = config.accessibility.button_hit_margin as i32;
+    if margin == 0 {
+        return None;
+    }
+    items
+        .filter(|&(item, bx, bw)| matches!(item, Location::Button(_)) && within((bx, bx + bw), margin))
+        .min_by(|&(_, a, _), &(_, b, _)| {
+            let center = |bx: i32| (x - (bx + BUTTON_WIDTH / 2) as f64).abs();
+            center(a).partial_cmp(&center(b)).unwrap()
+        })
+        .map(|(item, _, _)| item)
 }
 
 /// Subtracts the border dimensions from the given dimensions.
+///
+/// This assumes the default configuration, with all borders drawn. See
+/// `FrameConfig::subtract_borders` for a configuration-aware version.
+#[deprecated(note = "use `Frame::border_sizes` or `FrameConfig::subtract_borders`")]
 pub fn subtract_borders(width: i32, height: i32) -> (i32, i32) {
-    (
-        width - 2 * (DECORATION_SIZE as i32),
-        height - DECORATION_SIZE as i32 - DECORATION_TOP_SIZE as i32,
-    )
+    METRICS.subtract_borders((width, height), &FrameConfig::default())
 }
 
 /// Adds the border dimensions to the given dimensions.
+///
+/// This assumes the default configuration, with all borders drawn. See
+/// `FrameConfig::add_borders` for a configuration-aware version.
+#[deprecated(note = "use `Frame::border_sizes` or `FrameConfig::add_borders`")]
 pub fn add_borders(width: i32, height: i32) -> (i32, i32) {
-    (
-        width + 2 * (DECORATION_SIZE as i32),
-        height + DECORATION_SIZE as i32 + DECORATION_TOP_SIZE as i32,
-    )
+    METRICS.add_borders((width, height), &FrameConfig::default())
 }
 
-/// Total number of pixels of the rectangle containing the whole
-/// decorated window
-pub(crate) fn pxcount(w: i32, h: i32) -> i32 {
-    (w + 2 * DECORATION_SIZE) * (h + DECORATION_SIZE + DECORATION_TOP_SIZE)
-}
-
-/// Draw the decorations on the rectangle
+/// Draw the decorations on the rectangle, returns the parts of the canvas drawn
 ///
 /// Actual contents of the window will be drawn on top
-pub(crate) fn draw_contents(canvas: &mut [u8], w: u32, h: u32, activated: bool, _maximized: bool,
-                            maximizable: bool, ptr_location: Location) {
-    let ds = DECORATION_SIZE as u32;
-    let dts = DECORATION_TOP_SIZE as u32;
-    let mut canvas = Canvas::new(w + 2 * ds, h + ds + dts, canvas);
-    // draw the borders
+pub(crate) fn draw_contents(canvas: &mut [u8], meta: &FrameMetadata) -> Damage {
+    let (w, h) = (meta.dimensions.0 as u32, meta.dimensions.1 as u32);
+    let BorderSizes { left, top, right, bottom } = METRICS.border_sizes(&meta.config);
+    let (ls, dts, rs, bs) = (left as u32, top as u32, right as u32, bottom as u32);
+    let (full_w, full_h) = (w + ls + rs, h + dts + bs);
+    let mut canvas = Canvas::new(full_w, full_h, canvas);
+    // draw the borders, overlapping the contents by one pixel
+    // disabled borders are empty rectangles
+    let overlap = |size: u32| if size > 0 { size + 1 } else { 0 };
     let border_rectangles = [
-        (0, 0, w + 2 * ds, dts+1),      // top rectangle
-        (0, dts, ds+1, h),              // left rectangle
-        (w + ds-1, dts, ds+1, h),         // right rectangle
-        (0, h + dts-1, w + 2 * ds, ds+1), // bottom rectangle
+        (0, 0, full_w, dts + 1),                         // top rectangle
+        (0, dts, overlap(ls), h),                        // left rectangle
+        (full_w - overlap(rs), dts, overlap(rs), h),     // right rectangle
+        (0, full_h - overlap(bs), full_w, overlap(bs)),  // bottom rectangle
     ];
 
     // We've built an ImageBuffer from a raw &[u8] buffer, and the wayland spec
@@ -130,84 +308,715 @@ pub(crate) fn draw_contents(canvas: &mut [u8], w: u32, h: u32, activated: bool,
     // also we're doing ARGB (while image expects RGBA), though as long as we
     // only blit pixels it's not very important
 
+    // the buffer may hold older contents, clear what is below the window contents
+    canvas.write_rect(ls, dts, w, h, [0; 4]);
+
     // fill these rectangles with grey
-    let border_color = if activated {
-        ACTIVE_BORDER
+    let border_color = border_color(meta);
+    for &(x, y, w, h) in &border_rectangles {
+        canvas.fill_rect(x, y, w, h, border_color);
+    }
+
+    // draw the buttons: red close, yellow maximize and green minimize
+    let positions = titlebar_positions(w as i32, &meta.config, &meta.widgets);
+    for (button, bx) in buttons(&positions) {
+        draw_titlebar_button(&mut canvas, meta, button, bx as u32);
+    }
+
+    // the progress bar runs above the contents, at the bottom of the titlebar
+    if let Some(progress) = meta.progress {
+        let mut color = PROGRESS_BAR;
+        if !meta.activated {
+            color = mix(color, border_color, INACTIVE_DIMMING);
+        }
+        let width = w * u32::from(progress) / 100;
+        canvas.fill_rect(ls, dts - PROGRESS_HEIGHT as u32, width, PROGRESS_HEIGHT as u32, color);
+    }
+    canvas.damage
+}
+
+/// Let the application draw the widgets of the titlebar over the decorations
+///
+/// `draw` is given the id of each widget and the pixels below it, which are then copied
+/// back to the canvas.
+pub(crate) fn draw_widgets<F>(canvas: &mut [u8], meta: &FrameMetadata, pixels: &mut Vec<u8>, mut draw: F)
+where
+    F: FnMut(usize, &mut [u8], (i32, i32), WidgetState),
+{
+    let (full_w, full_h) = meta.config.add_borders(meta.dimensions.0, meta.dimensions.1);
+    let mut canvas = Canvas::new(full_w as u32, full_h as u32, canvas);
+    for item in titlebar_positions(meta.dimensions.0, &meta.config, &meta.widgets).iter() {
+        if let Some((Location::Widget(widget), x, width)) = *item {
+            let rect = (x as u32, DECORATION_SIZE as u32, width as u32, BUTTON_HEIGHT as u32);
+            canvas.read_rect(rect, pixels);
+            let state = WidgetState {
+                hovered: meta.ptr_location == Location::Widget(widget),
+                activated: meta.activated,
+            };
+            draw(widget.0, pixels, (width, BUTTON_HEIGHT), state);
+            canvas.copy_rect(rect, pixels);
+        }
+    }
+}
+
+/// Buffers reused by the drawings of a frame, which do not allocate once they are grown
+#[derive(Debug, Default)]
+pub(crate) struct Scratch {
+    // the pixels below a widget, given to the application
+    widget: Vec<u8>,
+    // the decorations at their normal size, before being scaled up
+    unscaled: Vec<u8>,
+}
+
+impl Scratch {
+    /// The buffer of the pixels below the widgets, see `draw_widgets`
+    pub(crate) fn widget(&mut self) -> &mut Vec<u8> {
+        &mut self.widget
+    }
+}
+
+/// Draw the decorations in a buffer of the caller, scaled up by `scale`
+///
+/// The buffer holds rows of `stride` bytes, large enough for the scaled decorations.
+/// Unscaled decorations in rows without padding are drawn in place, the others are
+/// drawn at their normal size in `scratch`, then every pixel is repeated.
+pub(crate) fn draw_scaled<F>(target: &mut [u8], stride: usize, scale: usize, meta: &FrameMetadata,
+                             scratch: &mut Scratch, draw: F)
+where
+    F: FnMut(usize, &mut [u8], (i32, i32), WidgetState),
+{
+    let (full_w, full_h) = meta.config.add_borders(meta.dimensions.0, meta.dimensions.1);
+    let row_len = full_w as usize * 4;
+    let len = row_len * full_h as usize;
+    if scale == 1 && stride == row_len {
+        let target = &mut target[..len];
+        draw_contents(target, meta);
+        draw_widgets(target, meta, &mut scratch.widget, draw);
+        return;
+    }
+    let pixels = &mut scratch.unscaled;
+    pixels.resize(len, 0);
+    draw_contents(pixels, meta);
+    draw_widgets(pixels, meta, &mut scratch.widget, draw);
+    for (y, row) in pixels.chunks(row_len).enumerate() {
+        for sy in 0..scale {
+            let start = (y * scale + sy) * stride;
+            let scaled_row = &mut target[start..start + row_len * scale];
+            for (scaled, pixel) in scaled_row.chunks_mut(4 * scale).zip(row.chunks(4)) {
+                for dst in scaled.chunks_mut(4) {
+                    dst.copy_from_slice(pixel);
+                }
+            }
+        }
+    }
+}
+
+/// Redraw the buttons whose hover level changed since `previous` was drawn
+///
+/// The canvas must hold the decorations as drawn for `previous`. Returns the
+/// parts of the canvas drawn, or `None` if anything else changed, in which case
+/// nothing is drawn and a full redraw is needed.
+pub(crate) fn draw_hover_changes(canvas: &mut [u8], meta: &FrameMetadata, previous: &FrameMetadata)
+                                 -> Option<Damage> {
+    if meta.dimensions != previous.dimensions || meta.activated != previous.activated
+        || meta.maximized != previous.maximized
+        || meta.max_size.is_none() != previous.max_size.is_none() || meta.config != previous.config
+        || meta.focused_button != previous.focused_button || meta.keep_above != previous.keep_above
+        || meta.widgets != previous.widgets || meta.progress != previous.progress
+        || meta.accent_color != previous.accent_color
+    {
+        return None;
+    }
+    // the widgets are drawn again by the application when their hover state changes
+    let widget_hover = |location: Location| matches!(location, Location::Widget(_));
+    if meta.ptr_location != previous.ptr_location
+        && (widget_hover(meta.ptr_location) || widget_hover(previous.ptr_location))
+    {
+        return None;
+    }
+    let (full_w, full_h) = meta.config.add_borders(meta.dimensions.0, meta.dimensions.1);
+    let mut canvas = Canvas::new(full_w as u32, full_h as u32, canvas);
+    let positions = titlebar_positions(meta.dimensions.0, &meta.config, &meta.widgets);
+    for (button, bx) in buttons(&positions) {
+        if meta.hover_level(button) == previous.hover_level(button) {
+            continue;
+        }
+        // clear the old button, as its rounded corners are blended on the border
+        let (x, y, w, h) = (bx as u32, DECORATION_SIZE as u32, BUTTON_WIDTH as u32, BUTTON_HEIGHT as u32);
+        canvas.fill_rect(x, y, w, h, border_color(meta));
+        draw_titlebar_button(&mut canvas, meta, button, x);
+    }
+    Some(canvas.damage)
+}
+
+fn border_color(meta: &FrameMetadata) -> [u8; 4] {
+    if meta.activated {
+        tint(meta, ACTIVE_BORDER)
     } else {
         INACTIVE_BORDER
+    }
+}
+
+/// Blend a color with the accent color of the frame
+fn tint(meta: &FrameMetadata, color: [u8; 4]) -> [u8; 4] {
+    let [a, r, g, b] = meta.accent_color;
+    if a == 0 {
+        return color;
+    }
+    mix(color, auto_endian!(0xFF, r, g, b), f32::from(a) / 255.)
+}
+
+/// Draw a titlebar button in the colors matching the state of the frame
+fn draw_titlebar_button(canvas: &mut Canvas, meta: &FrameMetadata, button: UIButton, x: u32) {
+    let (regular, hover) = match button {
+        UIButton::Close => (RED_BUTTON_REGULAR, tint(meta, RED_BUTTON_HOVER)),
+        UIButton::Maximize if meta.max_size.is_some() => (YELLOW_BUTTON_DISABLED, YELLOW_BUTTON_DISABLED),
+        UIButton::Maximize => (YELLOW_BUTTON_REGULAR, tint(meta, YELLOW_BUTTON_HOVER)),
+        UIButton::Minimize => (GREEN_BUTTON_REGULAR, tint(meta, GREEN_BUTTON_HOVER)),
+        // the button of a window kept above stays lit
+        UIButton::Pin if meta.keep_above => (tint(meta, BLUE_BUTTON_HOVER), tint(meta, BLUE_BUTTON_HOVER)),
+        UIButton::Pin => (BLUE_BUTTON_REGULAR, tint(meta, BLUE_BUTTON_HOVER)),
     };
-    for &(x, y, w, h) in &border_rectangles {
-        for xx in x..(x + w) {
-            for yy in y..(y + h) {
-                canvas.put_pixel(xx, yy, border_color);
-            }
+    let mut button_color = mix(regular, hover, meta.hover_level(button));
+    let glyph_color = if meta.activated {
+        BUTTON_GLYPH
+    } else {
+        // reduce the contrast of the buttons of inactive windows
+        button_color = mix(button_color, border_color(meta), INACTIVE_DIMMING);
+        INACTIVE_BUTTON_GLYPH
+    };
+    let restore = button == UIButton::Maximize && meta.maximized;
+    draw_button(canvas, button, restore, x, DECORATION_SIZE as u32, button_color, glyph_color);
+    if meta.focused_button == Some(button) {
+        // a ring in the spacing around the button, cut by the edge of the canvas
+        let (x0, x1) = (x.saturating_sub(2), x + BUTTON_WIDTH as u32 + 1);
+        let (y0, y1) = (DECORATION_SIZE as u32 - 2, (DECORATION_SIZE + BUTTON_HEIGHT) as u32 + 1);
+        canvas.fill_rect(x0, y0, x1 - x0 + 1, 1, FOCUS_RING);
+        canvas.fill_rect(x0, y1, x1 - x0 + 1, 1, FOCUS_RING);
+        canvas.fill_rect(x0, y0, 1, y1 - y0 + 1, FOCUS_RING);
+        canvas.fill_rect(x1, y0, 1, y1 - y0 + 1, FOCUS_RING);
+    }
+}
+
+/// Draw a rounded button with its glyph, its top-left corner at `(x, y)`
+///
+/// With `restore`, the maximize button shows that it restores the window instead.
+fn draw_button(canvas: &mut Canvas, button: UIButton, restore: bool, x: u32, y: u32, color: [u8; 4],
+               glyph: [u8; 4]) {
+    let (w, h) = (BUTTON_WIDTH as u32, BUTTON_HEIGHT as u32);
+    canvas.fill_rounded_rect(x, y, w, h, 4, color);
+
+    // the glyph is drawn in a 8x8 square at the center of the button
+    let (gx, gy) = ((x + w / 2) as f32 - 4., (y + h / 2) as f32 - 4.);
+    match button {
+        UIButton::Close => {
+            canvas.draw_line((gx, gy), (gx + 8., gy + 8.), 1.5, glyph);
+            canvas.draw_line((gx + 8., gy), (gx, gy + 8.), 1.5, glyph);
+        }
+        UIButton::Maximize if restore => {
+            // a window in front of another one
+            canvas.draw_line((gx, gy + 2.5), (gx + 5.5, gy + 2.5), 1.5, glyph);
+            canvas.draw_line((gx + 5.5, gy + 2.5), (gx + 5.5, gy + 8.), 1.5, glyph);
+            canvas.draw_line((gx + 5.5, gy + 8.), (gx, gy + 8.), 1.5, glyph);
+            canvas.draw_line((gx, gy + 8.), (gx, gy + 2.5), 1.5, glyph);
+            canvas.draw_line((gx + 2.5, gy + 2.5), (gx + 2.5, gy), 1.5, glyph);
+            canvas.draw_line((gx + 2.5, gy), (gx + 8., gy), 1.5, glyph);
+            canvas.draw_line((gx + 8., gy), (gx + 8., gy + 5.5), 1.5, glyph);
+            canvas.draw_line((gx + 8., gy + 5.5), (gx + 5.5, gy + 5.5), 1.5, glyph);
+        }
+        UIButton::Maximize => {
+            canvas.draw_line((gx, gy), (gx + 8., gy), 1.5, glyph);
+            canvas.draw_line((gx + 8., gy), (gx + 8., gy + 8.), 1.5, glyph);
+            canvas.draw_line((gx + 8., gy + 8.), (gx, gy + 8.), 1.5, glyph);
+            canvas.draw_line((gx, gy + 8.), (gx, gy), 1.5, glyph);
+        }
+        UIButton::Minimize => {
+            canvas.draw_line((gx, gy + 8.), (gx + 8., gy + 8.), 1.5, glyph);
+        }
+        UIButton::Pin => {
+            // an arrow pointing up to a bar
+            canvas.draw_line((gx, gy), (gx + 8., gy), 1.5, glyph);
+            canvas.draw_line((gx + 4., gy + 8.), (gx + 4., gy + 3.), 1.5, glyph);
+            canvas.draw_line((gx + 1.5, gy + 5.5), (gx + 4., gy + 3.), 1.5, glyph);
+            canvas.draw_line((gx + 6.5, gy + 5.5), (gx + 4., gy + 3.), 1.5, glyph);
         }
     }
+}
 
-    // draw the red close button
-    if w >= 24 {
-        let button_color = if let Location::Button(UIButton::Close) = ptr_location {
-            RED_BUTTON_HOVER
-        } else {
-            RED_BUTTON_REGULAR
-        };
-        for xx in (w + ds - 24)..(w + ds) {
-            for yy in ds..(ds + 16) {
-                canvas.put_pixel(xx, yy, button_color);
-            }
+/// Text of the tooltip of a button
+pub(crate) fn tooltip_label(button: UIButton, maximized: bool) -> &'static str {
+    match button {
+        UIButton::Minimize => "Minimize",
+        UIButton::Maximize if maximized => "Restore",
+        UIButton::Maximize => "Maximize",
+        UIButton::Close => "Close",
+        UIButton::Pin => "Keep above",
+    }
+}
+
+/// Size of the tooltip showing given text
+pub(crate) fn tooltip_size(label: &str) -> (i32, i32) {
+    let advance = (GLYPH_WIDTH + 1) * GLYPH_SCALE;
+    (
+        label.len() as i32 * advance - GLYPH_SCALE + 2 * TOOLTIP_PADDING,
+        GLYPH_HEIGHT * GLYPH_SCALE + 2 * TOOLTIP_PADDING,
+    )
+}
+
+/// Position of the tooltip of a button relative to the decorations, just below the button
+///
+/// The tooltip is kept within the width of the window when possible.
+pub(crate) fn tooltip_position(meta: &FrameMetadata, button: UIButton, size: (i32, i32))
+                               -> Option<(i32, i32)> {
+    let (full_w, _) = meta.config.add_borders(meta.dimensions.0, meta.dimensions.1);
+    let positions = titlebar_positions(meta.dimensions.0, &meta.config, &meta.widgets);
+    let found = buttons(&positions).find(|&(b, _)| b == button);
+    found.map(|(_, bx)| {
+            let x = ::std::cmp::max(::std::cmp::min(bx, full_w - size.0), 0);
+            (x, DECORATION_SIZE + BUTTON_HEIGHT + DECORATION_SIZE / 2)
+        })
+}
+
+/// Position of the resize overlay relative to the decorations, in the bottom-right corner
+/// of the contents
+pub(crate) fn resize_overlay_position(meta: &FrameMetadata, (w, h): (i32, i32)) -> (i32, i32) {
+    let (left, top) = METRICS.contents_offset(&meta.config);
+    let (contents_w, contents_h) = meta.dimensions;
+    (
+        (left + contents_w - w - DECORATION_SIZE).max(0),
+        (top + contents_h - h - DECORATION_SIZE).max(0),
+    )
+}
+
+/// Draw a tooltip showing given text, the canvas having the size given by `tooltip_size`
+pub(crate) fn draw_tooltip(canvas: &mut [u8], label: &str) {
+    let (w, h) = tooltip_size(label);
+    let mut canvas = Canvas::new(w as u32, h as u32, canvas);
+    canvas.write_rect(0, 0, w as u32, h as u32, TOOLTIP_BACKGROUND);
+    draw_text(&mut canvas, (TOOLTIP_PADDING, TOOLTIP_PADDING), label, TOOLTIP_TEXT);
+}
+
+/// Size of the client-side window menu listing given items
+pub(crate) fn menu_size(labels: &[&str]) -> (i32, i32) {
+    let width = labels.iter().map(|label| tooltip_size(label).0).max().unwrap_or(0);
+    (width, labels.len() as i32 * MENU_ITEM_HEIGHT)
+}
+
+/// Index of the item of the window menu at given height
+pub(crate) fn menu_item_at(y: f64, count: usize) -> Option<usize> {
+    if y < 0. {
+        return None;
+    }
+    let item = (y / MENU_ITEM_HEIGHT as f64) as usize;
+    if item < count {
+        Some(item)
+    } else {
+        None
+    }
+}
+
+/// Draw the window menu, the canvas having the size given by `menu_size`
+///
+/// `enabled` tells which items can be chosen, the others are greyed out.
+pub(crate) fn draw_menu(canvas: &mut [u8], labels: &[&str], enabled: &[bool], hovered: Option<usize>) {
+    let (w, h) = menu_size(labels);
+    let mut canvas = Canvas::new(w as u32, h as u32, canvas);
+    canvas.write_rect(0, 0, w as u32, h as u32, TOOLTIP_BACKGROUND);
+    for (i, (label, &enabled)) in labels.iter().zip(enabled).enumerate() {
+        let y = i as i32 * MENU_ITEM_HEIGHT;
+        if enabled && hovered == Some(i) {
+            canvas.write_rect(0, y as u32, w as u32, MENU_ITEM_HEIGHT as u32, MENU_HOVER);
         }
+        let color = if enabled { TOOLTIP_TEXT } else { MENU_DISABLED_TEXT };
+        draw_text(&mut canvas, (TOOLTIP_PADDING, y + TOOLTIP_PADDING), label, color);
     }
+}
 
-    // draw the yellow maximize button
-    if w >= 56 {
-        let button_color = if maximizable {
-            if let Location::Button(UIButton::Maximize) = ptr_location {
-                YELLOW_BUTTON_HOVER
-            } else {
-                YELLOW_BUTTON_REGULAR
-            }
-        } else {
-            YELLOW_BUTTON_DISABLED
-        };
-        for xx in (w + ds - 56)..(w + ds - 32) {
-            for yy in ds..(ds + 16) {
-                canvas.put_pixel(xx, yy, button_color);
+/// Draw a line of text with the tooltip font, its top-left corner at `(x, y)`
+fn draw_text(canvas: &mut Canvas, (x, y): (i32, i32), label: &str, color: [u8; 4]) {
+    let scale = GLYPH_SCALE as u32;
+    for (i, c) in label.chars().enumerate() {
+        let gx = (x + i as i32 * (GLYPH_WIDTH + 1) * GLYPH_SCALE) as u32;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH as u32 {
+                if bits & (1 << (GLYPH_WIDTH as u32 - 1 - col)) != 0 {
+                    let gy = y as u32 + row as u32 * scale;
+                    canvas.write_rect(gx + col * scale, gy, scale, scale, color);
+                }
             }
         }
     }
+}
+
+/// Rows of a glyph of the tooltip font, the leftmost pixel being the highest bit
+///
+/// Only the letters of the tooltips and of the window menu, and the characters of the
+/// resize overlay are provided.
+fn glyph(c: char) -> [u8; 7] {
+    match c {
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'a' => [0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111],
+        'b' => [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110],
+        'e' => [0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110],
+        'i' => [0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'l' => [0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'm' => [0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10101, 0b10101],
+        'n' => [0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001],
+        'o' => [0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110],
+        'p' => [0b00000, 0b00000, 0b11110, 0b10001, 0b11110, 0b10000, 0b10000],
+        'r' => [0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000],
+        's' => [0b00000, 0b00000, 0b01111, 0b10000, 0b01110, 0b00001, 0b11110],
+        't' => [0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110],
+        'v' => [0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'x' => [0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001],
+        'z' => [0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111],
+        _ => [0; 7],
+    }
+}
 
-    // draw the green minimize button
-    if w >= 88 {
-        let button_color = if let Location::Button(UIButton::Minimize) = ptr_location {
-            GREEN_BUTTON_HOVER
+/// Most rectangles kept by a `Damage`, beyond which they are merged into one
+const MAX_DAMAGE_RECTS: usize = 8;
+
+/// The rectangles of a canvas changed by drawing calls, as `(x, y, width, height)`
+///
+/// They are kept inline, recording them does not allocate.
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct Damage {
+    rects: [(i32, i32, i32, i32); MAX_DAMAGE_RECTS],
+    len: usize,
+}
+
+impl Damage {
+    pub(crate) fn rects(&self) -> &[(i32, i32, i32, i32)] {
+        &self.rects[..self.len]
+    }
+
+    /// Record a rectangle, unless it lies in one already recorded
+    fn add(&mut self, (x, y, w, h): (u32, u32, u32, u32)) {
+        let rect = (x as i32, y as i32, w as i32, h as i32);
+        if w == 0 || h == 0 || self.rects().iter().any(|&r| contains(r, rect)) {
+            return;
+        }
+        // forget the rectangles lying in the new one
+        let previous = self.rects;
+        let kept = previous[..self.len].iter().filter(|&&r| !contains(rect, r));
+        self.len = 0;
+        for &r in kept {
+            self.rects[self.len] = r;
+            self.len += 1;
+        }
+        if self.len < MAX_DAMAGE_RECTS {
+            self.rects[self.len] = rect;
+            self.len += 1;
         } else {
-            GREEN_BUTTON_REGULAR
-        };
-        for xx in (w + ds - 88)..(w + ds - 64) {
-            for yy in ds..(ds + 16) {
-                canvas.put_pixel(xx, yy, button_color);
-            }
+            let bounds = self.rects().iter().fold(rect, |(x0, y0, w0, h0), &(x1, y1, w1, h1)| {
+                let (left, top) = (x0.min(x1), y0.min(y1));
+                let (right, bottom) = ((x0 + w0).max(x1 + w1), (y0 + h0).max(y1 + h1));
+                (left, top, right - left, bottom - top)
+            });
+            self.rects[0] = bounds;
+            self.len = 1;
         }
     }
 }
 
+/// Whether the rectangle `outer` contains `inner`
+fn contains((x0, y0, w0, h0): (i32, i32, i32, i32), (x1, y1, w1, h1): (i32, i32, i32, i32)) -> bool {
+    x0 <= x1 && y0 <= y1 && x1 + w1 <= x0 + w0 && y1 + h1 <= y0 + h0
+}
+
+/// A drawing surface over a raw ARGB8888 buffer
+///
+/// Colors are premultiplied native-endian pixels, as expected by wl_shm. Drawing
+/// outside of the canvas is silently clipped. The drawn rectangles are recorded,
+/// so that only they are damaged.
 struct Canvas<'a> {
     width: u32,
-    contents: &'a mut [u8]
+    height: u32,
+    contents: &'a mut [u8],
+    damage: Damage,
 }
 
 impl<'a> Canvas<'a> {
     fn new(width: u32, height: u32, contents: &mut[u8]) -> Canvas {
         debug_assert!(contents.len() == (width*height*4) as usize);
-        Canvas { width, contents }
+        Canvas { width, height, contents, damage: Damage::default() }
+    }
+
+    /// Record the part of a rectangle lying in the canvas as drawn
+    fn add_damage(&mut self, x: u32, y: u32, x1: u32, y1: u32) {
+        let (x1, y1) = (x1.min(self.width), y1.min(self.height));
+        if x < x1 && y < y1 {
+            self.damage.add((x, y, x1 - x, y1 - y));
+        }
     }
 
     #[inline]
     fn put_pixel(&mut self, x: u32, y: u32, val: [u8; 4]) {
         let idx = ((y*self.width + x)*4) as usize;
-        self.contents[idx + 0] = val[0];
+        self.contents[idx] = val[0];
         self.contents[idx + 1] = val[1];
         self.contents[idx + 2] = val[2];
         self.contents[idx + 3] = val[3];
     }
-}
\ No newline at end of file
+
+    /// Blend a color over a pixel, `coverage` being the fraction of the pixel
+    /// covered by the shape being drawn
+    #[inline]
+    fn blend_pixel(&mut self, x: u32, y: u32, val: [u8; 4], coverage: f32) {
+        if x >= self.width || y >= self.height || coverage <= 0. {
+            return;
+        }
+        if coverage >= 1. && val[ALPHA] == 0xFF {
+            return self.put_pixel(x, y, val);
+        }
+        let coverage = coverage.min(1.);
+        let idx = ((y * self.width + x) * 4) as usize;
+        let remaining = 1. - (val[ALPHA] as f32 / 255.) * coverage;
+        for (dst, &src) in self.contents[idx..idx + 4].iter_mut().zip(val.iter()) {
+            *dst = (src as f32 * coverage + *dst as f32 * remaining + 0.5) as u8;
+        }
+    }
+
+    /// Fill a rectangle with given color
+    fn fill_rect(&mut self, x: u32, y: u32, w: u32, h: u32, val: [u8; 4]) {
+        if val[ALPHA] == 0xFF {
+            return self.write_rect(x, y, w, h, val);
+        }
+        self.add_damage(x, y, x + w, y + h);
+        for yy in y..(y + h) {
+            for xx in x..(x + w) {
+                self.blend_pixel(xx, yy, val, 1.);
+            }
+        }
+    }
+
+    /// Overwrite a rectangle with given color, without blending
+    ///
+    /// The color is written in the first row, which is then copied
+    /// over the following ones.
+    fn write_rect(&mut self, x: u32, y: u32, w: u32, h: u32, val: [u8; 4]) {
+        // clip the rectangle to the canvas
+        let (x1, y1) = ((x + w).min(self.width), (y + h).min(self.height));
+        if x >= x1 || y >= y1 {
+            return;
+        }
+        self.add_damage(x, y, x1, y1);
+        let stride = (self.width * 4) as usize;
+        let first = (y * self.width + x) as usize * 4;
+        let row_len = (x1 - x) as usize * 4;
+        for pixel in self.contents[first..first + row_len].chunks_mut(4) {
+            pixel.copy_from_slice(&val);
+        }
+        for row in 1..(y1 - y) as usize {
+            self.contents
+                .copy_within(first..first + row_len, first + row * stride);
+        }
+    }
+
+    /// Copy the pixels of a rectangle lying in the canvas, row after row
+    fn read_rect(&self, (x, y, w, h): (u32, u32, u32, u32), out: &mut Vec<u8>) {
+        out.clear();
+        for row in y..(y + h) {
+            let start = ((row * self.width + x) * 4) as usize;
+            out.extend_from_slice(&self.contents[start..start + (w * 4) as usize]);
+        }
+    }
+
+    /// Overwrite a rectangle lying in the canvas with pixels given row after row
+    fn copy_rect(&mut self, (x, y, w, h): (u32, u32, u32, u32), pixels: &[u8]) {
+        self.add_damage(x, y, x + w, y + h);
+        for (row, line) in pixels.chunks((w * 4) as usize).enumerate() {
+            let start = (((y + row as u32) * self.width + x) * 4) as usize;
+            self.contents[start..start + line.len()].copy_from_slice(line);
+        }
+    }
+
+    /// Fill a rectangle with rounded corners of given radius
+    fn fill_rounded_rect(&mut self, x: u32, y: u32, w: u32, h: u32, radius: u32, val: [u8; 4]) {
+        let r = radius.min(w / 2).min(h / 2);
+        self.fill_rect(x + r, y, w - 2 * r, h, val);
+        self.fill_rect(x, y + r, r, h - 2 * r, val);
+        self.fill_rect(x + w - r, y + r, r, h - 2 * r, val);
+        let (rf, (x0, y0), (x1, y1)) = (r as f32, (x + r, y + r), (x + w - r, y + h - r));
+        self.fill_circle((x0 as f32, y0 as f32), rf, (x, y, r, r), val);
+        self.fill_circle((x1 as f32, y0 as f32), rf, (x1, y, r, r), val);
+        self.fill_circle((x0 as f32, y1 as f32), rf, (x, y1, r, r), val);
+        self.fill_circle((x1 as f32, y1 as f32), rf, (x1, y1, r, r), val);
+    }
+
+    /// Fill the part of a disk of given center and radius lying in the `(x, y, w, h)`
+    /// clipping rectangle
+    fn fill_circle(&mut self, (cx, cy): (f32, f32), radius: f32, clip: (u32, u32, u32, u32),
+                   val: [u8; 4]) {
+        let (x0, y0) = (
+            (cx - radius).floor().max(clip.0 as f32) as u32,
+            (cy - radius).floor().max(clip.1 as f32) as u32,
+        );
+        let (x1, y1) = (
+            ((cx + radius).ceil() as u32).min(clip.0 + clip.2),
+            ((cy + radius).ceil() as u32).min(clip.1 + clip.3),
+        );
+        self.add_damage(x0, y0, x1, y1);
+        for yy in y0..y1 {
+            for xx in x0..x1 {
+                let dist = distance((xx as f32 + 0.5, yy as f32 + 0.5), (cx, cy));
+                self.blend_pixel(xx, yy, val, radius - dist + 0.5);
+            }
+        }
+    }
+
+    /// Draw a line of given width between two points
+    fn draw_line(&mut self, from: (f32, f32), to: (f32, f32), width: f32, val: [u8; 4]) {
+        let half = width / 2.;
+        let x0 = (from.0.min(to.0) - half).floor().max(0.) as u32;
+        let y0 = (from.1.min(to.1) - half).floor().max(0.) as u32;
+        let x1 = (from.0.max(to.0) + half).ceil().max(0.) as u32;
+        let y1 = (from.1.max(to.1) + half).ceil().max(0.) as u32;
+        self.add_damage(x0, y0, x1, y1);
+        for yy in y0..y1 {
+            for xx in x0..x1 {
+                let dist = segment_distance((xx as f32 + 0.5, yy as f32 + 0.5), from, to);
+                self.blend_pixel(xx, yy, val, half - dist + 0.5);
+            }
+        }
+    }
+}
+
+/// Linear interpolation between two colors, `t` going from 0 (`from`) to 1 (`to`)
+fn mix(from: [u8; 4], to: [u8; 4], t: f32) -> [u8; 4] {
+    let mut mixed = from;
+    for (m, &target) in mixed.iter_mut().zip(to.iter()) {
+        *m = (*m as f32 + (target as f32 - *m as f32) * t + 0.5) as u8;
+    }
+    mixed
+}
+
+fn distance((x0, y0): (f32, f32), (x1, y1): (f32, f32)) -> f32 {
+    ((x1 - x0) * (x1 - x0) + (y1 - y0) * (y1 - y0)).sqrt()
+}
+
+/// Distance between a point and the segment `[from, to]`
+fn segment_distance(point: (f32, f32), from: (f32, f32), to: (f32, f32)) -> f32 {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let len2 = dx * dx + dy * dy;
+    if len2 == 0. {
+        return distance(point, from);
+    }
+    let t = (((point.0 - from.0) * dx + (point.1 - from.1) * dy) / len2).clamp(0., 1.);
+    distance(point, (from.0 + t * dx, from.1 + t * dy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::{decorated, draw, pixel};
+
+    #[test]
+    fn borders_are_opaque() {
+        let meta = decorated((100, 100));
+        let drawn = draw(&meta);
+        assert_eq!(pixel(&drawn, (2, 60)), ACTIVE_BORDER);
+        assert_eq!(pixel(&drawn, (60, 2)), ACTIVE_BORDER);
+        assert_eq!(pixel(&drawn, (60, 134)), ACTIVE_BORDER);
+    }
+
+    #[test]
+    fn contents_are_transparent() {
+        let meta = decorated((100, 100));
+        assert_eq!(pixel(&draw(&meta), (60, 80)), [0; 4]);
+    }
+
+    #[test]
+    fn progress_bar() {
+        let mut meta = decorated((100, 100));
+        meta.progress = Some(50);
+        let drawn = draw(&meta);
+        assert_eq!(pixel(&drawn, (20, 30)), PROGRESS_BAR);
+        assert_eq!(pixel(&drawn, (70, 30)), ACTIVE_BORDER);
+    }
+
+    #[test]
+    fn inactive_borders() {
+        let mut meta = decorated((100, 100));
+        meta.activated = false;
+        assert_eq!(pixel(&draw(&meta), (2, 60)), INACTIVE_BORDER);
+    }
+
+    #[test]
+    fn accent_color() {
+        let mut meta = decorated((100, 100));
+        meta.accent_color = [0xFF, 0xFF, 0x00, 0x00];
+        assert_eq!(pixel(&draw(&meta), (2, 60)), auto_endian!(0xFF, 0xFF, 0x00, 0x00));
+        meta.activated = false;
+        assert_eq!(pixel(&draw(&meta), (2, 60)), INACTIVE_BORDER);
+    }
+
+    #[test]
+    fn hover_damages_the_button() {
+        let previous = decorated((100, 100));
+        let (mut canvas, _) = draw(&previous);
+        let mut meta = previous;
+        meta.hover_levels[UIButton::Close as usize] = 1.;
+        let positions = titlebar_positions(100, &meta.config, &meta.widgets);
+        let (_, x) = buttons(&positions).find(|&(b, _)| b == UIButton::Close).unwrap();
+        let damage = draw_hover_changes(&mut canvas, &meta, &previous).unwrap();
+        assert_eq!(damage.rects(), &[(x, DECORATION_SIZE, BUTTON_WIDTH, BUTTON_HEIGHT)]);
+    }
+
+    #[test]
+    fn scaled_decorations() {
+        let meta = decorated((100, 100));
+        let unscaled = draw(&meta);
+        let (width, height) = (unscaled.1 as usize, unscaled.0.len() / unscaled.1 as usize / 4);
+        let mut scratch = Scratch::default();
+        let mut in_place = vec![0; width * height * 4];
+        draw_scaled(&mut in_place, width * 4, 1, &meta, &mut scratch, |_, _, _, _| {});
+        assert!(in_place == unscaled.0);
+        let mut scaled = vec![0; width * height * 16];
+        draw_scaled(&mut scaled, width * 8, 2, &meta, &mut scratch, |_, _, _, _| {});
+        let scaled = (scaled, 2 * width as i32);
+        for &(x, y) in &[(2, 60), (60, 2), (60, 80), (96, 16)] {
+            assert_eq!(pixel(&scaled, (2 * x + 1, 2 * y + 1)), pixel(&unscaled, (x, y)));
+        }
+    }
+
+    #[test]
+    fn damage_merges_on_overflow() {
+        let mut damage = Damage::default();
+        damage.add((0, 0, 4, 4));
+        damage.add((0, 0, 8, 8));
+        assert_eq!(damage.rects(), &[(0, 0, 8, 8)]);
+        for i in 1..MAX_DAMAGE_RECTS as u32 {
+            damage.add((i * 10, 0, 4, 4));
+        }
+        assert_eq!(damage.rects().len(), MAX_DAMAGE_RECTS);
+        damage.add((0, 20, 4, 4));
+        assert_eq!(damage.rects(), &[(0, 0, MAX_DAMAGE_RECTS as i32 * 10 - 6, 24)]);
+    }
+
+    #[test]
+    fn restore_glyph_when_maximized() {
+        let mut meta = decorated((100, 100));
+        let positions = titlebar_positions(100, &meta.config, &meta.widgets);
+        let (_, x) = buttons(&positions).find(|&(b, _)| b == UIButton::Maximize).unwrap();
+        // the top-left corner of the glyph is only drawn by the maximize one
+        let corner = (x + BUTTON_WIDTH / 2 - 4, DECORATION_SIZE + BUTTON_HEIGHT / 2 - 4);
+        let maximize = pixel(&draw(&meta), corner);
+        meta.maximized = true;
+        assert_ne!(pixel(&draw(&meta), corner), maximize);
+    }
+}
@@ -1,37 +1,186 @@
-use std::cell::Cell;
-use std::ops::Deref;
-use wayland_client::Proxy;
+use output::OutputScales;
+use std::cell::{Cell, RefCell};
+use std::env;
+use std::rc::Rc;
+use wayland_client::{EventQueueHandle, Proxy};
 use wayland_client::cursor::{is_available, load_theme, CursorTheme};
-use wayland_client::protocol::{wl_compositor, wl_pointer, wl_shm, wl_surface};
+use wayland_client::protocol::{wl_compositor, wl_output, wl_pointer, wl_shm, wl_surface};
+#[cfg(feature = "wayland-protocols")]
+use wayland_protocols::unstable::tablet::v2::client::zwp_tablet_tool_v2::ZwpTabletToolV2;
+
+/// Size of the cursors when neither the user nor the environment specifies one
+const DEFAULT_CURSOR_SIZE: u32 = 16;
+
+/// Device whose cursor is themed
+enum Device {
+    Pointer(wl_pointer::WlPointer),
+    #[cfg(feature = "wayland-protocols")]
+    Tool(ZwpTabletToolV2),
+}
+
+/// The cursor theme of the pointers of a frame, loaded at the scales of the outputs
+pub struct CursorThemes {
+    shm: wl_shm::WlShm,
+    name: Option<String>,
+    size: u32,
+    // the theme loaded at each scale the cursors were shown at
+    loaded: Vec<(i32, Rc<CursorTheme>)>,
+    outputs: Option<OutputScales>,
+}
+
+/// The cursor themes of a frame, replaced by `Frame::set_cursor_theme`
+pub type ThemeSlot = Rc<RefCell<CursorThemes>>;
 
 pub struct ThemedPointer {
-    pointer: wl_pointer::WlPointer,
+    device: Device,
     surface: wl_surface::WlSurface,
-    theme: CursorTheme,
+    themes: ThemeSlot,
+    // outputs the cursor is shown on, its buffer has the largest of their scales
+    outputs: Rc<RefCell<Vec<wl_output::WlOutput>>>,
+    // the cursor last set, with the theme it was taken from
+    shown: RefCell<Option<(Rc<CursorTheme>, String)>>,
     last_serial: Cell<u32>,
 }
 
-impl ThemedPointer {
-    pub fn load(pointer: wl_pointer::WlPointer, name: Option<&str>,
-                compositor: &wl_compositor::WlCompositor, shm: &wl_shm::WlShm)
-                -> Result<ThemedPointer, wl_pointer::WlPointer> {
+impl CursorThemes {
+    /// Load a cursor theme, if libwayland-cursor is available
+    ///
+    /// If no theme name and size are provided, they are read from the `XCURSOR_THEME`
+    /// and `XCURSOR_SIZE` environment variables, like the rest of the desktop does.
+    pub fn load(theme: Option<(&str, u32)>, shm: &wl_shm::WlShm) -> Option<CursorThemes> {
         if !is_available() {
-            return Err(pointer);
+            return None;
+        }
+
+        let (name, size) = match theme {
+            Some((name, size)) => (Some(name.to_owned()), size),
+            None => {
+                let name = env::var("XCURSOR_THEME").ok();
+                let size = env::var("XCURSOR_SIZE")
+                    .ok()
+                    .and_then(|size| size.parse().ok())
+                    .unwrap_or(DEFAULT_CURSOR_SIZE);
+                (name, size)
+            }
+        };
+        let mut themes = CursorThemes {
+            shm: shm.clone()?,
+            name,
+            size,
+            loaded: Vec::new(),
+            outputs: None,
+        };
+        // most cursors are shown unscaled, the other scales are loaded when needed
+        themes.get(1);
+        Some(themes)
+    }
+
+    /// The same themes, to be replaced independently
+    pub fn share(&self) -> CursorThemes {
+        CursorThemes {
+            shm: self.shm.clone().expect("The shm global cannot be destroyed."),
+            name: self.name.clone(),
+            size: self.size,
+            loaded: self.loaded.clone(),
+            outputs: self.outputs.clone(),
         }
+    }
 
-        let theme = load_theme(name, 16, shm);
+    /// Follow the scales of the outputs of a list
+    pub fn set_outputs(&mut self, outputs: OutputScales) {
+        self.outputs = Some(outputs);
+    }
+
+    /// Replace the theme by the one of given name and size
+    pub fn set_theme(&mut self, name: &str, size: u32) {
+        self.name = Some(name.to_owned());
+        self.size = size;
+        self.loaded.clear();
+        self.get(1);
+    }
+
+    /// Scale of the cursors shown on given outputs
+    fn scale(&self, outputs: &[wl_output::WlOutput]) -> i32 {
+        self.outputs
+            .as_ref()
+            .map_or(1, |scales| scales.max_scale(outputs))
+    }
+
+    /// The theme at given scale, loaded the first time it is needed
+    fn get(&mut self, scale: i32) -> Rc<CursorTheme> {
+        if let Some((_, theme)) = self.loaded.iter().find(|&&(s, _)| s == scale) {
+            return theme.clone();
+        }
+        log_debug!("loading the cursor theme {:?} at scale {}", self.name, scale);
+        let name = self.name.as_ref().map(|name| &name[..]);
+        let theme = Rc::new(load_theme(name, self.size * scale as u32, &self.shm));
+        self.loaded.push((scale, theme.clone()));
+        theme
+    }
+}
+
+impl ThemedPointer {
+    /// Theme the cursor of given pointer, the themes being shared with the other pointers
+    /// of the frame
+    pub fn new(evqh: &mut EventQueueHandle, pointer: wl_pointer::WlPointer, themes: ThemeSlot,
+               compositor: &wl_compositor::WlCompositor)
+               -> ThemedPointer {
+        ThemedPointer::with_device(evqh, Device::Pointer(pointer), themes, compositor)
+    }
+
+    /// Theme the cursor of a tablet tool
+    #[cfg(feature = "wayland-protocols")]
+    pub fn for_tool(evqh: &mut EventQueueHandle, tool: ZwpTabletToolV2, themes: ThemeSlot,
+                    compositor: &wl_compositor::WlCompositor)
+                    -> ThemedPointer {
+        ThemedPointer::with_device(evqh, Device::Tool(tool), themes, compositor)
+    }
+
+    fn with_device(evqh: &mut EventQueueHandle, device: Device, themes: ThemeSlot,
+                   compositor: &wl_compositor::WlCompositor)
+                   -> ThemedPointer {
         let surface = compositor.create_surface();
+        let outputs = Rc::new(RefCell::new(Vec::new()));
+        evqh.register(&surface, ::frame::surface_implementation(), outputs.clone());
 
-        Ok(ThemedPointer {
-            pointer: pointer,
+        ThemedPointer {
+            device,
             surface: surface,
-            theme: theme,
+            themes,
+            outputs,
+            shown: RefCell::new(None),
             last_serial: Cell::new(0),
-        })
+        }
+    }
+
+    /// The theme at the scale of the outputs the cursor is shown on, with this scale
+    fn theme(&self) -> (Rc<CursorTheme>, i32) {
+        let mut themes = self.themes.borrow_mut();
+        // the buffer scale needs version 3 of wl_surface
+        let scale = if self.surface.version() >= 3 {
+            themes.scale(&self.outputs.borrow())
+        } else {
+            1
+        };
+        (themes.get(scale), scale)
+    }
+
+    /// Set the cursor again if the theme was replaced since it was set, or if the
+    /// cursor moved to an output of another scale
+    pub fn refresh_theme(&self) {
+        let (theme, _) = self.theme();
+        let name = match *self.shown.borrow() {
+            Some((ref shown, ref name)) if !Rc::ptr_eq(shown, &theme) => name.clone(),
+            _ => return,
+        };
+        self.set_cursor(&name, None);
     }
 
     pub fn set_cursor(&self, name: &str, serial: Option<u32>) {
-        let cursor = if let Some(c) = self.theme.get_cursor(name) {
+        let (theme, scale) = self.theme();
+        // the previous theme is only dropped once its buffer is replaced
+        let _previous = self.shown.replace(Some((theme.clone(), name.to_owned())));
+        let cursor = if let Some(c) = theme.get_cursor(name) {
             c
         } else {
             return;
@@ -53,29 +202,39 @@ impl ThemedPointer {
         }
 
         self.surface.attach(Some(&buffer), 0, 0);
+        if self.surface.version() >= 3 {
+            self.surface.set_buffer_scale(scale);
+        }
         if self.surface.version() >= 4 {
             self.surface.damage_buffer(0, 0, w, h);
         } else {
             // surface is old and does not support damage_buffer, so we damage
-            // in surface coordinates and hope it is not rescaled
-            self.surface.damage(0, 0, w, h);
+            // in surface coordinates
+            self.surface.damage(0, 0, w / scale, h / scale);
         }
         self.surface.commit();
-        self.pointer
-            .set_cursor(self.last_serial.get(), Some(&self.surface), hx, hy);
-    }
-}
-
-impl Deref for ThemedPointer {
-    type Target = wl_pointer::WlPointer;
-    fn deref(&self) -> &wl_pointer::WlPointer {
-        &self.pointer
+        match self.device {
+            Device::Pointer(ref pointer) => {
+                pointer.set_cursor(self.last_serial.get(), Some(&self.surface), hx / scale, hy / scale);
+            }
+            #[cfg(feature = "wayland-protocols")]
+            Device::Tool(ref tool) => {
+                tool.set_cursor(self.last_serial.get(), Some(&self.surface), hx / scale, hy / scale);
+            }
+        }
     }
 }
 
 impl Drop for ThemedPointer {
     fn drop(&mut self) {
-        self.pointer.release();
+        match self.device {
+            Device::Pointer(ref pointer) => {
+                pointer.release();
+            }
+            // tablet tools are shared by all the frames
+            #[cfg(feature = "wayland-protocols")]
+            Device::Tool(_) => {}
+        }
         self.surface.destroy();
     }
 }
@@ -0,0 +1,60 @@
+use std::time::{Duration, Instant};
+
+/// Delay the pointer has to rest on a button before its tooltip is shown, in milliseconds
+pub(crate) const TOOLTIP_DELAY: u32 = 600;
+
+/// A time-based behavior of the decorations
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Timer {
+    /// Show the tooltip of the hovered button
+    Tooltip,
+}
+
+const TIMER_COUNT: usize = 1;
+
+/// The deadlines of the running timers of a frame
+///
+/// The timers are fired either by the frame callbacks of the animations, which poll
+/// them on each frame of the compositor, or by the application with `Frame::tick`.
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct Timers {
+    deadlines: [Option<Instant>; TIMER_COUNT],
+    // whether the application fires the timers, they are not polled then
+    pub(crate) external: bool,
+}
+
+impl Timers {
+    /// Start a timer due in `delay` milliseconds, restarting it if it was running
+    pub(crate) fn start(&mut self, timer: Timer, delay: u32) {
+        self.deadlines[timer as usize] = Some(Instant::now() + Duration::from_millis(u64::from(delay)));
+    }
+
+    pub(crate) fn cancel(&mut self, timer: Timer) {
+        self.deadlines[timer as usize] = None;
+    }
+
+    /// Whether a timer is running and needs to be polled
+    pub(crate) fn is_polled(&self) -> bool {
+        !self.external && self.deadlines.iter().any(Option::is_some)
+    }
+
+    /// Time left before the next timer is due, zero if one already is
+    pub(crate) fn next_timeout(&self, now: Instant) -> Option<Duration> {
+        self.deadlines
+            .iter()
+            .filter_map(|&deadline| deadline)
+            .min()
+            .map(|deadline| deadline.saturating_duration_since(now))
+    }
+
+    /// Stop a timer if it is due at `now`, returns whether it was
+    pub(crate) fn fire(&mut self, timer: Timer, now: Instant) -> bool {
+        match self.deadlines[timer as usize] {
+            Some(deadline) if deadline <= now => {
+                self.deadlines[timer as usize] = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
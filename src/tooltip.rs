@@ -0,0 +1,67 @@
+use UIButton;
+use frame::FrameMetadata;
+use pool::Pool;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wayland_client::EventQueueHandle;
+use wayland_client::protocol::{wl_compositor, wl_subcompositor, wl_subsurface, wl_surface};
+
+/// The tooltip of a titlebar button, shown in a subsurface of the decorations
+pub(crate) struct Tooltip {
+    pub(crate) button: UIButton,
+    surface: wl_surface::WlSurface,
+    subsurface: wl_subsurface::WlSubsurface,
+    pool: Rc<RefCell<Pool>>,
+    pool_owner: usize,
+}
+
+impl Tooltip {
+    /// Show the tooltip of a button, with the next commit of the decorations
+    ///
+    /// Returns `None` if the button is not displayed.
+    pub(crate) fn new(evqh: &mut EventQueueHandle, button: UIButton, meta: &FrameMetadata,
+                      parent: &wl_surface::WlSurface, compositor: &wl_compositor::WlCompositor,
+                      subcompositor: &wl_subcompositor::WlSubcompositor, pool: &Rc<RefCell<Pool>>)
+                      -> Option<Tooltip> {
+        let label = ::theme::tooltip_label(button, meta.maximized);
+        let (w, h) = ::theme::tooltip_size(label);
+        let (x, y) = ::theme::tooltip_position(meta, button, (w, h))?;
+
+        let surface = compositor.create_surface();
+        let subsurface = subcompositor
+            .get_subsurface(&surface, parent)
+            .expect("Provided Subcompositor was defunct");
+        subsurface.set_position(x, y);
+        // the pointer goes through the tooltip
+        let region = compositor.create_region();
+        surface.set_input_region(Some(&region));
+        region.destroy();
+
+        let pool_owner = pool.borrow_mut().new_owner();
+        {
+            let mut pool = pool.borrow_mut();
+            let buffer = pool.buffer(evqh, pool_owner, w, h, |canvas, _| {
+                ::theme::draw_tooltip(canvas, label);
+            });
+            surface.attach(Some(buffer), 0, 0);
+        }
+        surface.damage(0, 0, w, h);
+        surface.commit();
+
+        Some(Tooltip {
+            button,
+            surface,
+            subsurface,
+            pool: pool.clone(),
+            pool_owner,
+        })
+    }
+}
+
+impl Drop for Tooltip {
+    fn drop(&mut self) {
+        self.subsurface.destroy();
+        self.surface.destroy();
+        self.pool.borrow_mut().release_owner(self.pool_owner);
+    }
+}
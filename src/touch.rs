@@ -0,0 +1,74 @@
+use PointerIData;
+use pointer::BTN_LEFT;
+use wayland_client::EventQueueHandle;
+use wayland_client::protocol::{wl_surface, wl_touch};
+
+/// The touch of a frame, handling the touch points on its decorations
+///
+/// The first touch point behaves like a pointer pressing its left button where it
+/// touches: it moves or resizes the window, or clicks a button. The other ones are
+/// ignored until it is lifted.
+pub(crate) struct FrameTouch<ID> {
+    idata: PointerIData<ID>,
+    // id of the touch point interacting with the decorations, and serial of its down event
+    point: Option<(i32, u32)>,
+}
+
+impl<ID: 'static> FrameTouch<ID> {
+    pub(crate) fn new(idata: PointerIData<ID>) -> FrameTouch<ID> {
+        FrameTouch { idata, point: None }
+    }
+
+    fn down(&mut self, evqh: &mut EventQueueHandle, serial: u32, time: u32, surface: &wl_surface::WlSurface,
+            id: i32, (x, y): (f64, f64)) {
+        if self.point.is_some() || !self.idata.pstate.owns(surface) {
+            return;
+        }
+        self.point = Some((id, serial));
+        self.idata.enter(evqh, serial, surface, x, y);
+        self.idata.button(evqh, serial, time, BTN_LEFT, true);
+    }
+
+    fn motion(&mut self, evqh: &mut EventQueueHandle, id: i32, x: f64, y: f64) {
+        if let Some((point, _)) = self.point {
+            if point == id {
+                self.idata.motion(evqh, x, y);
+            }
+        }
+    }
+
+    fn up(&mut self, evqh: &mut EventQueueHandle, serial: u32, time: u32, id: i32) {
+        if let Some((point, _)) = self.point {
+            if point == id {
+                self.point = None;
+                self.idata.button(evqh, serial, time, BTN_LEFT, false);
+                self.idata.leave(evqh, serial);
+            }
+        }
+    }
+
+    /// Forget the touch point, the compositor took it for a gesture
+    fn cancel(&mut self, evqh: &mut EventQueueHandle) {
+        if let Some((_, serial)) = self.point.take() {
+            self.idata.leave(evqh, serial);
+        }
+    }
+}
+
+pub(crate) fn touch_implementation<ID: 'static>() -> wl_touch::Implementation<FrameTouch<ID>> {
+    wl_touch::Implementation {
+        down: |evqh, touch, _, serial, time, surface, id, x, y| {
+            touch.down(evqh, serial, time, surface, id, (x, y));
+        },
+        up: |evqh, touch, _, serial, time, id| {
+            touch.up(evqh, serial, time, id);
+        },
+        motion: |evqh, touch, _, _, id, x, y| {
+            touch.motion(evqh, id, x, y);
+        },
+        frame: |_, _, _| {},
+        cancel: |evqh, touch, _| {
+            touch.cancel(evqh);
+        },
+    }
+}
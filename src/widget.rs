@@ -0,0 +1,105 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+use wayland_client::EventQueueHandle;
+
+/// Maximum number of widgets in the titlebar of a frame
+pub const MAX_WIDGETS: usize = 4;
+
+/// Side of the titlebar a widget is placed on
+///
+/// Widgets are packed next to the buttons of their side, towards the center of the
+/// titlebar, in the order they were added.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WidgetSide {
+    /// After the buttons on the left of the titlebar
+    Left,
+    /// Before the buttons on the right of the titlebar
+    Right,
+}
+
+/// A widget of the titlebar, see `Frame::add_titlebar_widget`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WidgetId(pub(crate) usize);
+
+/// Error adding a widget to the titlebar, see `Frame::add_titlebar_widget`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WidgetError {
+    /// The width of the widget is not positive
+    InvalidWidth,
+    /// The titlebar already has `MAX_WIDGETS` widgets
+    TooManyWidgets,
+}
+
+impl fmt::Display for WidgetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WidgetError::InvalidWidth => f.write_str("the width of the widget is not positive"),
+            WidgetError::TooManyWidgets => f.write_str("the titlebar cannot hold more widgets"),
+        }
+    }
+}
+
+impl Error for WidgetError {}
+
+/// State of a titlebar widget when it is drawn
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WidgetState {
+    /// Whether the pointer is over the widget
+    pub hovered: bool,
+    /// Whether the window is activated, the decorations of inactive windows are dimmed
+    pub activated: bool,
+}
+
+/// For drawing and handling a widget of the titlebar
+pub struct WidgetImplementation<ID> {
+    /// Draw the widget
+    ///
+    /// `canvas` holds `width * height` native-endian ARGB8888 pixels, with premultiplied
+    /// alpha, the widget having the height of the titlebar buttons. It already contains
+    /// the titlebar, draw the widget over it.
+    pub draw: fn(idata: &mut ID, canvas: &mut [u8], width: i32, height: i32, state: WidgetState),
+    /// Called when the widget is clicked with the left button
+    pub click: fn(evqh: &mut EventQueueHandle, idata: &mut ID),
+}
+
+impl<ID> Copy for WidgetImplementation<ID> {}
+impl<ID> Clone for WidgetImplementation<ID> {
+    fn clone(&self) -> WidgetImplementation<ID> {
+        *self
+    }
+}
+
+/// A widget with its implementation data, without its type
+pub(crate) trait WidgetHandler {
+    fn draw(&mut self, canvas: &mut [u8], size: (i32, i32), state: WidgetState);
+    fn click(&mut self, evqh: &mut EventQueueHandle);
+}
+
+impl<ID> WidgetHandler for (WidgetImplementation<ID>, ID) {
+    fn draw(&mut self, canvas: &mut [u8], (width, height): (i32, i32), state: WidgetState) {
+        (self.0.draw)(&mut self.1, canvas, width, height, state);
+    }
+
+    fn click(&mut self, evqh: &mut EventQueueHandle) {
+        (self.0.click)(evqh, &mut self.1);
+    }
+}
+
+/// Side and width of the widgets of a frame, by id
+pub(crate) type WidgetSlots = [Option<(WidgetSide, i32)>; MAX_WIDGETS];
+
+// a widget with its own cell, so that its callbacks can add or remove widgets
+type SharedHandler = Rc<RefCell<dyn WidgetHandler>>;
+
+/// The widgets of a frame by id, shared with the handlers of its pointers
+pub(crate) type WidgetHandlers = Rc<RefCell<[Option<SharedHandler>; MAX_WIDGETS]>>;
+
+/// Click the widget of given id, if it still exists
+pub(crate) fn click(handlers: &WidgetHandlers, evqh: &mut EventQueueHandle, widget: WidgetId) {
+    let handler = handlers.borrow()[widget.0].clone();
+    if let Some(handler) = handler {
+        handler.borrow_mut().click(evqh);
+    }
+}
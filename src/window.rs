@@ -0,0 +1,226 @@
+use {DecorationManager, Frame, FrameEvent, WindowState};
+use shell::StateSet;
+use std::error::Error;
+use std::fmt;
+use std::sync::mpsc::Receiver;
+use wayland_client::EventQueueHandle;
+use wayland_client::protocol::wl_surface;
+
+/// An event of a window, see `Window::events`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WindowEvent {
+    /// The contents need to be drawn again, with `Window::draw`
+    Redraw,
+    /// The user asked to close the window
+    Close,
+    /// A state of the window was set or unset, see `FrameImplementation::state_changed`
+    StateChanged(WindowState, bool),
+}
+
+/// Error creating a `Window`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WindowError {
+    /// The compositor or a global of the decoration manager is no longer alive
+    Defunct,
+}
+
+impl fmt::Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WindowError::Defunct => f.write_str("the globals of the decoration manager are defunct"),
+        }
+    }
+}
+
+impl Error for WindowError {}
+
+/// The events of a window received since the last call to `Window::events`
+pub struct WindowEvents {
+    events: ::std::vec::IntoIter<WindowEvent>,
+}
+
+impl Iterator for WindowEvents {
+    type Item = WindowEvent;
+
+    fn next(&mut self) -> Option<WindowEvent> {
+        self.events.next()
+    }
+}
+
+/// A surface destroyed once its frame is
+struct OwnedSurface(wl_surface::WlSurface);
+
+impl Drop for OwnedSurface {
+    fn drop(&mut self) {
+        self.0.destroy();
+    }
+}
+
+/// A decorated window, owning its surface and its frame
+///
+/// This handles the events of the frame the way most applications do: the last size
+/// suggested by the configures is kept until the contents are drawn, the frame is
+/// refreshed when it asks for it, and the contents are committed together with the
+/// decorations. The application only reacts to the `WindowEvent`s, and draws its
+/// contents in `draw`.
+///
+/// ```ignore
+/// use wayland_window::{Window, WindowEvent};
+/// let mut window = Window::new(&mut event_queue, &manager, 200, 150, |frame| {
+///     frame.set_title("My window".into());
+///     frame.set_decorate(true);
+/// }).unwrap();
+///
+/// loop {
+///     display.flush().unwrap();
+///     event_queue.dispatch().unwrap();
+///     for event in window.events(&mut event_queue) {
+///         match event {
+///             WindowEvent::Redraw => window.draw(&mut event_queue, |surface, width, height| {
+///                 // attach a buffer of this size to the surface, and damage it
+///             }),
+///             WindowEvent::Close => return,
+///             WindowEvent::StateChanged(..) => {}
+///         }
+///     }
+/// }
+/// ```
+pub struct Window {
+    // declared first, the frame must be dropped before the surface is destroyed
+    frame: Frame,
+    surface: OwnedSurface,
+    events: Receiver<FrameEvent>,
+    size: (i32, i32),
+    // the size suggested by the last configure, applied by the next draw
+    pending_size: Option<(i32, i32)>,
+    states: StateSet,
+    // whether the contents were not drawn since the last redraw reported
+    redraw: bool,
+    closed: bool,
+}
+
+impl Window {
+    /// Create a window from a decoration manager, with contents of given size
+    ///
+    /// The surface of the window is created from the compositor of the manager, and
+    /// `setup` is applied to the frame before its initial commit, to set its title for
+    /// example. The first call to `events` asks for the initial contents.
+    ///
+    /// Fails with `WindowError::Defunct` if the globals given to the manager are no
+    /// longer alive.
+    pub fn new<F>(evqh: &mut EventQueueHandle, manager: &DecorationManager, width: i32, height: i32,
+                  setup: F)
+                  -> Result<Window, WindowError>
+    where
+        F: FnOnce(&mut Frame),
+    {
+        let surface = OwnedSurface(manager.compositor().create_surface());
+        let (sender, events) = ::channel::channel();
+        let frame = manager.create_frame_with(
+            evqh,
+            ::channel::channel_implementation(),
+            sender,
+            (&surface.0, None),
+            (width, height),
+            setup,
+        ).map_err(|_| WindowError::Defunct)?;
+        Ok(Window {
+            frame,
+            surface,
+            events,
+            size: (width.max(1), height.max(1)),
+            pending_size: None,
+            states: StateSet::default(),
+            redraw: true,
+            closed: false,
+        })
+    }
+
+    /// Handle the events of the frame, and return those the application must react to
+    ///
+    /// Call this after each dispatch of the event queue. The frame is refreshed if it
+    /// needs to, and a single `Redraw` is reported for all the configures received.
+    pub fn events(&mut self, evqh: &mut EventQueueHandle) -> WindowEvents {
+        let mut events = Vec::new();
+        let mut refresh = false;
+        for event in self.events.try_iter() {
+            match event {
+                FrameEvent::Configure(_, newsize) => {
+                    if let Some((w, h)) = newsize {
+                        self.pending_size = Some((w.max(1), h.max(1)));
+                    }
+                    self.redraw = true;
+                }
+                FrameEvent::Refresh => refresh = true,
+                FrameEvent::Close => {
+                    self.closed = true;
+                    events.push(WindowEvent::Close);
+                }
+                FrameEvent::StateChanged(state, active) => {
+                    self.states.set(state, active);
+                    events.push(WindowEvent::StateChanged(state, active));
+                }
+            }
+        }
+        if refresh {
+            self.frame.refresh(evqh);
+        }
+        if ::std::mem::replace(&mut self.redraw, false) {
+            events.push(WindowEvent::Redraw);
+        }
+        WindowEvents {
+            events: events.into_iter(),
+        }
+    }
+
+    /// Draw the contents of the window
+    ///
+    /// `draw` attaches a buffer of the given size to the surface of the window, and
+    /// damages it. The window then commits it with the decorations: if the size changed,
+    /// they are resized at once like with `Frame::resize_synced`.
+    pub fn draw<F>(&mut self, evqh: &mut EventQueueHandle, draw: F)
+    where
+        F: FnOnce(&wl_surface::WlSurface, i32, i32),
+    {
+        match self.pending_size.take() {
+            Some((w, h)) if (w, h) != self.size => {
+                self.size = (w, h);
+                self.frame.resize_synced(evqh, w, h, |surface| draw(surface, w, h));
+            }
+            _ => {
+                draw(&self.surface.0, self.size.0, self.size.1);
+                self.frame.commit(evqh);
+            }
+        }
+    }
+
+    /// Size of the contents, as last drawn
+    pub fn size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    /// Whether the window has a state, as given by the last configure
+    pub fn has_state(&self, state: WindowState) -> bool {
+        self.states.contains(state)
+    }
+
+    /// Whether the user asked to close the window
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// The surface of the contents
+    pub fn surface(&self) -> &wl_surface::WlSurface {
+        &self.surface.0
+    }
+
+    /// The frame of the window
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /// The frame of the window, to change its title or decorations for example
+    pub fn frame_mut(&mut self) -> &mut Frame {
+        &mut self.frame
+    }
+}
@@ -0,0 +1,200 @@
+use frame::FrameMetadata;
+use pool::Pool;
+use popup::{Popup, PopupImplementation, PopupPosition};
+use protocols::xdg_shell::client::xdg_positioner::Anchor;
+use protocols::xdg_shell::client::xdg_surface::XdgSurface;
+use protocols::xdg_shell::client::xdg_wm_base::XdgWmBase;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wayland_client::{EventQueueHandle, Proxy};
+use wayland_client::protocol::{wl_compositor, wl_seat, wl_surface};
+
+/// An entry of the window menu
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum MenuItem {
+    Move,
+    Resize,
+    Minimize,
+    Maximize,
+    Close,
+}
+
+impl MenuItem {
+    fn label(&self, maximized: bool) -> &'static str {
+        match *self {
+            MenuItem::Move => "Move",
+            MenuItem::Resize => "Resize",
+            MenuItem::Minimize => "Minimize",
+            MenuItem::Maximize if maximized => "Restore",
+            MenuItem::Maximize => "Maximize",
+            MenuItem::Close => "Close",
+        }
+    }
+
+    /// Whether the item can be chosen, the others are greyed out
+    fn enabled(&self, meta: &FrameMetadata) -> bool {
+        match *self {
+            MenuItem::Move | MenuItem::Close => true,
+            MenuItem::Resize => !meta.maximized && !meta.fullscreen && !meta.is_shaded(),
+            MenuItem::Minimize => meta.capabilities.minimize,
+            MenuItem::Maximize => meta.max_size.is_none() && meta.capabilities.maximize,
+        }
+    }
+}
+
+const ITEMS: [MenuItem; 5] = [
+    MenuItem::Move,
+    MenuItem::Resize,
+    MenuItem::Minimize,
+    MenuItem::Maximize,
+    MenuItem::Close,
+];
+
+/// What the frames need to show their own window menu
+///
+/// Only available with the stable xdg_shell, which provides the popups.
+pub(crate) struct MenuContext {
+    pub(crate) wm_base: XdgWmBase,
+    pub(crate) compositor: wl_compositor::WlCompositor,
+    pub(crate) pool: Rc<RefCell<Pool>>,
+}
+
+struct MenuState {
+    surface: wl_surface::WlSurface,
+    pool: Rc<RefCell<Pool>>,
+    pool_owner: usize,
+    labels: Vec<&'static str>,
+    enabled: Vec<bool>,
+    hovered: Option<usize>,
+    // size given by the compositor, the menu is drawn once it is known
+    size: Option<(i32, i32)>,
+    // the compositor dismissed the menu
+    done: bool,
+}
+
+impl MenuState {
+    fn draw(&mut self, evqh: &mut EventQueueHandle) {
+        let (w, h) = match self.size {
+            Some(size) => size,
+            None => return,
+        };
+        {
+            let labels = &self.labels;
+            let enabled = &self.enabled;
+            let hovered = self.hovered;
+            let mut pool = self.pool.borrow_mut();
+            let buffer = pool.buffer(evqh, self.pool_owner, w, h, |canvas, _| {
+                ::theme::draw_menu(canvas, labels, enabled, hovered);
+            });
+            self.surface.attach(Some(buffer), 0, 0);
+        }
+        self.surface.damage(0, 0, w, h);
+        self.surface.commit();
+    }
+}
+
+/// A window menu drawn by the frame, for compositors not providing their own
+///
+/// It is shown in a popup grabbing the pointer, and dismissed by dropping it.
+pub(crate) struct WindowMenu {
+    popup: Option<Popup>,
+    state: Rc<RefCell<MenuState>>,
+}
+
+impl WindowMenu {
+    /// Show the menu at the given position of the window geometry of `parent`
+    ///
+    /// The menu grabs the pointer of `seat`, `serial` must be the one of the click
+    /// opening it.
+    pub(crate) fn new(evqh: &mut EventQueueHandle, context: &MenuContext, parent: &XdgSurface,
+                      meta: &FrameMetadata, (x, y): (i32, i32), seat: &wl_seat::WlSeat, serial: u32)
+                      -> Option<WindowMenu> {
+        let labels = ITEMS
+            .iter()
+            .map(|item| item.label(meta.maximized))
+            .collect::<Vec<_>>();
+        let enabled = ITEMS.iter().map(|item| item.enabled(meta)).collect();
+        let size = ::theme::menu_size(&labels);
+
+        let surface = context.compositor.create_surface();
+        let state = Rc::new(RefCell::new(MenuState {
+            surface: surface.clone().unwrap(),
+            pool: context.pool.clone(),
+            pool_owner: context.pool.borrow_mut().new_owner(),
+            labels,
+            enabled,
+            hovered: None,
+            size: None,
+            done: false,
+        }));
+        let mut position = PopupPosition::new(size, (x, y, 1, 1));
+        position.anchor = Anchor::TopLeft;
+        let popup = Popup::create(
+            evqh,
+            &context.wm_base,
+            (parent, (0, 0)),
+            &surface,
+            &position,
+            menu_implementation(),
+            state.clone(),
+        ).ok()?;
+        popup.grab(seat, serial);
+        Some(WindowMenu {
+            popup: Some(popup),
+            state,
+        })
+    }
+
+    /// Whether this is the surface of the menu
+    pub(crate) fn owns(&self, surface: &wl_surface::WlSurface) -> bool {
+        self.state.borrow().surface.equals(surface)
+    }
+
+    /// Whether the compositor dismissed the menu, which should then be dropped
+    pub(crate) fn is_done(&self) -> bool {
+        self.state.borrow().done
+    }
+
+    /// Highlight the item under the pointer, `None` if it left the menu
+    pub(crate) fn hover(&self, evqh: &mut EventQueueHandle, y: Option<f64>) {
+        let mut state = self.state.borrow_mut();
+        let hovered = y.and_then(|y| ::theme::menu_item_at(y, ITEMS.len()));
+        if hovered != state.hovered {
+            state.hovered = hovered;
+            state.draw(evqh);
+        }
+    }
+
+    /// The enabled item under the pointer, if any
+    pub(crate) fn hovered_item(&self) -> Option<MenuItem> {
+        let state = self.state.borrow();
+        state
+            .hovered
+            .filter(|&i| state.enabled[i])
+            .map(|i| ITEMS[i])
+    }
+}
+
+impl Drop for WindowMenu {
+    fn drop(&mut self) {
+        // the popup must be destroyed before its surface
+        self.popup.take();
+        let state = self.state.borrow();
+        state.surface.destroy();
+        state.pool.borrow_mut().release_owner(state.pool_owner);
+    }
+}
+
+fn menu_implementation() -> PopupImplementation<Rc<RefCell<MenuState>>> {
+    PopupImplementation {
+        configure: |evqh, state, _, _, _, _| {
+            let mut state = state.borrow_mut();
+            // the size of the positioner is kept, the menu is not constrained in size
+            state.size = Some(::theme::menu_size(&state.labels));
+            state.draw(evqh);
+        },
+        done: |_, state| {
+            state.borrow_mut().done = true;
+        },
+    }
+}